@@ -31,6 +31,11 @@ class UpdateBlockedAppsArgs {
     var packages: List<String> = emptyList()
 }
 
+@InvokeArg
+class SetSafeSearchArgs {
+    var rewrites: Map<String, String> = emptyMap()
+}
+
 @InvokeArg
 class SaveLockExpiryArgs {
     var expiry: String = ""
@@ -51,6 +56,16 @@ class BlockerPlugin(private val activity: Activity) : Plugin(activity) {
             // Save domains to SharedPreferences
             BlocklistManager.saveDomains(activity, domains)
 
+            // A running VPN only reads its lists at startup, so ask it to reload
+            if (DnsVpnService.isRunning) {
+                val intent = Intent(activity, DnsVpnService::class.java).apply {
+                    action = DnsVpnService.ACTION_RELOAD
+                }
+                activity.startService(intent)
+                invoke.resolve()
+                return
+            }
+
             // Check if VPN permission is needed
             val prepareIntent = VpnService.prepare(activity)
             if (prepareIntent != null) {
@@ -116,6 +131,28 @@ class BlockerPlugin(private val activity: Activity) : Plugin(activity) {
         invoke.resolve(result)
     }
 
+    // ─── SafeSearch ─────────────────────────────────────────────────────
+
+    @Command
+    fun setSafeSearch(invoke: Invoke) {
+        try {
+            val args = invoke.parseArgs(SetSafeSearchArgs::class.java)
+            BlocklistManager.saveSafeSearchRewrites(activity, args.rewrites)
+
+            // A running VPN only reads its lists at startup, so ask it to reload
+            if (DnsVpnService.isRunning) {
+                val intent = Intent(activity, DnsVpnService::class.java).apply {
+                    action = DnsVpnService.ACTION_RELOAD
+                }
+                activity.startService(intent)
+            }
+            Log.i(TAG, "SafeSearch set with ${args.rewrites.size} rewritten domains")
+            invoke.resolve()
+        } catch (e: Exception) {
+            invoke.reject(e.message ?: "Failed to set SafeSearch")
+        }
+    }
+
     // ─── App Blocking ───────────────────────────────────────────────────
 
     @Command
@@ -3,12 +3,14 @@ package com.tamashii.app.blocker
 import android.content.Context
 import android.content.SharedPreferences
 import org.json.JSONArray
+import org.json.JSONObject
 
 object BlocklistManager {
     private const val PREFS_NAME = "tamashii_blocker"
     private const val KEY_DOMAINS = "blocked_domains"
     private const val KEY_BLOCKED_APPS = "blocked_apps"
     private const val KEY_LOCK_EXPIRY = "lock_expiry"
+    private const val KEY_SAFE_SEARCH = "safe_search_rewrites"
 
     private fun prefs(context: Context): SharedPreferences {
         return context.getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
@@ -37,6 +39,25 @@ object BlocklistManager {
         prefs(context).edit().remove(KEY_DOMAINS).apply()
     }
 
+    // ─── SafeSearch rewrites ────────────────────────────────────────────
+
+    fun saveSafeSearchRewrites(context: Context, rewrites: Map<String, String>) {
+        val json = JSONObject(rewrites).toString()
+        prefs(context).edit().putString(KEY_SAFE_SEARCH, json).apply()
+    }
+
+    fun getSafeSearchRewrites(context: Context): Map<String, String> {
+        val json = prefs(context).getString(KEY_SAFE_SEARCH, "{}") ?: "{}"
+        val result = mutableMapOf<String, String>()
+        try {
+            val obj = JSONObject(json)
+            for (key in obj.keys()) {
+                result[key.lowercase()] = obj.getString(key).lowercase()
+            }
+        } catch (_: Exception) {}
+        return result
+    }
+
     // ─── Blocked apps ───────────────────────────────────────────────────
 
     fun saveBlockedApps(context: Context, packages: List<String>) {
@@ -1,5 +1,7 @@
 package com.tamashii.app.blocker
 
+import java.io.ByteArrayOutputStream
+
 object DnsPacketParser {
     private const val IP_HEADER_MIN_LEN = 20
     private const val UDP_HEADER_LEN = 8
@@ -53,6 +55,142 @@ object DnsPacketParser {
         }
     }
 
+    fun extractQueryType(packet: ByteArray): Int? {
+        try {
+            val ihl = (packet[0].toInt() and 0x0F) * 4
+            val dnsStart = ihl + UDP_HEADER_LEN
+            val nameEnd = skipName(packet, dnsStart + DNS_HEADER_LEN) ?: return null
+            if (nameEnd + 2 > packet.size) return null
+            return readU16(packet, nameEnd)
+        } catch (_: Exception) {
+            return null
+        }
+    }
+
+    /** Build a bare recursive DNS query (no IP/UDP headers) for [name]. */
+    fun buildQuery(name: String, qtype: Int): ByteArray {
+        val out = ByteArrayOutputStream()
+        // ID, flags (RD=1), QDCOUNT=1, ANCOUNT/NSCOUNT/ARCOUNT=0
+        out.write(byteArrayOf(0x54, 0x4D, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0))
+        out.write(encodeName(name))
+        writeU16(out, qtype)
+        writeU16(out, 1) // Class IN
+        return out.toByteArray()
+    }
+
+    /**
+     * Answer [queryPacket] with a CNAME to [target], followed by the A/AAAA
+     * records the upstream resolver returned for [target].
+     */
+    fun buildCnameResponse(queryPacket: ByteArray, target: String, targetResponse: ByteArray): ByteArray? {
+        val queryDns = extractDnsPayload(queryPacket)
+        val questionEnd = (skipName(queryDns, DNS_HEADER_LEN) ?: return null) + 4
+        if (questionEnd > queryDns.size) return null
+
+        val records = extractAddressRecords(targetResponse) ?: return null
+
+        val out = ByteArrayOutputStream()
+        // Header: copy ID, set QR=1 keeping RD, RA=1, QDCOUNT=1, ANCOUNT=1+records
+        out.write(queryDns, 0, 2)
+        out.write((queryDns[2].toInt() and 0x01) or 0x80)
+        out.write(0x80)
+        writeU16(out, 1)
+        writeU16(out, 1 + records.size)
+        writeU16(out, 0)
+        writeU16(out, 0)
+        // Question (without any EDNS additional records from the query)
+        out.write(queryDns, DNS_HEADER_LEN, questionEnd - DNS_HEADER_LEN)
+
+        // CNAME answer pointing at the question name
+        val encodedTarget = encodeName(target)
+        writeU16(out, 0xC00C)
+        writeU16(out, 5) // Type CNAME
+        writeU16(out, 1) // Class IN
+        writeU32(out, 300)
+        writeU16(out, encodedTarget.size)
+        val targetOffset = out.size()
+        out.write(encodedTarget)
+
+        // Address records, named by pointer to the CNAME target
+        for (record in records) {
+            writeU16(out, 0xC000 or targetOffset)
+            writeU16(out, record.type)
+            writeU16(out, 1)
+            writeU32(out, record.ttl)
+            writeU16(out, record.data.size)
+            out.write(record.data)
+        }
+
+        return wrapInIpUdp(queryPacket, out.toByteArray())
+    }
+
+    private class AddressRecord(val type: Int, val ttl: Long, val data: ByteArray)
+
+    private fun extractAddressRecords(dns: ByteArray): List<AddressRecord>? {
+        try {
+            val qdCount = readU16(dns, 4)
+            val anCount = readU16(dns, 6)
+            var offset = DNS_HEADER_LEN
+            repeat(qdCount) {
+                offset = (skipName(dns, offset) ?: return null) + 4
+            }
+
+            val records = mutableListOf<AddressRecord>()
+            repeat(anCount) {
+                offset = skipName(dns, offset) ?: return null
+                val type = readU16(dns, offset)
+                val ttl = ((readU16(dns, offset + 4).toLong()) shl 16) or readU16(dns, offset + 6).toLong()
+                val rdLength = readU16(dns, offset + 8)
+                val rdStart = offset + 10
+                if (rdStart + rdLength > dns.size) return null
+                // Type A (1) or AAAA (28); intermediate CNAMEs are dropped
+                if (type == 1 || type == 28) {
+                    records.add(AddressRecord(type, ttl, dns.copyOfRange(rdStart, rdStart + rdLength)))
+                }
+                offset = rdStart + rdLength
+            }
+            return records
+        } catch (_: Exception) {
+            return null
+        }
+    }
+
+    /** Return the offset just past the (possibly compressed) name at [start]. */
+    private fun skipName(data: ByteArray, start: Int): Int? {
+        var offset = start
+        while (offset < data.size) {
+            val len = data[offset].toInt() and 0xFF
+            if (len == 0) return offset + 1
+            if (len and 0xC0 == 0xC0) return offset + 2
+            offset += len + 1
+        }
+        return null
+    }
+
+    private fun encodeName(name: String): ByteArray {
+        val out = ByteArrayOutputStream()
+        for (label in name.trimEnd('.').split('.')) {
+            val bytes = label.toByteArray(Charsets.US_ASCII)
+            out.write(bytes.size)
+            out.write(bytes)
+        }
+        out.write(0)
+        return out.toByteArray()
+    }
+
+    private fun readU16(data: ByteArray, offset: Int): Int =
+        ((data[offset].toInt() and 0xFF) shl 8) or (data[offset + 1].toInt() and 0xFF)
+
+    private fun writeU16(out: ByteArrayOutputStream, value: Int) {
+        out.write((value shr 8) and 0xFF)
+        out.write(value and 0xFF)
+    }
+
+    private fun writeU32(out: ByteArrayOutputStream, value: Long) {
+        writeU16(out, ((value shr 16) and 0xFFFF).toInt())
+        writeU16(out, (value and 0xFFFF).toInt())
+    }
+
     fun extractDnsPayload(packet: ByteArray): ByteArray {
         val ihl = (packet[0].toInt() and 0x0F) * 4
         val dnsStart = ihl + UDP_HEADER_LEN
@@ -20,6 +20,7 @@ class DnsVpnService : VpnService() {
     companion object {
         const val TAG = "TamashiiVPN"
         const val ACTION_STOP = "com.tamashii.app.STOP_VPN"
+        const val ACTION_RELOAD = "com.tamashii.app.RELOAD_VPN"
         const val CHANNEL_ID = "tamashii_blocking"
         const val NOTIFICATION_ID = 1
 
@@ -55,6 +56,7 @@ class DnsVpnService : VpnService() {
 
     private var vpnInterface: ParcelFileDescriptor? = null
     private var blockedDomains = HashSet<String>()
+    private var safeSearchRewrites = emptyMap<String, String>()
     private var dnsThread: Thread? = null
 
     override fun onStartCommand(intent: Intent?, flags: Int, startId: Int): Int {
@@ -63,6 +65,11 @@ class DnsVpnService : VpnService() {
             return START_NOT_STICKY
         }
 
+        if (intent?.action == ACTION_RELOAD) {
+            if (isRunning) loadBlocklist()
+            return START_STICKY
+        }
+
         if (!isRunning) {
             loadBlocklist()
             createNotificationChannel()
@@ -100,9 +107,10 @@ class DnsVpnService : VpnService() {
 
     private fun loadBlocklist() {
         blockedDomains = HashSet(BlocklistManager.getDomains(this))
+        safeSearchRewrites = BlocklistManager.getSafeSearchRewrites(this)
         domainsLoaded = blockedDomains.size.toLong()
         blockedCount = 0
-        Log.i(TAG, "Loaded ${blockedDomains.size} blocked domains")
+        Log.i(TAG, "Loaded ${blockedDomains.size} blocked domains, ${safeSearchRewrites.size} SafeSearch rewrites")
     }
 
     private fun establishVpn(): Boolean {
@@ -140,6 +148,8 @@ class DnsVpnService : VpnService() {
 
                     val domain = DnsPacketParser.extractDomain(packet) ?: continue
 
+                    val safeSearchTarget = safeSearchRewrites[domain]
+
                     if (shouldBlock(domain)) {
                         val response = DnsPacketParser.buildBlockedResponse(packet)
                         synchronized(output) {
@@ -147,6 +157,14 @@ class DnsVpnService : VpnService() {
                         }
                         blockedCount++
                         Log.d(TAG, "BLOCKED: $domain")
+                    } else if (safeSearchTarget != null) {
+                        val response = rewriteToSafeSearch(packet, safeSearchTarget)
+                        if (response != null) {
+                            synchronized(output) {
+                                output.write(response)
+                            }
+                        }
+                        Log.d(TAG, "SAFESEARCH: $domain -> $safeSearchTarget")
                     } else {
                         val response = forwardDnsQuery(packet)
                         if (response != null) {
@@ -193,9 +211,24 @@ class DnsVpnService : VpnService() {
     }
 
     private fun forwardDnsQuery(queryPacket: ByteArray): ByteArray? {
-        try {
-            val dnsPayload = DnsPacketParser.extractDnsPayload(queryPacket)
+        val dnsPayload = DnsPacketParser.extractDnsPayload(queryPacket)
+        val dnsResponse = queryUpstream(dnsPayload) ?: return null
+        return DnsPacketParser.buildForwardedResponse(queryPacket, dnsResponse)
+    }
 
+    /**
+     * Answer a query for a search engine domain with a CNAME to its
+     * safe-search endpoint, plus the endpoint's own addresses.
+     */
+    private fun rewriteToSafeSearch(queryPacket: ByteArray, target: String): ByteArray? {
+        val qtype = DnsPacketParser.extractQueryType(queryPacket) ?: return null
+        val targetQuery = DnsPacketParser.buildQuery(target, qtype)
+        val targetResponse = queryUpstream(targetQuery) ?: return null
+        return DnsPacketParser.buildCnameResponse(queryPacket, target, targetResponse)
+    }
+
+    private fun queryUpstream(dnsPayload: ByteArray): ByteArray? {
+        try {
             val socket = DatagramSocket()
             protect(socket) // Prevent VPN loop
 
@@ -209,8 +242,7 @@ class DnsVpnService : VpnService() {
             socket.receive(responseDatagram)
             socket.close()
 
-            val dnsResponse = responseBuffer.copyOfRange(0, responseDatagram.length)
-            return DnsPacketParser.buildForwardedResponse(queryPacket, dnsResponse)
+            return responseBuffer.copyOfRange(0, responseDatagram.length)
         } catch (e: Exception) {
             Log.e(TAG, "Error forwarding DNS query", e)
             return null
@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, Runtime};
+
+use super::events::EventKind;
+use super::Category;
+
+/// The achievements the app ships with, mirroring `achievements.ts`.
+const BUNDLED: &str = include_str!("definitions.json");
+
+/// Extra achievements in the app config directory, in the bundled format. A
+/// definition with a bundled id replaces the bundled one.
+const USER_FILE: &str = "achievements.json";
+
+/// One achievement as written in a definitions file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Definition {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: Category,
+    pub rule: Rule,
+}
+
+/// How progress towards an achievement is measured. Written as an object
+/// tagged by `type`, e.g. `{ "type": "streak_days", "min": 7 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Rule {
+    /// A main streak reached `min` clean days.
+    StreakDays { min: u64 },
+    /// The event happened `min` times within `window`.
+    Count {
+        event: EventKind,
+        min: u64,
+        #[serde(default)]
+        window: Window,
+    },
+    /// The event happened on `min` days in a row.
+    ConsecutiveDays { event: EventKind, min: u64 },
+    /// The event happened at a local hour in `from..to` within `window`. A
+    /// `from` later than `to` wraps past midnight: 22 to 2 is 22:00–01:59.
+    TimeOfDay {
+        event: EventKind,
+        from: u32,
+        to: u32,
+        #[serde(default)]
+        window: Window,
+    },
+    /// The blocklist covers `min` block categories at once. Only the current
+    /// blocklist is known, so this one can't be met in hindsight.
+    BlockedCategories { min: u64 },
+}
+
+impl Rule {
+    pub fn requirement(self) -> u64 {
+        match self {
+            Self::StreakDays { min }
+            | Self::Count { min, .. }
+            | Self::ConsecutiveDays { min, .. }
+            | Self::BlockedCategories { min } => min,
+            Self::TimeOfDay { .. } => 1,
+        }
+    }
+
+    /// Whether the saved history shows if the rule was met. Other rules look
+    /// at how things are now.
+    pub fn replayable(self) -> bool {
+        !matches!(self, Self::BlockedCategories { .. })
+    }
+}
+
+/// Which occurrences of an event count: `"all"`, `"streak"` or
+/// `{ "days": N }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Window {
+    /// Every occurrence ever.
+    #[default]
+    All,
+    /// Occurrences during one main streak, the running one or a past one.
+    Streak,
+    /// Occurrences within any stretch of this many days.
+    Days(u32),
+}
+
+/// The bundled definitions plus the user's. A user file that fails to load
+/// is logged and left out.
+pub fn load<R: Runtime>(app: &AppHandle<R>) -> Vec<Definition> {
+    let mut definitions = parse(BUNDLED).unwrap_or_else(|e| {
+        log::error!("Bundled achievements are invalid: {}", e);
+        Vec::new()
+    });
+    let Ok(dir) = app.path().app_config_dir() else {
+        return definitions;
+    };
+    match read(&dir.join(USER_FILE)) {
+        Ok(extra) => {
+            for definition in extra {
+                match definitions.iter_mut().find(|d| d.id == definition.id) {
+                    Some(existing) => *existing = definition,
+                    None => definitions.push(definition),
+                }
+            }
+        }
+        Err(e) => log::error!("Skipping user achievements: {}", e),
+    }
+    log::info!("Loaded {} achievement definitions", definitions.len());
+    definitions
+}
+
+fn read(path: &Path) -> Result<Vec<Definition>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Parse a definitions file and check every definition in it.
+pub fn parse(text: &str) -> Result<Vec<Definition>, String> {
+    let definitions: Vec<Definition> = serde_json::from_str(text)
+        .map_err(|e| format!("Invalid achievement definitions: {}", e))?;
+    let mut ids = HashSet::new();
+    for definition in &definitions {
+        validate(definition).map_err(|e| format!("Achievement {:?}: {}", definition.id, e))?;
+        if !ids.insert(definition.id.as_str()) {
+            return Err(format!("Achievement {:?} is defined twice", definition.id));
+        }
+    }
+    Ok(definitions)
+}
+
+fn validate(definition: &Definition) -> Result<(), String> {
+    let id_ok = !definition.id.is_empty()
+        && definition
+            .id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !id_ok {
+        return Err("the id must be lowercase letters, digits and underscores".into());
+    }
+    if definition.name.trim().is_empty() {
+        return Err("the name is empty".into());
+    }
+    if definition.rule.requirement() == 0 {
+        return Err("min must be at least 1".into());
+    }
+
+    match definition.rule {
+        Rule::StreakDays { .. } | Rule::BlockedCategories { .. } => Ok(()),
+        Rule::ConsecutiveDays { event, .. } => check_event(event),
+        Rule::Count { event, window, .. } => {
+            check_event(event)?;
+            if window == Window::Days(0) {
+                return Err("the window must be at least 1 day".into());
+            }
+            Ok(())
+        }
+        Rule::TimeOfDay {
+            event,
+            from,
+            to,
+            window,
+        } => {
+            check_event(event)?;
+            if from == to || from > 23 || to > 24 {
+                return Err(format!(
+                    "the hours {}..{} are not a range within a day",
+                    from, to
+                ));
+            }
+            if let Window::Days(_) = window {
+                return Err("time_of_day only takes the window \"all\" or \"streak\"".into());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Whether `hour` falls in `from..to`, wrapping past midnight when `from` is
+/// later than `to`.
+pub fn hours_contain(from: u32, to: u32, hour: u32) -> bool {
+    if from <= to {
+        from <= hour && hour < to
+    } else {
+        from <= hour || hour < to
+    }
+}
+
+fn check_event(event: EventKind) -> Result<(), String> {
+    if event == EventKind::StreakTick {
+        return Err("streak_tick is not recorded, so it can't be counted".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_definitions_are_valid() {
+        let definitions = parse(BUNDLED).unwrap();
+        assert_eq!(definitions.len(), 25);
+        let night_owl = definitions.iter().find(|d| d.id == "night_owl").unwrap();
+        assert_eq!(
+            night_owl.rule,
+            Rule::TimeOfDay {
+                event: EventKind::AppOpened,
+                from: 0,
+                to: 5,
+                window: Window::Streak,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_day_windows() {
+        let text = r#"[{ "id": "busy_month", "name": "Busy", "description": "",
+            "category": "journal",
+            "rule": { "type": "count", "event": "journal_written", "min": 20,
+                      "window": { "days": 30 } } }]"#;
+        let definitions = parse(text).unwrap();
+        assert_eq!(
+            definitions[0].rule,
+            Rule::Count {
+                event: EventKind::JournalWritten,
+                min: 20,
+                window: Window::Days(30),
+            }
+        );
+    }
+
+    #[test]
+    fn time_of_day_wraps_past_midnight() {
+        let text = r#"[{ "id": "late", "name": "Late", "description": "",
+            "category": "special",
+            "rule": { "type": "time_of_day", "event": "journal_written", "from": 22, "to": 2 } }]"#;
+        assert!(parse(text).is_ok());
+
+        let hours = |from, to| {
+            (0..24)
+                .filter(|h| hours_contain(from, to, *h))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hours(22, 2), vec![0, 1, 22, 23]);
+        assert_eq!(hours(0, 5), vec![0, 1, 2, 3, 4]);
+        assert_eq!(hours(20, 24), vec![20, 21, 22, 23]);
+    }
+
+    #[test]
+    fn rejects_invalid_definitions() {
+        let with_rule = |rule: &str| {
+            format!(
+                r#"[{{ "id": "x", "name": "X", "description": "", "category": "special",
+                      "rule": {} }}]"#,
+                rule
+            )
+        };
+        for rule in [
+            r#"{ "type": "streak_days", "min": 0 }"#,
+            r#"{ "type": "streak_days", "days": 7 }"#,
+            r#"{ "type": "count", "event": "streak_tick", "min": 1 }"#,
+            r#"{ "type": "count", "event": "unknown", "min": 1 }"#,
+            r#"{ "type": "count", "event": "panic_used", "min": 1, "window": { "days": 0 } }"#,
+            r#"{ "type": "time_of_day", "event": "app_opened", "from": 5, "to": 5 }"#,
+            r#"{ "type": "time_of_day", "event": "app_opened", "from": 0, "to": 25 }"#,
+            r#"{ "type": "time_of_day", "event": "app_opened", "from": 24, "to": 2 }"#,
+            r#"{ "type": "sometimes" }"#,
+        ] {
+            assert!(parse(&with_rule(rule)).is_err(), "accepted {}", rule);
+        }
+
+        let twice = r#"[
+            { "id": "a", "name": "A", "description": "", "category": "special",
+              "rule": { "type": "streak_days", "min": 1 } },
+            { "id": "a", "name": "A", "description": "", "category": "special",
+              "rule": { "type": "streak_days", "min": 2 } }]"#;
+        assert!(parse(twice).is_err());
+    }
+}
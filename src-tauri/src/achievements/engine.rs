@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use chrono::Local;
+use tauri::{AppHandle, Emitter, Listener, Manager, Runtime};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::mpsc;
+
+use super::events::{self, EventKind};
+use super::{definitions, AchievementStatus, Catalog};
+use crate::db::Db;
+use crate::plugins::blocker::{clock, escalation};
+use crate::streak::{self, days};
+
+/// Longest wait between streak ticks, so time-based achievements unlock
+/// without anything else happening.
+const TICK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Hands domain events to the engine task.
+pub struct AchievementEngine {
+    sender: mpsc::UnboundedSender<EventKind>,
+}
+
+/// Start the achievement engine.
+/// Loads the achievement definitions, then records every event published and
+/// checks all locked achievements. Unlocks are saved, emitted as
+/// `achievement-unlocked` and shown as a notification.
+pub fn start(app_handle: &tauri::AppHandle) {
+    app_handle.manage(Catalog(definitions::load(app_handle)));
+    let (sender, receiver) = mpsc::unbounded_channel();
+    app_handle.manage(AchievementEngine { sender });
+
+    // Events the blocker plugin emits
+    let handle = app_handle.clone();
+    app_handle.listen_any("lock-extended", move |_| {
+        publish(&handle, EventKind::LockApplied);
+    });
+    let handle = app_handle.clone();
+    app_handle.listen_any("blocklist-applied", move |_| {
+        publish(&handle, EventKind::BlockerEnabled);
+    });
+
+    let handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        run(handle, receiver).await;
+    });
+}
+
+/// Tell the engine something happened. Does nothing before it started.
+pub fn publish<R: Runtime>(app: &AppHandle<R>, kind: EventKind) {
+    if let Some(engine) = app.try_state::<AchievementEngine>() {
+        let _ = engine.sender.send(kind);
+    }
+}
+
+async fn run(app_handle: tauri::AppHandle, mut receiver: mpsc::UnboundedReceiver<EventKind>) {
+    log::info!("Achievement engine started");
+
+    loop {
+        let kind = tokio::select! {
+            Some(kind) = receiver.recv() => kind,
+            _ = tokio::time::sleep(TICK_INTERVAL) => EventKind::StreakTick,
+        };
+        if let Err(e) = handle(&app_handle, kind).await {
+            log::error!("Achievement check after {:?} failed: {}", kind, e);
+        }
+    }
+}
+
+async fn handle(app_handle: &tauri::AppHandle, kind: EventKind) -> Result<(), String> {
+    let pool = app_handle.state::<Db>().0.clone();
+    let now = clock::now();
+
+    match kind {
+        // Counted per day, so once a day is enough
+        EventKind::StreakTick | EventKind::BlockerEnabled => {
+            if !escalation::enabled_categories(app_handle).is_empty() {
+                let settings = streak::load_settings(&pool).await?;
+                let today =
+                    days::day_start(streak::today(&settings), &Local, settings.day_starts_at);
+                if events::latest(&pool, EventKind::BlockerEnabled)
+                    .await?
+                    .map_or(true, |last| last < today)
+                {
+                    events::record(&pool, EventKind::BlockerEnabled, now).await?;
+                }
+            }
+        }
+        _ => events::record(&pool, kind, now).await?,
+    }
+
+    let catalog = app_handle.state::<Catalog>();
+    for achievement in super::evaluate(app_handle, &pool, &catalog.0).await? {
+        notify(app_handle, &achievement);
+    }
+    Ok(())
+}
+
+fn notify(app_handle: &tauri::AppHandle, achievement: &AchievementStatus) {
+    let _ = app_handle.emit("achievement-unlocked", achievement);
+
+    match app_handle
+        .notification()
+        .builder()
+        .title("Tamashii - Achievement unlocked")
+        .body(format!("{}: {}", achievement.name, achievement.description))
+        .show()
+    {
+        Ok(()) => {
+            log::info!("Achievement notification sent for {}", achievement.id);
+        }
+        Err(e) => {
+            log::error!("Failed to send achievement notification: {}", e);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::streak::{self, days::StreakStart};
+
+/// Something that happened which achievements are counted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// Time passed; streak lengths may have changed. Not recorded.
+    StreakTick,
+    /// The user locked the blocklist or started a lockdown.
+    LockApplied,
+    JournalWritten,
+    PanicUsed,
+    AppOpened,
+    /// At least one block category was on that day. Recorded once a day.
+    BlockerEnabled,
+    /// A main streak began. Read from the streak history, never published.
+    StreakStarted,
+}
+
+impl EventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::StreakTick => "streak_tick",
+            Self::LockApplied => "lock_applied",
+            Self::JournalWritten => "journal_written",
+            Self::PanicUsed => "panic_used",
+            Self::AppOpened => "app_opened",
+            Self::BlockerEnabled => "blocker_enabled",
+            Self::StreakStarted => "streak_started",
+        }
+    }
+
+    /// Events no other table records, so they go to `domain_events`.
+    fn is_logged(self) -> bool {
+        matches!(
+            self,
+            Self::LockApplied | Self::AppOpened | Self::BlockerEnabled
+        )
+    }
+}
+
+/// Save an event at `at` if no other table records it.
+pub async fn record(pool: &SqlitePool, kind: EventKind, at: DateTime<Utc>) -> Result<(), String> {
+    if !kind.is_logged() {
+        return Ok(());
+    }
+    sqlx::query("INSERT INTO domain_events (kind, occurred_at) VALUES (?, ?)")
+        .bind(kind.as_str())
+        .bind(at.to_rfc3339())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to record {} event: {}", kind.as_str(), e))?;
+    Ok(())
+}
+
+/// Every time `kind` happened, oldest first. Journal entries, panic button
+/// presses and streak starts are read from their own tables, so those count
+/// from before the engine existed.
+pub async fn occurrences(pool: &SqlitePool, kind: EventKind) -> Result<Vec<DateTime<Utc>>, String> {
+    let query = match kind {
+        EventKind::StreakTick => return Ok(Vec::new()),
+        EventKind::StreakStarted => return streak_starts(pool).await,
+        EventKind::JournalWritten => sqlx::query_as(
+            "SELECT created_at FROM journal_entries WHERE created_at IS NOT NULL
+             ORDER BY julianday(created_at)",
+        ),
+        EventKind::PanicUsed => {
+            sqlx::query_as("SELECT occurred_at FROM panic_events ORDER BY julianday(occurred_at)")
+        }
+        _ => sqlx::query_as(
+            "SELECT occurred_at FROM domain_events WHERE kind = ?
+             ORDER BY julianday(occurred_at)",
+        )
+        .bind(kind.as_str()),
+    };
+    let rows: Vec<(String,)> = query
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load {} events: {}", kind.as_str(), e))?;
+    Ok(rows
+        .iter()
+        .filter_map(|(at,)| DateTime::parse_from_rfc3339(at).ok())
+        .map(|at| at.with_timezone(&Utc))
+        .collect())
+}
+
+/// Start of every main streak, oldest first. Streaks of earlier versions
+/// began on a date and count from that day's start.
+async fn streak_starts(pool: &SqlitePool) -> Result<Vec<DateTime<Utc>>, String> {
+    let settings = streak::load_settings(pool).await?;
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT start_date FROM streaks
+         WHERE habit_id = (SELECT id FROM habits WHERE is_default = 1)
+           AND start_date IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load streak history: {}", e))?;
+    let mut starts: Vec<DateTime<Utc>> = rows
+        .iter()
+        .filter_map(|(start,)| StreakStart::parse(start).ok())
+        .map(|start| streak::began_at(start, &settings))
+        .collect();
+    starts.sort();
+    Ok(starts)
+}
+
+/// When `kind` was last recorded.
+pub async fn latest(pool: &SqlitePool, kind: EventKind) -> Result<Option<DateTime<Utc>>, String> {
+    Ok(occurrences(pool, kind).await?.pop())
+}
@@ -0,0 +1,474 @@
+pub mod definitions;
+pub mod engine;
+pub mod events;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Runtime};
+
+use crate::plugins::blocker::{clock, escalation};
+use crate::streak::days::StreakStart;
+use crate::streak::{self, grace, habits, milestones, StreakSettings};
+use definitions::{Definition, Rule, Window};
+use events::EventKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Streak,
+    Blocker,
+    Journal,
+    Panic,
+    Special,
+}
+
+/// Achievement definitions loaded at startup.
+pub struct Catalog(pub Vec<Definition>);
+
+/// An achievement with its saved progress.
+#[derive(Debug, Clone, Serialize)]
+pub struct AchievementStatus {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: Category,
+    pub requirement: u64,
+    pub progress: u64,
+    /// RFC 3339 time it was unlocked, or null while locked.
+    pub unlocked_at: Option<String>,
+}
+
+/// Every achievement with the progress saved in the `achievements` table.
+pub async fn list(
+    pool: &SqlitePool,
+    definitions: &[Definition],
+) -> Result<Vec<AchievementStatus>, String> {
+    let saved = load_saved(pool).await?;
+    Ok(definitions
+        .iter()
+        .map(|definition| {
+            let (unlocked_at, progress) = saved.get(&definition.id).cloned().unwrap_or_default();
+            to_status(definition, progress, unlocked_at)
+        })
+        .collect())
+}
+
+/// Work out the progress of every locked achievement, save what changed and
+/// return the achievements unlocked just now. Rules are measured against the
+/// whole saved history, so an achievement added later unlocks at the time it
+/// was first earned.
+pub async fn evaluate<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    definitions: &[Definition],
+) -> Result<Vec<AchievementStatus>, String> {
+    let saved = load_saved(pool).await?;
+    let facts = Facts::load(app, pool).await?;
+
+    let mut unlocked = Vec::new();
+    for definition in definitions {
+        let (unlocked_at, saved_progress) = saved.get(&definition.id).cloned().unwrap_or_default();
+        if unlocked_at.is_some() {
+            continue;
+        }
+
+        let requirement = definition.rule.requirement();
+        let outcome = facts.outcome(definition.rule);
+        if let Some(met_at) = outcome.met_at {
+            let met_at = met_at.to_rfc3339();
+            save(pool, &definition.id, requirement, Some(&met_at)).await?;
+            log::info!("Achievement {} unlocked", definition.id);
+            unlocked.push(to_status(definition, requirement, Some(met_at)));
+        } else {
+            let progress = outcome.progress.min(requirement);
+            if progress != saved_progress {
+                save(pool, &definition.id, progress, None).await?;
+            }
+        }
+    }
+    Ok(unlocked)
+}
+
+/// An achievement before and after a recompute.
+#[derive(Debug, Clone, Serialize, Hash)]
+pub struct AchievementChange {
+    pub id: String,
+    pub name: String,
+    pub progress_before: u64,
+    pub progress_after: u64,
+    pub unlocked_at_before: Option<String>,
+    pub unlocked_at_after: Option<String>,
+}
+
+/// What a recompute changes, by kind of change.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecomputeDiff {
+    /// Locked achievements the history shows were earned.
+    pub unlocked: Vec<AchievementChange>,
+    /// Unlocked achievements the history no longer supports.
+    pub revoked: Vec<AchievementChange>,
+    /// Unlocked achievements saved as earned before they could have been.
+    /// They move to the time they were earned.
+    pub retimed: Vec<AchievementChange>,
+    /// Achievements where only the progress changes.
+    pub progress: Vec<AchievementChange>,
+    /// Identifies this set of changes. Applying takes it back, so only the
+    /// changes the user saw are saved.
+    pub fingerprint: String,
+    /// Whether the changes were saved.
+    pub applied: bool,
+}
+
+/// Replay the whole saved history through the rules of every achievement,
+/// unlocked ones included, and return how the saved state differs. It is
+/// saved only when `confirm` carries the fingerprint of a preview showing the
+/// same changes. An unlock time is kept while the rule is still met by then;
+/// a rule only the current state can show is never revoked.
+pub async fn recompute<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    definitions: &[Definition],
+    confirm: Option<&str>,
+) -> Result<RecomputeDiff, String> {
+    let saved = load_saved(pool).await?;
+    let facts = Facts::load(app, pool).await?;
+
+    let mut diff = RecomputeDiff::default();
+    for definition in definitions {
+        let (unlocked_at_before, progress_before) =
+            saved.get(&definition.id).cloned().unwrap_or_default();
+        let requirement = definition.rule.requirement();
+        let outcome = facts.outcome(definition.rule);
+
+        let saved_at = unlocked_at_before
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at.with_timezone(&Utc));
+        let unlocked_at_after = match (outcome.met_at, saved_at) {
+            (Some(met_at), Some(saved_at)) if saved_at >= met_at => unlocked_at_before.clone(),
+            (Some(met_at), _) => Some(met_at.to_rfc3339()),
+            (None, _) if !definition.rule.replayable() => unlocked_at_before.clone(),
+            (None, _) => None,
+        };
+        let progress_after = match unlocked_at_after {
+            Some(_) => requirement,
+            None => outcome.progress.min(requirement),
+        };
+
+        let group = match (&unlocked_at_before, &unlocked_at_after) {
+            (None, Some(_)) => &mut diff.unlocked,
+            (Some(_), None) => &mut diff.revoked,
+            (Some(before), Some(after)) if before != after => &mut diff.retimed,
+            _ if progress_before != progress_after => &mut diff.progress,
+            _ => continue,
+        };
+        group.push(AchievementChange {
+            id: definition.id.clone(),
+            name: definition.name.clone(),
+            progress_before,
+            progress_after,
+            unlocked_at_before,
+            unlocked_at_after,
+        });
+    }
+
+    let changes: Vec<&AchievementChange> = diff
+        .unlocked
+        .iter()
+        .chain(&diff.revoked)
+        .chain(&diff.retimed)
+        .chain(&diff.progress)
+        .collect();
+    let mut hasher = DefaultHasher::new();
+    changes.hash(&mut hasher);
+    let fingerprint = format!("{:016x}", hasher.finish());
+
+    if let Some(confirmed) = confirm {
+        if confirmed != fingerprint {
+            return Err(
+                "The achievements changed since the preview. Review the changes again.".into(),
+            );
+        }
+        overwrite(pool, &changes).await?;
+        diff.applied = true;
+        log::info!(
+            "Achievements recomputed: {} unlocked, {} revoked, {} retimed",
+            diff.unlocked.len(),
+            diff.revoked.len(),
+            diff.retimed.len()
+        );
+    }
+    diff.fingerprint = fingerprint;
+    Ok(diff)
+}
+
+/// Where a rule stands.
+struct Outcome {
+    progress: u64,
+    /// When the rule was first met, if it was.
+    met_at: Option<DateTime<Utc>>,
+}
+
+/// A main streak, running or past.
+struct Span {
+    start: StreakStart,
+    began: DateTime<Utc>,
+    ended: Option<DateTime<Utc>>,
+    clean_days: u64,
+    frozen: Vec<NaiveDate>,
+}
+
+impl Span {
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.began <= at && self.ended.map_or(true, |ended| at < ended)
+    }
+}
+
+/// What the rules are measured against, loaded once per evaluation.
+struct Facts {
+    now: DateTime<Utc>,
+    settings: StreakSettings,
+    /// Main streaks, the running one last.
+    streaks: Vec<Span>,
+    enabled_categories: u64,
+    occurrences: HashMap<EventKind, Vec<DateTime<Utc>>>,
+}
+
+impl Facts {
+    async fn load<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool) -> Result<Self, String> {
+        let now = clock::now();
+        let settings = streak::load_settings(pool).await?;
+        let habit_id = habits::default_id(pool).await?;
+
+        let mut records = streak::history(pool, habit_id).await?;
+        records.reverse();
+        let running = streak::current(pool, habit_id).await?;
+        let mut streaks = Vec::new();
+        for record in records.iter().chain(running.as_ref()) {
+            let Ok(start) = StreakStart::parse(&record.start_date) else {
+                continue;
+            };
+            let ended = record
+                .end_date
+                .as_deref()
+                .and_then(|end| DateTime::parse_from_rfc3339(end).ok())
+                .map(|end| end.with_timezone(&Utc));
+            let clean_days = match record.end_date {
+                Some(_) => record.days.unwrap_or(0).max(0) as u64,
+                None => streak::clean_days_of(pool, record, &settings, now).await?,
+            };
+            streaks.push(Span {
+                start,
+                began: streak::began_at(start, &settings),
+                ended,
+                clean_days,
+                frozen: grace::frozen_in_streak(pool, record.id).await?,
+            });
+        }
+
+        let mut occurrences = HashMap::new();
+        for kind in [
+            EventKind::LockApplied,
+            EventKind::JournalWritten,
+            EventKind::PanicUsed,
+            EventKind::AppOpened,
+            EventKind::BlockerEnabled,
+            EventKind::StreakStarted,
+        ] {
+            occurrences.insert(kind, events::occurrences(pool, kind).await?);
+        }
+
+        Ok(Self {
+            now,
+            settings,
+            streaks,
+            enabled_categories: escalation::enabled_categories(app).len() as u64,
+            occurrences,
+        })
+    }
+
+    fn outcome(&self, rule: Rule) -> Outcome {
+        match rule {
+            Rule::StreakDays { min } => Outcome {
+                progress: self.streaks.iter().map(|s| s.clean_days).max().unwrap_or(0),
+                met_at: self
+                    .streaks
+                    .iter()
+                    .filter(|s| s.clean_days >= min)
+                    .map(|s| milestones::reached_at(s.start, min, &s.frozen, &self.settings))
+                    .min(),
+            },
+            Rule::Count { event, min, window } => {
+                let times = self.of(event);
+                let nth = (min as usize).max(1) - 1;
+                match window {
+                    Window::All => Outcome {
+                        progress: times.len() as u64,
+                        met_at: times.get(nth).copied(),
+                    },
+                    Window::Streak => Outcome {
+                        progress: self.running().map_or(0, |running| {
+                            times.iter().filter(|at| running.contains(**at)).count() as u64
+                        }),
+                        met_at: self
+                            .streaks
+                            .iter()
+                            .filter_map(|s| times.iter().filter(|at| s.contains(**at)).nth(nth))
+                            .min()
+                            .copied(),
+                    },
+                    Window::Days(days) => {
+                        let span = Duration::days(days as i64);
+                        Outcome {
+                            progress: times.iter().filter(|at| **at > self.now - span).count()
+                                as u64,
+                            met_at: times
+                                .windows(nth + 1)
+                                .find(|w| w[nth] - w[0] < span)
+                                .map(|w| w[nth]),
+                        }
+                    }
+                }
+            }
+            Rule::ConsecutiveDays { event, min } => {
+                let mut longest = 0;
+                let mut met_at = None;
+                let mut run = 0;
+                let mut previous: Option<NaiveDate> = None;
+                for at in self.of(event) {
+                    let date = streak::day_of(*at, &self.settings);
+                    if previous == Some(date) {
+                        continue;
+                    }
+                    run = match previous {
+                        Some(previous) if previous.succ_opt() == Some(date) => run + 1,
+                        _ => 1,
+                    };
+                    longest = longest.max(run);
+                    if run >= min && met_at.is_none() {
+                        met_at = Some(*at);
+                    }
+                    previous = Some(date);
+                }
+                Outcome {
+                    progress: longest,
+                    met_at,
+                }
+            }
+            Rule::TimeOfDay {
+                event,
+                from,
+                to,
+                window,
+            } => {
+                let met_at = self
+                    .of(event)
+                    .iter()
+                    .filter(|at| {
+                        definitions::hours_contain(from, to, at.with_timezone(&Local).hour())
+                    })
+                    .find(|at| {
+                        window != Window::Streak || self.streaks.iter().any(|s| s.contains(**at))
+                    })
+                    .copied();
+                Outcome {
+                    progress: met_at.is_some() as u64,
+                    met_at,
+                }
+            }
+            Rule::BlockedCategories { min } => Outcome {
+                progress: self.enabled_categories,
+                met_at: (self.enabled_categories >= min).then_some(self.now),
+            },
+        }
+    }
+
+    fn of(&self, event: EventKind) -> &[DateTime<Utc>] {
+        self.occurrences.get(&event).map_or(&[], Vec::as_slice)
+    }
+
+    fn running(&self) -> Option<&Span> {
+        self.streaks.last().filter(|s| s.ended.is_none())
+    }
+}
+
+/// Saved `(unlocked_at, progress)` by achievement id.
+async fn load_saved(pool: &SqlitePool) -> Result<HashMap<String, (Option<String>, u64)>, String> {
+    let rows: Vec<(String, Option<String>, Option<i64>)> =
+        sqlx::query_as("SELECT id, unlocked_at, progress FROM achievements")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to load achievements: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, unlocked_at, progress)| (id, (unlocked_at, progress.unwrap_or(0).max(0) as u64)))
+        .collect())
+}
+
+/// Save progress. An unlock time already saved is kept.
+async fn save(
+    pool: &SqlitePool,
+    id: &str,
+    progress: u64,
+    unlocked_at: Option<&str>,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO achievements (id, unlocked_at, progress) VALUES (?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+             unlocked_at = COALESCE(achievements.unlocked_at, excluded.unlocked_at),
+             progress = excluded.progress",
+    )
+    .bind(id)
+    .bind(unlocked_at)
+    .bind(progress as i64)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save achievement {}: {}", id, e))?;
+    Ok(())
+}
+
+/// Save recomputed state as is, in one transaction.
+async fn overwrite(pool: &SqlitePool, changes: &[&AchievementChange]) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to save achievements: {}", e))?;
+    for change in changes {
+        sqlx::query(
+            "INSERT INTO achievements (id, unlocked_at, progress) VALUES (?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                 unlocked_at = excluded.unlocked_at,
+                 progress = excluded.progress",
+        )
+        .bind(&change.id)
+        .bind(&change.unlocked_at_after)
+        .bind(change.progress_after as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save achievement {}: {}", change.id, e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to save achievements: {}", e))
+}
+
+fn to_status(
+    definition: &Definition,
+    progress: u64,
+    unlocked_at: Option<String>,
+) -> AchievementStatus {
+    AchievementStatus {
+        id: definition.id.clone(),
+        name: definition.name.clone(),
+        description: definition.description.clone(),
+        category: definition.category,
+        requirement: definition.rule.requirement(),
+        progress,
+        unlocked_at,
+    }
+}
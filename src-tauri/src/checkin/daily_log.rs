@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use sqlx::SqlitePool;
+
+use crate::db;
+use crate::streak::days::{self, StreakStart};
+use crate::streak::{self, habits, StreakSettings};
+
+/// Last day whose `daily_logs` row was filled in from the recorded events.
+const KEY_CLOSED_THROUGH: &str = "daily_logs_closed_through";
+
+/// Days a first run fills in, counting back from yesterday.
+const FIRST_RUN_DAYS: i64 = 90;
+
+/// Fill in the `daily_logs` flags of every day that ended since the last
+/// run, from what was recorded: a running main streak, a reset, the panic
+/// button, a journal entry. Days without a check-in are filled in too. A
+/// flag already set on a day stays set.
+/// Returns how many days were filled in.
+pub async fn close_past_days(pool: &SqlitePool) -> Result<u32, String> {
+    let settings = streak::load_settings(pool).await?;
+    let yesterday = streak::today(&settings) - Duration::days(1);
+    let first = match db::get_setting(pool, KEY_CLOSED_THROUGH)
+        .await?
+        .and_then(|v| v.parse::<NaiveDate>().ok())
+    {
+        Some(closed) => closed + Duration::days(1),
+        None => yesterday - Duration::days(FIRST_RUN_DAYS - 1),
+    };
+    if first > yesterday {
+        return Ok(0);
+    }
+
+    let habit_id = habits::default_id(pool).await?;
+    let spans = streak_spans(pool, habit_id, &settings).await?;
+    let mut closed = 0;
+    let mut day = first;
+    while day <= yesterday {
+        close_day(pool, habit_id, &spans, &settings, day).await?;
+        closed += 1;
+        day += Duration::days(1);
+    }
+    db::set_setting(pool, KEY_CLOSED_THROUGH, &yesterday.to_string()).await?;
+
+    log::info!("Daily log filled in from {} to {}", first, yesterday);
+    Ok(closed)
+}
+
+/// When each streak of the main habit began and, unless it is running,
+/// ended.
+async fn streak_spans(
+    pool: &SqlitePool,
+    habit_id: i64,
+    settings: &StreakSettings,
+) -> Result<Vec<(DateTime<Utc>, Option<DateTime<Utc>>)>, String> {
+    let mut records = streak::history(pool, habit_id).await?;
+    records.extend(streak::current(pool, habit_id).await?);
+    Ok(records
+        .iter()
+        .filter_map(|record| {
+            let began = streak::began_at(StreakStart::parse(&record.start_date).ok()?, settings);
+            let ended = record
+                .end_date
+                .as_deref()
+                .and_then(|e| DateTime::parse_from_rfc3339(e).ok())
+                .map(|e| e.with_timezone(&Utc));
+            Some((began, ended))
+        })
+        .collect())
+}
+
+async fn close_day(
+    pool: &SqlitePool,
+    habit_id: i64,
+    spans: &[(DateTime<Utc>, Option<DateTime<Utc>>)],
+    settings: &StreakSettings,
+    day: NaiveDate,
+) -> Result<(), String> {
+    let start = days::day_start(day, &Local, settings.day_starts_at);
+    let end = days::day_start(day + Duration::days(1), &Local, settings.day_starts_at);
+    let had_streak = spans
+        .iter()
+        .any(|(began, ended)| *began < end && ended.map_or(true, |e| e > start));
+
+    let (resets, panics, entries): (i64, i64, i64) = sqlx::query_as(
+        "SELECT
+             (SELECT COUNT(*) FROM streaks WHERE habit_id = ?1 AND end_date IS NOT NULL
+                 AND julianday(end_date) >= julianday(?2) AND julianday(end_date) < julianday(?3)),
+             (SELECT COUNT(*) FROM panic_events WHERE julianday(occurred_at) >= julianday(?2)
+                 AND julianday(occurred_at) < julianday(?3)),
+             (SELECT COUNT(*) FROM journal_entries WHERE julianday(created_at) >= julianday(?2)
+                 AND julianday(created_at) < julianday(?3))",
+    )
+    .bind(habit_id)
+    .bind(start.to_rfc3339())
+    .bind(end.to_rfc3339())
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to read the events of {}: {}", day, e))?;
+
+    sqlx::query(
+        "INSERT INTO daily_logs (date, had_streak, was_reset, panic_used, journal_written)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(date) DO UPDATE SET
+             had_streak = MAX(COALESCE(daily_logs.had_streak, 0), excluded.had_streak),
+             was_reset = MAX(COALESCE(daily_logs.was_reset, 0), excluded.was_reset),
+             panic_used = MAX(COALESCE(daily_logs.panic_used, 0), excluded.panic_used),
+             journal_written = MAX(COALESCE(daily_logs.journal_written, 0),
+                 excluded.journal_written)",
+    )
+    .bind(day.to_string())
+    .bind(had_streak)
+    .bind(resets > 0)
+    .bind(panics > 0)
+    .bind(entries > 0)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update daily log: {}", e))?;
+    Ok(())
+}
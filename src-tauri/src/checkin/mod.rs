@@ -0,0 +1,173 @@
+pub mod daily_log;
+
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::db;
+use crate::plugins::blocker::clock;
+use crate::streak;
+
+const KEY_CHECK_IN: &str = "check_in";
+
+const MAX_NOTE_CHARS: usize = 200;
+
+/// The evening prompt to check in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckInSettings {
+    pub enabled: bool,
+    /// Local `HH:MM` the notification is sent at, unless the day's check-in
+    /// is already done.
+    pub prompt_time: String,
+}
+
+impl Default for CheckInSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prompt_time: "21:00".into(),
+        }
+    }
+}
+
+impl CheckInSettings {
+    pub fn prompt_time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(&self.prompt_time, "%H:%M").ok()
+    }
+}
+
+/// How a day went, one per streak day.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CheckIn {
+    /// `YYYY-MM-DD`, in streak days.
+    pub date: String,
+    /// 1 (terrible) to 5 (great), the scale of journal moods.
+    pub mood: i64,
+    /// 1 (drained) to 5 (full of energy).
+    pub energy: Option<i64>,
+    pub sleep_hours: Option<f64>,
+    /// Strongest urge of the day, 0 (none) to 10.
+    pub urge_level: Option<i64>,
+    pub note: Option<String>,
+    pub recorded_at: String,
+}
+
+/// A check-in as entered by the user.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckInInput {
+    /// The day checked in for; defaults to today. Only today and earlier.
+    pub date: Option<NaiveDate>,
+    pub mood: u8,
+    pub energy: Option<u8>,
+    pub sleep_hours: Option<f64>,
+    pub urge_level: Option<u8>,
+    pub note: Option<String>,
+}
+
+pub async fn load_settings(pool: &SqlitePool) -> Result<CheckInSettings, String> {
+    Ok(db::get_setting(pool, KEY_CHECK_IN)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default())
+}
+
+pub async fn save_settings(pool: &SqlitePool, settings: &CheckInSettings) -> Result<(), String> {
+    if settings.prompt_time().is_none() {
+        return Err(format!(
+            "Invalid prompt time {}, expected HH:MM",
+            settings.prompt_time
+        ));
+    }
+    let value = serde_json::to_string(settings)
+        .map_err(|e| format!("Failed to serialize check-in settings: {}", e))?;
+    db::set_setting(pool, KEY_CHECK_IN, &value).await
+}
+
+/// Save the check-in for a day, replacing one already made for it.
+pub async fn record(pool: &SqlitePool, input: CheckInInput) -> Result<CheckIn, String> {
+    let today = streak::today(&streak::load_settings(pool).await?);
+    let date = input.date.unwrap_or(today);
+    if date > today {
+        return Err("You can't check in for a day that hasn't started".into());
+    }
+    if !(1..=5).contains(&input.mood) {
+        return Err("Mood must be between 1 and 5".into());
+    }
+    if input.energy.is_some_and(|e| !(1..=5).contains(&e)) {
+        return Err("Energy must be between 1 and 5".into());
+    }
+    if input
+        .sleep_hours
+        .is_some_and(|h| !(0.0..=24.0).contains(&h))
+    {
+        return Err("Sleep must be between 0 and 24 hours".into());
+    }
+    if input.urge_level.is_some_and(|u| u > 10) {
+        return Err("Urge level must be between 0 and 10".into());
+    }
+    let note = input
+        .note
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+    if let Some(note) = &note {
+        if note.contains('\n') || note.chars().count() > MAX_NOTE_CHARS {
+            return Err(format!(
+                "The note is one line of at most {} characters",
+                MAX_NOTE_CHARS
+            ));
+        }
+    }
+
+    sqlx::query(
+        "INSERT INTO check_ins (date, mood, energy, sleep_hours, urge_level, note, recorded_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(date) DO UPDATE SET mood = excluded.mood, energy = excluded.energy,
+             sleep_hours = excluded.sleep_hours, urge_level = excluded.urge_level,
+             note = excluded.note, recorded_at = excluded.recorded_at",
+    )
+    .bind(date.to_string())
+    .bind(input.mood as i64)
+    .bind(input.energy.map(i64::from))
+    .bind(input.sleep_hours)
+    .bind(input.urge_level.map(i64::from))
+    .bind(&note)
+    .bind(clock::now().to_rfc3339())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save check-in: {}", e))?;
+
+    log::info!("Checked in for {}", date);
+    get(pool, date)
+        .await?
+        .ok_or_else(|| format!("Check-in for {} was not saved", date))
+}
+
+pub async fn get(pool: &SqlitePool, date: NaiveDate) -> Result<Option<CheckIn>, String> {
+    sqlx::query_as(
+        "SELECT date, mood, energy, sleep_hours, urge_level, note, recorded_at
+         FROM check_ins WHERE date = ?",
+    )
+    .bind(date.to_string())
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load check-in: {}", e))
+}
+
+/// Check-ins from `from` to `to`, both optional and inclusive, newest first.
+pub async fn list(
+    pool: &SqlitePool,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CheckIn>, String> {
+    sqlx::query_as(
+        "SELECT date, mood, energy, sleep_hours, urge_level, note, recorded_at
+         FROM check_ins WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+         ORDER BY date DESC",
+    )
+    .bind(from.map(|d| d.to_string()))
+    .bind(to.map(|d| d.to_string()))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load check-ins: {}", e))
+}
@@ -0,0 +1,27 @@
+use tauri::{AppHandle, State};
+
+use crate::achievements::{self, AchievementStatus, Catalog, RecomputeDiff};
+use crate::db::Db;
+
+/// Every achievement with its saved progress. Unlocking happens in the
+/// backend engine as events come in.
+#[tauri::command]
+pub async fn get_achievements(
+    db: State<'_, Db>,
+    catalog: State<'_, Catalog>,
+) -> Result<Vec<AchievementStatus>, String> {
+    achievements::list(&db.0, &catalog.0).await
+}
+
+/// Replay the whole event and streak history through the achievement rules.
+/// Returns what would change; call again with the preview's fingerprint as
+/// `confirm` once the user confirmed to save exactly those changes.
+#[tauri::command]
+pub async fn recompute_achievements(
+    app: AppHandle,
+    db: State<'_, Db>,
+    catalog: State<'_, Catalog>,
+    confirm: Option<String>,
+) -> Result<RecomputeDiff, String> {
+    achievements::recompute(&app, &db.0, &catalog.0, confirm.as_deref()).await
+}
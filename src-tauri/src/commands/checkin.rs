@@ -0,0 +1,50 @@
+use chrono::NaiveDate;
+use tauri::State;
+
+use crate::checkin::{self, CheckIn, CheckInInput, CheckInSettings};
+use crate::db::Db;
+use crate::streak;
+
+/// The check-in for `date`, today by default, if there is one.
+#[tauri::command]
+pub async fn get_check_in(
+    db: State<'_, Db>,
+    date: Option<NaiveDate>,
+) -> Result<Option<CheckIn>, String> {
+    let date = match date {
+        Some(date) => date,
+        None => streak::today(&streak::load_settings(&db.0).await?),
+    };
+    checkin::get(&db.0, date).await
+}
+
+/// Save the check-in for a day, replacing one already made for it.
+#[tauri::command]
+pub async fn save_check_in(db: State<'_, Db>, check_in: CheckInInput) -> Result<CheckIn, String> {
+    checkin::record(&db.0, check_in).await
+}
+
+/// Check-ins between two days, both optional and inclusive, newest first.
+#[tauri::command]
+pub async fn list_check_ins(
+    db: State<'_, Db>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CheckIn>, String> {
+    checkin::list(&db.0, from, to).await
+}
+
+#[tauri::command]
+pub async fn get_check_in_settings(db: State<'_, Db>) -> Result<CheckInSettings, String> {
+    checkin::load_settings(&db.0).await
+}
+
+/// Change when, and whether, the evening check-in prompt is sent.
+#[tauri::command]
+pub async fn set_check_in_settings(
+    db: State<'_, Db>,
+    settings: CheckInSettings,
+) -> Result<CheckInSettings, String> {
+    checkin::save_settings(&db.0, &settings).await?;
+    Ok(settings)
+}
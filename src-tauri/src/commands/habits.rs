@@ -0,0 +1,114 @@
+use tauri::{AppHandle, State};
+
+use crate::achievements::engine;
+use crate::achievements::events::EventKind;
+use crate::db::Db;
+use crate::scheduler::milestone_scheduler;
+use crate::streak::grace::{self, FreezeEntry, GraceSettings};
+use crate::streak::habits::{self, HabitChanges, HabitRun, HabitStatus, NewHabit};
+
+/// Habits that aren't archived, the default one first.
+#[tauri::command]
+pub async fn list_habits(db: State<'_, Db>) -> Result<Vec<HabitStatus>, String> {
+    habits::list(&db.0).await
+}
+
+#[tauri::command]
+pub async fn create_habit(db: State<'_, Db>, habit: NewHabit) -> Result<HabitStatus, String> {
+    habits::create(&db.0, habit).await
+}
+
+/// Rename a habit or change its reminder.
+#[tauri::command]
+pub async fn update_habit(
+    db: State<'_, Db>,
+    id: i64,
+    changes: HabitChanges,
+) -> Result<HabitStatus, String> {
+    habits::update(&db.0, id, changes).await
+}
+
+/// Stop tracking a habit, keeping its history.
+#[tauri::command]
+pub async fn archive_habit(db: State<'_, Db>, id: i64) -> Result<(), String> {
+    habits::archive(&db.0, id).await
+}
+
+/// Mark a do habit as done today, or on `date` (YYYY-MM-DD).
+#[tauri::command]
+pub async fn check_in_habit(
+    db: State<'_, Db>,
+    id: i64,
+    date: Option<String>,
+) -> Result<HabitStatus, String> {
+    habits::check_in(&db.0, id, date).await
+}
+
+/// End the running streak of an avoid habit, optionally noting why.
+#[tauri::command]
+pub async fn reset_habit(
+    app: AppHandle,
+    db: State<'_, Db>,
+    id: i64,
+    reason: Option<String>,
+) -> Result<HabitStatus, String> {
+    let status = habits::reset(&db.0, id, reason).await?;
+    if status.habit.is_default {
+        super::streak::protect_after_reset(&app);
+    }
+    Ok(status)
+}
+
+/// Start a new streak of an avoid habit.
+#[tauri::command]
+pub async fn start_habit(
+    app: AppHandle,
+    db: State<'_, Db>,
+    id: i64,
+) -> Result<HabitStatus, String> {
+    let status = habits::start(&db.0, id).await?;
+    if status.habit.is_default {
+        milestone_scheduler::reschedule();
+        engine::publish(&app, EventKind::StreakTick);
+    }
+    Ok(status)
+}
+
+/// Streaks of a habit, newest first, the running one included.
+#[tauri::command]
+pub async fn get_habit_history(db: State<'_, Db>, id: i64) -> Result<Vec<HabitRun>, String> {
+    habits::history(&db.0, id).await
+}
+
+/// Spend a freeze token on `day` (YYYY-MM-DD, default today) to keep the
+/// streak going through a declared exception.
+#[tauri::command]
+pub async fn use_streak_freeze(
+    db: State<'_, Db>,
+    id: i64,
+    day: Option<String>,
+    note: Option<String>,
+) -> Result<HabitStatus, String> {
+    grace::spend(&db.0, id, day, note).await?;
+    let status = habits::status(&db.0, id).await?;
+    if status.habit.is_default {
+        milestone_scheduler::reschedule();
+    }
+    Ok(status)
+}
+
+/// Freeze tokens a habit earned, forfeited and spent, newest first.
+#[tauri::command]
+pub async fn get_freeze_history(db: State<'_, Db>, id: i64) -> Result<Vec<FreezeEntry>, String> {
+    grace::ledger(&db.0, id).await
+}
+
+#[tauri::command]
+pub async fn get_grace_settings(db: State<'_, Db>) -> Result<GraceSettings, String> {
+    grace::load_settings(&db.0).await
+}
+
+#[tauri::command]
+pub async fn set_grace_settings(db: State<'_, Db>, settings: GraceSettings) -> Result<(), String> {
+    grace::save_settings(&db.0, &settings).await
+}
@@ -0,0 +1,273 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter, State};
+use zeroize::Zeroizing;
+
+use crate::achievements::engine;
+use crate::achievements::events::EventKind;
+use crate::db::Db;
+use crate::journal::export::{self, ExportFilter, ExportFormat, ExportSummary};
+use crate::journal::import::{self, ImportSummary};
+use crate::journal::mood::{self, MoodAnalytics, MoodQuery};
+use crate::journal::revisions::{self, Revision, RevisionDiff};
+use crate::journal::search::{self, SearchHit, SearchQuery};
+use crate::journal::templates::{self, AggregateQuery, Bucket, Template};
+use crate::journal::vault::{self, JournalVault, VaultStatus};
+use crate::journal::{self, JournalEntry, JournalInput};
+
+/// Journal entries, newest first.
+#[tauri::command]
+pub async fn list_journal_entries(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+) -> Result<Vec<JournalEntry>, String> {
+    journal::list(&db.0, &vault).await
+}
+
+#[tauri::command]
+pub async fn create_journal_entry(
+    app: AppHandle,
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    entry: JournalInput,
+) -> Result<JournalEntry, String> {
+    let entry = journal::create(&db.0, &vault, entry).await?;
+    engine::publish(&app, EventKind::JournalWritten);
+    Ok(entry)
+}
+
+#[tauri::command]
+pub async fn update_journal_entry(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    id: i64,
+    entry: JournalInput,
+) -> Result<JournalEntry, String> {
+    journal::update(&db.0, &vault, id, entry).await
+}
+
+#[tauri::command]
+pub async fn delete_journal_entry(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    id: i64,
+) -> Result<(), String> {
+    journal::delete(&db.0, &vault, id).await
+}
+
+/// Earlier versions of an entry, newest first.
+#[tauri::command]
+pub async fn list_journal_revisions(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    entry_id: i64,
+) -> Result<Vec<Revision>, String> {
+    revisions::list(&db.0, &vault, entry_id).await
+}
+
+/// Line diff between two versions of an entry; a missing revision id stands
+/// for the entry as it is now.
+#[tauri::command]
+pub async fn diff_journal_revisions(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    entry_id: i64,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<RevisionDiff, String> {
+    revisions::diff(&db.0, &vault, entry_id, from, to).await
+}
+
+/// Bring back an earlier version of an entry. The replaced text is kept as a
+/// revision.
+#[tauri::command]
+pub async fn restore_journal_revision(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    entry_id: i64,
+    revision_id: i64,
+) -> Result<JournalEntry, String> {
+    revisions::restore(&db.0, &vault, entry_id, revision_id).await
+}
+
+#[tauri::command]
+pub async fn get_journal_revision_limit(db: State<'_, Db>) -> Result<u32, String> {
+    revisions::revision_limit(&db.0).await
+}
+
+/// Revisions kept per entry; older ones are dropped. 0 keeps none.
+#[tauri::command]
+pub async fn set_journal_revision_limit(db: State<'_, Db>, limit: u32) -> Result<u32, String> {
+    revisions::set_revision_limit(&db.0, limit).await?;
+    revisions::revision_limit(&db.0).await
+}
+
+/// Built-in templates, then the user's.
+#[tauri::command]
+pub async fn list_journal_templates(db: State<'_, Db>) -> Result<Vec<Template>, String> {
+    templates::list(&db.0).await
+}
+
+/// Create a template, or replace one of the user's with the same id.
+#[tauri::command]
+pub async fn save_journal_template(
+    db: State<'_, Db>,
+    template: Template,
+) -> Result<Template, String> {
+    templates::save(&db.0, template).await
+}
+
+/// Delete a template of the user's that no entry uses.
+#[tauri::command]
+pub async fn delete_journal_template(db: State<'_, Db>, id: String) -> Result<(), String> {
+    templates::delete(&db.0, &id).await
+}
+
+/// Answers to one template field over time, e.g. average emotion intensity
+/// per month.
+#[tauri::command]
+pub async fn aggregate_journal_field(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    query: AggregateQuery,
+) -> Result<Vec<Bucket>, String> {
+    templates::aggregate(&db.0, &vault, query).await
+}
+
+/// Moods from the journal and check-ins as a time series with rolling
+/// averages, and how they compare before resets, urges and relapses. For the
+/// stats page.
+#[tauri::command]
+pub async fn get_mood_analytics(
+    db: State<'_, Db>,
+    query: Option<MoodQuery>,
+) -> Result<MoodAnalytics, String> {
+    mood::compute(&db.0, query.unwrap_or_default()).await
+}
+
+/// Entries matching a full-text query, best match first. Needs the journal
+/// unlocked.
+#[tauri::command]
+pub async fn search_journal(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    query: SearchQuery,
+) -> Result<Vec<SearchHit>, String> {
+    search::search(&db.0, &vault, query).await
+}
+
+/// Write the entries `filter` picks, every entry without one, to
+/// `destination`: a folder for Markdown, a file for the other formats.
+#[tauri::command]
+pub async fn export_journal(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    format: ExportFormat,
+    destination: String,
+    filter: Option<ExportFilter>,
+) -> Result<ExportSummary, String> {
+    export::export(
+        &db.0,
+        &vault,
+        format,
+        filter.unwrap_or_default(),
+        Path::new(&destination),
+    )
+    .await
+}
+
+/// Import Markdown entries from a file, a folder or a zip archive, leaving
+/// out entries already in the journal.
+#[tauri::command]
+pub async fn import_journal(
+    app: AppHandle,
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    source: String,
+) -> Result<ImportSummary, String> {
+    let summary = import::import_markdown(&db.0, &vault, Path::new(&source)).await?;
+    if summary.imported > 0 {
+        engine::publish(&app, EventKind::JournalWritten);
+    }
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn get_journal_lock_status(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+) -> Result<VaultStatus, String> {
+    vault::status(&db.0, &vault).await
+}
+
+/// Encrypt every entry under `passphrase`. The journal stays unlocked.
+#[tauri::command]
+pub async fn enable_journal_encryption(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    passphrase: String,
+) -> Result<VaultStatus, String> {
+    let passphrase = Zeroizing::new(passphrase);
+    vault::enable(&db.0, &vault, &passphrase).await?;
+    vault::status(&db.0, &vault).await
+}
+
+/// Decrypt every entry and stop asking for a passphrase.
+#[tauri::command]
+pub async fn disable_journal_encryption(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    passphrase: String,
+) -> Result<VaultStatus, String> {
+    let passphrase = Zeroizing::new(passphrase);
+    vault::disable(&db.0, &vault, &passphrase).await?;
+    vault::status(&db.0, &vault).await
+}
+
+#[tauri::command]
+pub async fn unlock_journal(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    passphrase: String,
+) -> Result<VaultStatus, String> {
+    let passphrase = Zeroizing::new(passphrase);
+    vault::unlock(&db.0, &vault, &passphrase).await?;
+    vault::status(&db.0, &vault).await
+}
+
+/// Forget the key now rather than after the idle timeout.
+#[tauri::command]
+pub async fn lock_journal(
+    app: AppHandle,
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+) -> Result<VaultStatus, String> {
+    vault.lock();
+    let _ = app.emit("journal-locked", ());
+    vault::status(&db.0, &vault).await
+}
+
+/// Re-encrypt every entry under `new_passphrase`.
+#[tauri::command]
+pub async fn change_journal_passphrase(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<VaultStatus, String> {
+    let old_passphrase = Zeroizing::new(old_passphrase);
+    let new_passphrase = Zeroizing::new(new_passphrase);
+    vault::change_passphrase(&db.0, &vault, &old_passphrase, &new_passphrase).await?;
+    vault::status(&db.0, &vault).await
+}
+
+/// Idle minutes after which the key is dropped and the journal locks.
+#[tauri::command]
+pub async fn set_journal_lock_timeout(
+    db: State<'_, Db>,
+    vault: State<'_, JournalVault>,
+    minutes: u32,
+) -> Result<VaultStatus, String> {
+    vault::set_lock_after_minutes(&db.0, minutes).await?;
+    vault::status(&db.0, &vault).await
+}
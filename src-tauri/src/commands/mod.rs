@@ -1 +1,8 @@
+pub mod achievements;
+pub mod checkin;
+pub mod habits;
+pub mod journal;
+pub mod panic;
+pub mod recovery;
+pub mod risk;
 pub mod streak;
@@ -0,0 +1,91 @@
+use serde::Serialize;
+use tauri::{AppHandle, Runtime, State};
+
+use crate::achievements::engine;
+use crate::achievements::events::EventKind;
+use crate::db::Db;
+use crate::plugins::blocker::{clock, escalation, models::EscalationStatus};
+use crate::streak;
+
+/// Result of pressing the panic button.
+#[derive(Debug, Clone, Serialize)]
+pub struct PanicResult {
+    /// RFC 3339 time the press was logged at.
+    pub logged_at: String,
+    /// The escalation now in force, or null if escalation is turned off.
+    pub escalation: Option<EscalationStatus>,
+    /// Why the escalation could not be applied. The press is still logged.
+    pub escalation_error: Option<String>,
+}
+
+/// Log a panic button press and apply the configured escalation, if any.
+/// The press is logged first, so an escalation is never left unlogged.
+#[tauri::command]
+pub async fn use_panic_button<R: Runtime>(
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+) -> Result<PanicResult, String> {
+    let now = clock::now();
+    let logged_at = now.to_rfc3339();
+    let settings = streak::load_settings(&db.0).await?;
+
+    let mut tx =
+        db.0.begin()
+            .await
+            .map_err(|e| format!("Failed to log panic event: {}", e))?;
+    let id = sqlx::query("INSERT INTO panic_events (occurred_at) VALUES (?)")
+        .bind(&logged_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to log panic event: {}", e))?
+        .last_insert_rowid();
+    // Keep the frontend's daily summary in step
+    sqlx::query(
+        "INSERT INTO daily_logs (date, panic_used) VALUES (?, 1)
+         ON CONFLICT(date) DO UPDATE SET panic_used = 1",
+    )
+    .bind(streak::day_of(now, &settings).to_string())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to update daily log: {}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to log panic event: {}", e))?;
+
+    let (escalation, escalation_error) = match escalation::escalate(&app) {
+        Ok(escalation) => (escalation, None),
+        Err(e) => {
+            log::error!("Panic escalation failed: {}", e);
+            (None, Some(e))
+        }
+    };
+
+    if let Some(escalation) = &escalation {
+        let profile = serde_json::to_value(escalation.profile)
+            .ok()
+            .and_then(|v| v.as_str().map(String::from));
+        if let Err(e) = sqlx::query(
+            "UPDATE panic_events SET escalation_profile = ?, escalated_until = ? WHERE id = ?",
+        )
+        .bind(profile)
+        .bind(&escalation.until)
+        .bind(id)
+        .execute(&db.0)
+        .await
+        {
+            log::error!(
+                "Failed to record the escalation of panic event {}: {}",
+                id,
+                e
+            );
+        }
+    }
+
+    log::info!("Panic button used at {}", logged_at);
+    engine::publish(&app, EventKind::PanicUsed);
+    Ok(PanicResult {
+        logged_at,
+        escalation,
+        escalation_error,
+    })
+}
@@ -0,0 +1,50 @@
+use tauri::{AppHandle, State};
+
+use crate::db::Db;
+use crate::streak::recovery::{
+    self, EntryFilter, EntryKind, NewEntry, RecoveryEntry, TriggerCount,
+};
+
+/// Record a relapse and end the running streak.
+#[tauri::command]
+pub async fn log_relapse(
+    app: AppHandle,
+    db: State<'_, Db>,
+    entry: NewEntry,
+) -> Result<RecoveryEntry, String> {
+    let entry = recovery::log_relapse(&db.0, entry).await?;
+    if entry.streak_id.is_some() {
+        super::streak::protect_after_reset(&app);
+    }
+    Ok(entry)
+}
+
+/// Record an urge that was resisted.
+#[tauri::command]
+pub async fn log_urge(db: State<'_, Db>, entry: NewEntry) -> Result<RecoveryEntry, String> {
+    recovery::log_urge(&db.0, entry).await
+}
+
+/// Relapses matching `filter`, newest first.
+#[tauri::command]
+pub async fn get_relapses(
+    db: State<'_, Db>,
+    filter: Option<EntryFilter>,
+) -> Result<Vec<RecoveryEntry>, String> {
+    recovery::list(&db.0, EntryKind::Relapse, &filter.unwrap_or_default()).await
+}
+
+/// Urges matching `filter`, newest first.
+#[tauri::command]
+pub async fn get_urges(
+    db: State<'_, Db>,
+    filter: Option<EntryFilter>,
+) -> Result<Vec<RecoveryEntry>, String> {
+    recovery::list(&db.0, EntryKind::Urge, &filter.unwrap_or_default()).await
+}
+
+/// Trigger tags with how often each was used, most used first.
+#[tauri::command]
+pub async fn get_trigger_tags(db: State<'_, Db>) -> Result<Vec<TriggerCount>, String> {
+    recovery::trigger_counts(&db.0).await
+}
@@ -0,0 +1,27 @@
+use tauri::State;
+
+use crate::db::Db;
+use crate::streak::risk::{self, RiskProfile, RiskSettings};
+
+/// Resets and urges by weekday and hour, with the high-risk windows.
+#[tauri::command]
+pub async fn get_risk_profile(db: State<'_, Db>) -> Result<RiskProfile, String> {
+    let settings = risk::load_settings(&db.0).await?;
+    risk::profile(&db.0, &settings).await
+}
+
+#[tauri::command]
+pub async fn get_risk_settings(db: State<'_, Db>) -> Result<RiskSettings, String> {
+    risk::load_settings(&db.0).await
+}
+
+/// Change the alerts sent before high-risk windows. Returns the profile
+/// under the new settings.
+#[tauri::command]
+pub async fn set_risk_settings(
+    db: State<'_, Db>,
+    settings: RiskSettings,
+) -> Result<RiskProfile, String> {
+    risk::save_settings(&db.0, &settings).await?;
+    risk::profile(&db.0, &settings).await
+}
@@ -1,76 +1,99 @@
-use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
 
-/// Data structure representing the user's streak information.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StreakData {
-    /// ISO 8601 date string of when the current streak started, or null if not active.
-    pub start_date: Option<String>,
-    /// The best (longest) streak in days.
-    pub best_streak: u64,
-    /// Total number of streak resets.
-    pub total_resets: u64,
-    /// Current streak length in days (computed).
-    pub current_days: u64,
-}
+use crate::achievements::engine;
+use crate::achievements::events::EventKind;
+use crate::db::Db;
+use crate::plugins::blocker::reset_lock;
+use crate::scheduler::milestone_scheduler;
+use crate::streak::analytics::{self, StreakAnalytics};
+use crate::streak::milestones::{self, Milestone};
+use crate::streak::{self, habits, StreakData, StreakRecord, StreakSettings};
+
+// The streak commands act on the default habit.
 
 /// Get the current streak data.
-/// Computes current_days from start_date if present.
+/// Best streak and reset count are derived from the stored history.
 #[tauri::command]
-pub fn get_streak_data(start: Option<String>, best: Option<u64>, resets: Option<u64>) -> Result<StreakData, String> {
-    let best_streak = best.unwrap_or(0);
-    let total_resets = resets.unwrap_or(0);
+pub async fn get_streak_data(db: State<'_, Db>) -> Result<StreakData, String> {
+    streak::summary(&db.0, habits::default_id(&db.0).await?).await
+}
 
-    let current_days = if let Some(ref start_date) = start {
-        compute_days_since(start_date).unwrap_or(0)
-    } else {
-        0
-    };
+/// Start a new streak now. Returns the current data unchanged if a streak is
+/// already running.
+#[tauri::command]
+pub async fn start_streak(app: AppHandle, db: State<'_, Db>) -> Result<StreakData, String> {
+    let habit_id = habits::default_id(&db.0).await?;
+    streak::start(&db.0, habit_id).await?;
+    milestone_scheduler::reschedule();
+    engine::publish(&app, EventKind::StreakTick);
+    streak::summary(&db.0, habit_id).await
+}
 
-    Ok(StreakData {
-        start_date: start,
-        best_streak,
-        total_resets,
-        current_days,
-    })
+/// End the running streak, optionally noting why.
+#[tauri::command]
+pub async fn reset_streak(
+    app: AppHandle,
+    db: State<'_, Db>,
+    reason: Option<String>,
+) -> Result<StreakData, String> {
+    let habit_id = habits::default_id(&db.0).await?;
+    streak::reset(&db.0, habit_id, reason).await?;
+    protect_after_reset(&app);
+    streak::summary(&db.0, habit_id).await
 }
 
-/// Save streak data. Returns the updated StreakData with computed current_days.
+/// Completed streaks, newest first.
 #[tauri::command]
-pub fn save_streak_data(start: Option<String>, best: u64, resets: u64) -> Result<StreakData, String> {
-    let current_days = if let Some(ref start_date) = start {
-        compute_days_since(start_date).unwrap_or(0)
-    } else {
-        0
-    };
+pub async fn get_streak_history(db: State<'_, Db>) -> Result<Vec<StreakRecord>, String> {
+    streak::history(&db.0, habits::default_id(&db.0).await?).await
+}
 
-    log::info!(
-        "Streak data saved: start={:?}, best={}, resets={}, current={}",
-        start, best, resets, current_days
-    );
+/// Statistics over the saved streak history. `trend_length` is how many
+/// recent streaks the trend covers (default 10).
+#[tauri::command]
+pub async fn get_streak_analytics(
+    db: State<'_, Db>,
+    trend_length: Option<usize>,
+) -> Result<StreakAnalytics, String> {
+    analytics::compute(
+        &db.0,
+        trend_length.unwrap_or(analytics::DEFAULT_TREND_LENGTH),
+    )
+    .await
+}
 
-    Ok(StreakData {
-        start_date: start,
-        best_streak: best,
-        total_resets: resets,
-        current_days,
-    })
+#[tauri::command]
+pub async fn get_streak_settings(db: State<'_, Db>) -> Result<StreakSettings, String> {
+    streak::load_settings(&db.0).await
 }
 
-/// Compute the number of days since a given ISO 8601 date string.
-fn compute_days_since(date_str: &str) -> Result<u64, String> {
-    // Try parsing as a full RFC 3339 datetime first
-    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(date_str) {
-        let now = chrono::Utc::now();
-        let duration = now.signed_duration_since(dt);
-        return Ok(duration.num_days().max(0) as u64);
-    }
+/// Change when a new day begins, e.g. 4 for night owls.
+#[tauri::command]
+pub async fn set_streak_settings(
+    db: State<'_, Db>,
+    settings: StreakSettings,
+) -> Result<StreakData, String> {
+    streak::save_settings(&db.0, &settings).await?;
+    milestone_scheduler::reschedule();
+    streak::summary(&db.0, habits::default_id(&db.0).await?).await
+}
 
-    // Try parsing as a date-only string (YYYY-MM-DD)
-    if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-        let today = chrono::Utc::now().date_naive();
-        let duration = today.signed_duration_since(date);
-        return Ok(duration.num_days().max(0) as u64);
-    }
+/// When the running streak reaches each milestone, reached ones included.
+#[tauri::command]
+pub async fn get_streak_milestones(db: State<'_, Db>) -> Result<Vec<Milestone>, String> {
+    milestones::list(&db.0, habits::default_id(&db.0).await?).await
+}
 
-    Err(format!("Could not parse date: {}", date_str))
+/// Start the protective lock after a reset if the user set one up, and drop
+/// the ended streak's milestone notifications. The reset itself stands even
+/// if the lock can't be applied.
+pub(crate) fn protect_after_reset(app: &AppHandle) {
+    milestone_scheduler::reschedule();
+    match reset_lock::activate(app) {
+        Ok(Some(status)) => {
+            let _ = app.emit("reset-lock-started", status);
+        }
+        Ok(None) => {}
+        Err(e) => log::error!("Failed to apply reset lock: {}", e),
+    }
 }
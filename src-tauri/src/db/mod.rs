@@ -0,0 +1,305 @@
+use std::time::Duration;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Same file the frontend opens through `tauri-plugin-sql` as `sqlite:tamashii.db`.
+const DB_FILE: &str = "tamashii.db";
+
+/// Name of the habit the single streak of earlier versions becomes.
+const DEFAULT_HABIT_NAME: &str = "Main streak";
+
+/// Connection pool for the tables the Rust backend writes.
+pub struct Db(pub SqlitePool);
+
+/// Open the app database and create the backend-owned tables.
+pub async fn init<R: Runtime>(app: &AppHandle<R>) -> Result<Db, String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("No app config directory: {}", e))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+
+    let options = SqliteConnectOptions::new()
+        .filename(dir.join(DB_FILE))
+        .create_if_missing(true)
+        // Deleted and overwritten journal text is zeroed rather than left in
+        // free pages
+        .pragma("secure_delete", "ON")
+        // The frontend holds its own connection to the same file
+        .busy_timeout(Duration::from_secs(5));
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(4)
+        .connect_with(options)
+        .await
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+
+    for statement in SCHEMA {
+        sqlx::query(statement)
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to create tables: {}", e))?;
+    }
+    for (table, column, definition) in ADDED_COLUMNS {
+        add_column(&pool, table, column, definition).await?;
+    }
+    migrate_default_habit(&pool).await?;
+
+    Ok(Db(pool))
+}
+
+/// Read a value from the `settings` table.
+pub async fn get_setting(pool: &SqlitePool, key: &str) -> Result<Option<String>, String> {
+    let row: Option<(Option<String>,)> = sqlx::query_as("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to read setting {}: {}", key, e))?;
+    Ok(row.and_then(|(value,)| value))
+}
+
+/// Write a value to the `settings` table.
+pub async fn set_setting(pool: &SqlitePool, key: &str, value: &str) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO settings (key, value) VALUES (?, ?)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+    Ok(())
+}
+
+/// Add a column the backend needs to a table the frontend may have created
+/// without it.
+async fn add_column(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), String> {
+    let columns: Vec<(String,)> =
+        sqlx::query_as(&format!("SELECT name FROM pragma_table_info('{}')", table))
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to read {} columns: {}", table, e))?;
+    if columns.iter().any(|(name,)| name == column) {
+        return Ok(());
+    }
+
+    sqlx::query(&format!(
+        "ALTER TABLE {} ADD COLUMN {} {}",
+        table, column, definition
+    ))
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to add {}.{}: {}", table, column, e))?;
+    Ok(())
+}
+
+/// Create the default habit and move streaks that predate habits onto it.
+async fn migrate_default_habit(pool: &SqlitePool) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO habits (name, kind, created_at, is_default)
+         SELECT ?, 'avoid', COALESCE((SELECT MIN(start_date) FROM streaks), ?), 1
+         WHERE NOT EXISTS (SELECT 1 FROM habits WHERE is_default = 1)",
+    )
+    .bind(DEFAULT_HABIT_NAME)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create default habit: {}", e))?;
+
+    sqlx::query(
+        "UPDATE streaks SET habit_id = (SELECT id FROM habits WHERE is_default = 1)
+         WHERE habit_id IS NULL",
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to migrate streaks: {}", e))?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_streaks_habit ON streaks(habit_id)")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to migrate streaks: {}", e))?;
+    Ok(())
+}
+
+const SCHEMA: &[&str] = &[
+    // ── settings (shared with the frontend) ──────────────────────────────
+    "CREATE TABLE IF NOT EXISTS settings (
+        key   TEXT PRIMARY KEY,
+        value TEXT
+    )",
+    // ── streaks (shared with the frontend) ───────────────────────────────
+    // One row per streak; the running streak has no end_date yet.
+    "CREATE TABLE IF NOT EXISTS streaks (
+        id         INTEGER PRIMARY KEY,
+        start_date TEXT,
+        end_date   TEXT,
+        days       INTEGER
+    )",
+    // ── daily_logs (shared with the frontend) ────────────────────────────
+    "CREATE TABLE IF NOT EXISTS daily_logs (
+        date            TEXT PRIMARY KEY,
+        had_streak      INTEGER,
+        was_reset       INTEGER,
+        panic_used      INTEGER,
+        journal_written INTEGER
+    )",
+    // ── achievements (shared with the frontend) ──────────────────────────
+    "CREATE TABLE IF NOT EXISTS achievements (
+        id          TEXT PRIMARY KEY,
+        unlocked_at TEXT,
+        progress    INTEGER DEFAULT 0
+    )",
+    // ── journal_entries (shared with the frontend) ───────────────────────
+    "CREATE TABLE IF NOT EXISTS journal_entries (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        title      TEXT,
+        content    TEXT,
+        mood       TEXT,
+        created_at TEXT,
+        updated_at TEXT
+    )",
+    // ── journal_vault ────────────────────────────────────────────────────
+    // How the journal key is derived from the passphrase. One row while the
+    // journal is encrypted; entries then keep title and content in
+    // journal_entries.sealed.
+    "CREATE TABLE IF NOT EXISTS journal_vault (
+        id          INTEGER PRIMARY KEY CHECK (id = 1),
+        salt        BLOB NOT NULL,
+        memory_kib  INTEGER NOT NULL,
+        iterations  INTEGER NOT NULL,
+        parallelism INTEGER NOT NULL,
+        check_nonce BLOB NOT NULL,
+        check_value BLOB NOT NULL,
+        created_at  TEXT NOT NULL
+    )",
+    // ── journal_revisions ────────────────────────────────────────────────
+    // Earlier versions of an entry, saved as each update replaces them.
+    // Sealed like the entry they came from.
+    "CREATE TABLE IF NOT EXISTS journal_revisions (
+        id       INTEGER PRIMARY KEY AUTOINCREMENT,
+        entry_id INTEGER NOT NULL,
+        title    TEXT,
+        content  TEXT,
+        mood     TEXT,
+        saved_at TEXT NOT NULL,
+        nonce    BLOB,
+        sealed   BLOB
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_journal_revisions_entry ON journal_revisions(entry_id)",
+    // ── journal_templates ────────────────────────────────────────────────
+    // Templates the user made; the bundled ones are not stored. `definition`
+    // is the template as JSON.
+    "CREATE TABLE IF NOT EXISTS journal_templates (
+        id         TEXT PRIMARY KEY,
+        name       TEXT NOT NULL,
+        definition TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    )",
+    // ── check_ins ────────────────────────────────────────────────────────
+    // One evening check-in per streak day.
+    "CREATE TABLE IF NOT EXISTS check_ins (
+        date        TEXT PRIMARY KEY,
+        mood        INTEGER NOT NULL,
+        energy      INTEGER,
+        sleep_hours REAL,
+        urge_level  INTEGER,
+        note        TEXT,
+        recorded_at TEXT NOT NULL
+    )",
+    // ── domain_events ────────────────────────────────────────────────────
+    // Events achievements are counted from that no other table records.
+    "CREATE TABLE IF NOT EXISTS domain_events (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind        TEXT NOT NULL,
+        occurred_at TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_domain_events_kind ON domain_events(kind, occurred_at)",
+    // ── panic_events ─────────────────────────────────────────────────────
+    "CREATE TABLE IF NOT EXISTS panic_events (
+        id                 INTEGER PRIMARY KEY AUTOINCREMENT,
+        occurred_at        TEXT NOT NULL,
+        escalation_profile TEXT,
+        escalated_until    TEXT
+    )",
+    // ── relapses / urges ─────────────────────────────────────────────────
+    "CREATE TABLE IF NOT EXISTS relapses (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        occurred_at TEXT NOT NULL,
+        intensity   INTEGER NOT NULL,
+        notes       TEXT,
+        streak_id   INTEGER
+    )",
+    "CREATE TABLE IF NOT EXISTS relapse_triggers (
+        entry_id INTEGER NOT NULL REFERENCES relapses(id) ON DELETE CASCADE,
+        tag      TEXT NOT NULL,
+        PRIMARY KEY (entry_id, tag)
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_relapse_triggers_tag ON relapse_triggers(tag)",
+    "CREATE TABLE IF NOT EXISTS urges (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        occurred_at TEXT NOT NULL,
+        intensity   INTEGER NOT NULL,
+        notes       TEXT,
+        streak_id   INTEGER
+    )",
+    "CREATE TABLE IF NOT EXISTS urge_triggers (
+        entry_id INTEGER NOT NULL REFERENCES urges(id) ON DELETE CASCADE,
+        tag      TEXT NOT NULL,
+        PRIMARY KEY (entry_id, tag)
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_urge_triggers_tag ON urge_triggers(tag)",
+    // ── habits ───────────────────────────────────────────────────────────
+    // Avoid habits keep their streaks in `streaks`; do habits are counted
+    // from their check-ins.
+    "CREATE TABLE IF NOT EXISTS habits (
+        id            INTEGER PRIMARY KEY AUTOINCREMENT,
+        name          TEXT NOT NULL,
+        kind          TEXT NOT NULL,
+        created_at    TEXT NOT NULL,
+        reminder_time TEXT,
+        is_default    INTEGER NOT NULL DEFAULT 0,
+        archived_at   TEXT
+    )",
+    "CREATE TABLE IF NOT EXISTS habit_check_ins (
+        habit_id   INTEGER NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        date       TEXT NOT NULL,
+        checked_at TEXT NOT NULL,
+        PRIMARY KEY (habit_id, date)
+    )",
+    // Freeze tokens: earned at streak milestones, spent on declared
+    // exceptions. Spent days never count as clean.
+    "CREATE TABLE IF NOT EXISTS streak_freezes (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        habit_id    INTEGER NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        kind        TEXT NOT NULL,
+        recorded_at TEXT NOT NULL,
+        run_start   TEXT,
+        milestone   INTEGER,
+        day         TEXT,
+        streak_id   INTEGER REFERENCES streaks(id),
+        note        TEXT
+    )",
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_streak_freezes_milestone
+        ON streak_freezes(habit_id, run_start, milestone) WHERE kind <> 'spent'",
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_streak_freezes_day
+        ON streak_freezes(habit_id, day) WHERE kind = 'spent'",
+];
+
+/// Columns added to tables that predate the backend: (table, column, type).
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("streaks", "reset_reason", "TEXT"),
+    ("streaks", "habit_id", "INTEGER REFERENCES habits(id)"),
+    ("journal_entries", "nonce", "BLOB"),
+    ("journal_entries", "sealed", "BLOB"),
+    ("journal_entries", "template_id", "TEXT"),
+    ("journal_entries", "fields", "TEXT"),
+    ("journal_revisions", "template_id", "TEXT"),
+    ("journal_revisions", "fields", "TEXT"),
+];
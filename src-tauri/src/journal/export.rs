@@ -0,0 +1,281 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::templates::{self, FieldKind, Template};
+use super::vault::JournalVault;
+use super::{JournalEntry, MOODS};
+use crate::plugins::blocker::clock;
+use crate::streak;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One Markdown file with front matter per entry, in a folder.
+    Markdown,
+    /// The same Markdown files in a zip archive.
+    MarkdownZip,
+    /// Every entry in one JSON document.
+    Json,
+    /// A single page to read or print.
+    Html,
+}
+
+/// Which entries to export, like the filters of a `SearchQuery`. An empty
+/// filter exports every entry.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportFilter {
+    pub mood: Option<String>,
+    /// First and last day to include, as `YYYY-MM-DD`.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub entries: usize,
+}
+
+/// The JSON export document.
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    exported_at: String,
+    entries: &'a [JournalEntry],
+}
+
+/// Write the entries `filter` picks, oldest first, to `destination`: a
+/// folder for `Markdown`, a file otherwise. Entries are written decrypted, so
+/// an encrypted journal has to be unlocked.
+pub async fn export(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    format: ExportFormat,
+    filter: ExportFilter,
+    destination: &Path,
+) -> Result<ExportSummary, String> {
+    let mood = filter
+        .mood
+        .map(|m| m.trim().to_lowercase())
+        .filter(|m| !m.is_empty());
+    if let Some(mood) = mood.as_deref().filter(|m| !MOODS.contains(m)) {
+        return Err(format!("Unknown mood: {}", mood));
+    }
+    if let (Some(from), Some(to)) = (filter.from, filter.to) {
+        if from > to {
+            return Err("The export range ends before it starts".into());
+        }
+    }
+
+    let settings = streak::load_settings(pool).await?;
+    let mut entries = super::list(pool, vault).await?;
+    entries.retain(|entry| {
+        if mood.is_some() && entry.mood != mood {
+            return false;
+        }
+        match DateTime::parse_from_rfc3339(&entry.created_at) {
+            Ok(at) => {
+                let day = streak::day_of(at.with_timezone(&Utc), &settings);
+                filter.from.map_or(true, |from| day >= from)
+                    && filter.to.map_or(true, |to| day <= to)
+            }
+            Err(_) => filter.from.is_none() && filter.to.is_none(),
+        }
+    });
+    entries.reverse();
+
+    match format {
+        ExportFormat::Markdown => {
+            fs::create_dir_all(destination)
+                .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+            for entry in &entries {
+                write_file(&destination.join(file_name(entry)), &to_markdown(entry))?;
+            }
+        }
+        ExportFormat::MarkdownZip => write_zip(destination, &entries)?,
+        ExportFormat::Json => {
+            let json = serde_json::to_string_pretty(&JsonExport {
+                exported_at: clock::now().to_rfc3339(),
+                entries: &entries,
+            })
+            .map_err(|e| format!("Failed to serialize journal: {}", e))?;
+            write_file(destination, &json)?;
+        }
+        ExportFormat::Html => {
+            let templates = templates::list(pool).await?;
+            write_file(destination, &to_html(&entries, &templates))?
+        }
+    }
+
+    log::info!(
+        "Exported {} journal entries as {:?} to {}",
+        entries.len(),
+        format,
+        destination.display()
+    );
+    Ok(ExportSummary {
+        path: destination.display().to_string(),
+        entries: entries.len(),
+    })
+}
+
+/// An entry as Markdown with its title, mood and times in YAML front matter,
+/// the format `import` reads back.
+pub fn to_markdown(entry: &JournalEntry) -> String {
+    let mut text = String::from("---\n");
+    // A JSON string is also a valid double-quoted YAML string
+    text.push_str(&format!(
+        "title: {}\n",
+        serde_json::to_string(&entry.title).unwrap_or_default()
+    ));
+    if let Some(mood) = &entry.mood {
+        text.push_str(&format!("mood: {}\n", mood));
+    }
+    text.push_str(&format!("created_at: {}\n", entry.created_at));
+    text.push_str(&format!("updated_at: {}\n", entry.updated_at));
+    if let Some(template_id) = &entry.template_id {
+        text.push_str(&format!("template: {}\n", template_id));
+        // A JSON object on one line is a YAML flow mapping
+        text.push_str(&format!(
+            "fields: {}\n",
+            serde_json::to_string(&entry.fields).unwrap_or_default()
+        ));
+    }
+    text.push_str("---\n\n");
+    text.push_str(entry.content.trim_end());
+    text.push('\n');
+    text
+}
+
+/// `2024-06-01-a-hard-day-12.md`: local date, title and id, so names sort by
+/// date and never collide.
+fn file_name(entry: &JournalEntry) -> String {
+    let date = DateTime::parse_from_rfc3339(&entry.created_at)
+        .map(|at| at.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| "undated".into());
+    let mut slug = String::new();
+    for c in entry.title.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= 40 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        format!("{}-{}.md", date, entry.id)
+    } else {
+        format!("{}-{}-{}.md", date, slug, entry.id)
+    }
+}
+
+fn write_zip(path: &Path, entries: &[JournalEntry]) -> Result<(), String> {
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for entry in entries {
+        zip.start_file(file_name(entry), options)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        zip.write_all(to_markdown(entry).as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+fn write_file(path: &Path, text: &str) -> Result<(), String> {
+    fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn to_html(entries: &[JournalEntry], templates: &[Template]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Tamashii journal</title>\n<style>\n\
+         body { font-family: system-ui, sans-serif; max-width: 42rem; margin: 2rem auto; \
+         padding: 0 1rem; line-height: 1.6; color: #222; }\n\
+         article { border-bottom: 1px solid #ddd; padding: 1rem 0; page-break-inside: avoid; }\n\
+         h2 { margin: 0 0 0.25rem; }\n\
+         .meta { color: #777; font-size: 0.9rem; margin: 0 0 0.75rem; }\n\
+         dt { font-weight: 600; }\n\
+         dd { margin: 0 0 0.5rem; white-space: pre-wrap; }\n\
+         </style>\n</head>\n<body>\n<h1>Journal</h1>\n",
+    );
+    for entry in entries {
+        let written = DateTime::parse_from_rfc3339(&entry.created_at)
+            .map(|at| {
+                at.with_timezone(&Local)
+                    .format("%A, %B %-d, %Y at %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| entry.created_at.clone());
+        let meta = match &entry.mood {
+            Some(mood) => format!("{} · {}", written, mood),
+            None => written,
+        };
+
+        html.push_str("<article>\n");
+        if !entry.title.is_empty() {
+            html.push_str(&format!("<h2>{}</h2>\n", escape(&entry.title)));
+        }
+        html.push_str(&format!("<p class=\"meta\">{}</p>\n", escape(&meta)));
+        let template = templates
+            .iter()
+            .find(|t| entry.template_id.as_ref() == Some(&t.id));
+        if let Some(template) = template.filter(|_| !entry.fields.is_empty()) {
+            html.push_str("<dl>\n");
+            for field in &template.fields {
+                let Some(value) = entry.fields.get(&field.key) else {
+                    continue;
+                };
+                let answer = match value {
+                    Value::String(text) => text.clone(),
+                    Value::Bool(yes) => if *yes { "Yes" } else { "No" }.to_string(),
+                    Value::Array(answers) => answers
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    other if field.kind == FieldKind::Number => match &field.unit {
+                        Some(unit) => format!("{}{}", other, unit),
+                        None => other.to_string(),
+                    },
+                    other => other.to_string(),
+                };
+                html.push_str(&format!(
+                    "<dt>{}</dt><dd>{}</dd>\n",
+                    escape(&field.label),
+                    escape(&answer)
+                ));
+            }
+            html.push_str("</dl>\n");
+        }
+        for paragraph in entry.content.split("\n\n").filter(|p| !p.trim().is_empty()) {
+            html.push_str(&format!(
+                "<p>{}</p>\n",
+                escape(paragraph.trim()).replace('\n', "<br>\n")
+            ));
+        }
+        html.push_str("</article>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use zip::ZipArchive;
+
+use super::templates::Fields;
+use super::vault::{self, JournalVault};
+use super::{JournalInput, LOCKED};
+use crate::streak::days;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// Entries left out because one written at the same time already exists.
+    pub duplicates: usize,
+    /// Files that could not be imported, with the reason.
+    pub skipped: Vec<String>,
+}
+
+/// An entry read from a Markdown file.
+struct Parsed {
+    input: JournalInput,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// Import Markdown entries with front matter, as `export` writes them, from
+/// a `.md` file, a folder of them or a zip archive of them. An entry written
+/// at the same second as an existing one is taken to be that entry and left
+/// out.
+pub async fn import_markdown(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    source: &Path,
+) -> Result<ImportSummary, String> {
+    if vault::is_encrypted(pool).await? && !vault.is_unlocked() {
+        return Err(LOCKED.into());
+    }
+    let files = read_files(source)?;
+
+    let rows: Vec<(String,)> =
+        sqlx::query_as("SELECT created_at FROM journal_entries WHERE created_at IS NOT NULL")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to load journal: {}", e))?;
+    let mut seen = HashSet::new();
+    for at in rows
+        .iter()
+        .filter_map(|(at,)| DateTime::parse_from_rfc3339(at).ok())
+    {
+        first_at(&mut seen, at.with_timezone(&Utc));
+    }
+
+    let mut summary = ImportSummary::default();
+    for (name, text) in files {
+        let parsed = match parse(&text) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                summary.skipped.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+        if !first_at(&mut seen, parsed.created_at) {
+            summary.duplicates += 1;
+            continue;
+        }
+        match super::insert(
+            pool,
+            vault,
+            parsed.input,
+            parsed.created_at,
+            parsed.updated_at,
+        )
+        .await
+        {
+            Ok(_) => summary.imported += 1,
+            Err(e) => summary.skipped.push(format!("{}: {}", name, e)),
+        }
+    }
+
+    log::info!(
+        "Imported {} journal entries from {} ({} duplicates, {} skipped)",
+        summary.imported,
+        source.display(),
+        summary.duplicates,
+        summary.skipped.len()
+    );
+    Ok(summary)
+}
+
+/// Note an entry written at `at`; false if one written in the same second was
+/// seen before.
+fn first_at(seen: &mut HashSet<i64>, at: DateTime<Utc>) -> bool {
+    seen.insert(at.timestamp())
+}
+
+/// `(name, text)` of every Markdown file in `source`, in name order.
+fn read_files(source: &Path) -> Result<Vec<(String, String)>, String> {
+    let is_markdown = |name: &str| name.to_lowercase().ends_with(".md");
+    let mut files = Vec::new();
+
+    if source.is_dir() {
+        let dir = fs::read_dir(source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        for item in dir.flatten() {
+            let path = item.path();
+            let name = item.file_name().to_string_lossy().into_owned();
+            if path.is_file() && is_markdown(&name) {
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                files.push((name, text));
+            }
+        }
+    } else if source
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+    {
+        let file = File::open(source)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        let mut archive = ZipArchive::new(file)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        for i in 0..archive.len() {
+            let mut item = archive
+                .by_index(i)
+                .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+            let name = item.name().to_string();
+            if item.is_file() && is_markdown(&name) {
+                let mut text = String::new();
+                item.read_to_string(&mut text)
+                    .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+                files.push((name, text));
+            }
+        }
+    } else {
+        let text = fs::read_to_string(source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        files.push((name, text));
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Read an entry from Markdown with YAML front matter. `created_at` (or
+/// `date`) is required; `title`, `mood`, `updated_at`, `template` and
+/// `fields`, a JSON object, are optional.
+fn parse(text: &str) -> Result<Parsed, String> {
+    let mut lines = text.trim_start_matches('\u{feff}').lines();
+    if lines.next().map(str::trim) != Some("---") {
+        return Err("no front matter".into());
+    }
+    let mut fields = HashMap::new();
+    loop {
+        let line = lines.next().ok_or("the front matter is not closed")?;
+        if line.trim() == "---" {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.trim().to_lowercase(), unquote(value.trim())?);
+        }
+    }
+    let content = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+
+    let created_at = fields
+        .get("created_at")
+        .or_else(|| fields.get("date"))
+        .ok_or("no created_at in the front matter")?;
+    let created_at = parse_time(created_at)?;
+    let updated_at = match fields.get("updated_at") {
+        Some(at) => parse_time(at)?,
+        None => created_at,
+    };
+    let answers = match fields.get("fields") {
+        Some(json) => serde_json::from_str(json).map_err(|_| "fields is not a JSON object")?,
+        None => Fields::new(),
+    };
+
+    Ok(Parsed {
+        input: JournalInput {
+            title: fields.remove("title").unwrap_or_default(),
+            content,
+            mood: fields.remove("mood").filter(|m| !m.is_empty()),
+            template_id: fields.remove("template").filter(|t| !t.is_empty()),
+            fields: answers,
+        },
+        created_at,
+        updated_at,
+    })
+}
+
+fn unquote(value: &str) -> Result<String, String> {
+    if value.starts_with('"') {
+        return serde_json::from_str(value).map_err(|_| format!("bad quoted value {}", value));
+    }
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return Ok(inner.replace("''", "'"));
+    }
+    Ok(value.to_string())
+}
+
+/// An RFC 3339 time, or a `YYYY-MM-DD` date taken as local midnight.
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| days::day_start(date, &Local, 0))
+        .map_err(|_| format!("unreadable time {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::export::to_markdown;
+    use crate::journal::JournalEntry;
+
+    fn entry() -> JournalEntry {
+        let mut fields = Fields::new();
+        fields.insert("situation".into(), "Late night, alone".into());
+        fields.insert("intensity".into(), 7.into());
+        JournalEntry {
+            id: 12,
+            title: "A \"hard\" day: part 2".into(),
+            content: "First line\n\n---\nStill the body".into(),
+            mood: Some("bad".into()),
+            created_at: "2024-06-01T21:15:30+02:00".into(),
+            updated_at: "2024-06-02T08:00:00+00:00".into(),
+            template_id: Some("thought_record".into()),
+            fields,
+        }
+    }
+
+    #[test]
+    fn markdown_round_trips() {
+        let entry = entry();
+        let parsed = parse(&to_markdown(&entry)).unwrap();
+
+        assert_eq!(parsed.input.title, entry.title);
+        assert_eq!(parsed.input.content, entry.content);
+        assert_eq!(parsed.input.mood, entry.mood);
+        assert_eq!(parsed.input.template_id, entry.template_id);
+        assert_eq!(parsed.input.fields, entry.fields);
+        assert_eq!(
+            parsed.created_at,
+            DateTime::parse_from_rfc3339(&entry.created_at).unwrap()
+        );
+        assert_eq!(
+            parsed.updated_at,
+            DateTime::parse_from_rfc3339(&entry.updated_at).unwrap()
+        );
+    }
+
+    #[test]
+    fn markdown_without_optional_front_matter() {
+        let entry = JournalEntry {
+            title: String::new(),
+            mood: None,
+            template_id: None,
+            fields: Fields::new(),
+            ..entry()
+        };
+        let parsed = parse(&to_markdown(&entry)).unwrap();
+
+        assert_eq!(parsed.input.title, "");
+        assert_eq!(parsed.input.mood, None);
+        assert_eq!(parsed.input.template_id, None);
+        assert!(parsed.input.fields.is_empty());
+    }
+
+    #[test]
+    fn duplicates_are_entries_written_in_the_same_second() {
+        let at = |text: &str| parse_time(text).unwrap();
+        let mut seen = HashSet::new();
+
+        assert!(first_at(&mut seen, at("2024-06-01T21:15:30+02:00")));
+        // The same instant in another offset, and later in the same second
+        assert!(!first_at(&mut seen, at("2024-06-01T19:15:30Z")));
+        assert!(!first_at(&mut seen, at("2024-06-01T19:15:30.750Z")));
+        assert!(first_at(&mut seen, at("2024-06-01T19:15:31Z")));
+    }
+}
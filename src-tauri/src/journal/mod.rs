@@ -0,0 +1,370 @@
+pub mod export;
+pub mod import;
+pub mod mood;
+pub mod revisions;
+pub mod search;
+pub mod templates;
+pub mod vault;
+
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::plugins::blocker::clock;
+use crate::streak;
+use templates::Fields;
+use vault::{EntryText, JournalVault, Row};
+
+/// Moods the frontend offers, best first.
+pub const MOODS: &[&str] = &["great", "good", "okay", "bad", "terrible"];
+
+/// One entry of `journal_entries`, decrypted. Field names follow the
+/// frontend's `JournalEntry`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub id: i64,
+    pub title: String,
+    pub content: String,
+    pub mood: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// The template the entry was written with, if any, and its answers.
+    pub template_id: Option<String>,
+    pub fields: Fields,
+}
+
+/// An entry as written by the user.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalInput {
+    pub title: String,
+    pub content: String,
+    pub mood: Option<String>,
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub fields: Fields,
+}
+
+const LOCKED: &str = "The journal is locked";
+
+/// A row of `journal_entries`. Title, content and fields of an encrypted
+/// entry are in `sealed`.
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    id: i64,
+    title: String,
+    content: String,
+    mood: Option<String>,
+    created_at: String,
+    updated_at: String,
+    template_id: Option<String>,
+    fields: Option<String>,
+    nonce: Option<Vec<u8>>,
+    sealed: Option<Vec<u8>>,
+}
+
+const ENTRY_COLUMNS: &str = "id, COALESCE(title, '') AS title, COALESCE(content, '') AS content,
+    mood, COALESCE(created_at, '') AS created_at, COALESCE(updated_at, created_at, '') AS updated_at,
+    template_id, fields, nonce, sealed";
+
+/// Every entry, newest first.
+pub async fn list(pool: &SqlitePool, vault: &JournalVault) -> Result<Vec<JournalEntry>, String> {
+    let rows: Vec<EntryRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM journal_entries ORDER BY julianday(created_at) DESC, id DESC",
+        ENTRY_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load journal: {}", e))?;
+    rows.into_iter().map(|row| reveal(row, vault)).collect()
+}
+
+pub async fn get(pool: &SqlitePool, vault: &JournalVault, id: i64) -> Result<JournalEntry, String> {
+    let row: EntryRow = sqlx::query_as(&format!(
+        "SELECT {} FROM journal_entries WHERE id = ?",
+        ENTRY_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load journal entry: {}", e))?
+    .ok_or_else(|| format!("No journal entry with id {}", id))?;
+    reveal(row, vault)
+}
+
+/// Save a new entry and mark today as journaled in the daily log.
+pub async fn create(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    input: JournalInput,
+) -> Result<JournalEntry, String> {
+    let now = clock::now();
+    insert(pool, vault, input, now, now).await
+}
+
+/// Save an entry written at `created_at`, e.g. one being imported, and mark
+/// that day as journaled in the daily log.
+pub(crate) async fn insert(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    input: JournalInput,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+) -> Result<JournalEntry, String> {
+    let input = validate(templates::check(pool, input).await?)?;
+    let settings = streak::load_settings(pool).await?;
+
+    // The text is sealed for its row, so it is written once the row has an id
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to save journal entry: {}", e))?;
+    let cipher = sealer(&mut tx, vault).await?;
+    let id = sqlx::query(
+        "INSERT INTO journal_entries (title, content, mood, created_at, updated_at, template_id)
+         VALUES ('', '', ?, ?, ?, ?)",
+    )
+    .bind(&input.mood)
+    .bind(created_at.to_rfc3339())
+    .bind(updated_at.to_rfc3339())
+    .bind(&input.template_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save journal entry: {}", e))?
+    .last_insert_rowid();
+    let stored = store(cipher.as_ref(), id, &input)?;
+    sqlx::query(
+        "UPDATE journal_entries SET title = ?, content = ?, fields = ?, nonce = ?, sealed = ?
+         WHERE id = ?",
+    )
+    .bind(&stored.title)
+    .bind(&stored.content)
+    .bind(&stored.fields)
+    .bind(&stored.nonce)
+    .bind(&stored.sealed)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save journal entry: {}", e))?;
+    sqlx::query(
+        "INSERT INTO daily_logs (date, journal_written) VALUES (?, 1)
+         ON CONFLICT(date) DO UPDATE SET journal_written = 1",
+    )
+    .bind(streak::day_of(created_at, &settings).to_string())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to update daily log: {}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to save journal entry: {}", e))?;
+
+    let entry = get(pool, vault, id).await?;
+    vault.index.upsert(&entry).await;
+    Ok(entry)
+}
+
+/// Save new text for an entry. The text it replaces is kept as a revision.
+pub async fn update(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    id: i64,
+    input: JournalInput,
+) -> Result<JournalEntry, String> {
+    let input = validate(templates::check(pool, input).await?)?;
+    let current = get(pool, vault, id).await?;
+    if current.title == input.title
+        && current.content == input.content
+        && current.mood == input.mood
+        && current.template_id == input.template_id
+        && current.fields == input.fields
+    {
+        return Ok(current);
+    }
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to save journal entry: {}", e))?;
+    let cipher = sealer(&mut tx, vault).await?;
+    let stored = store(cipher.as_ref(), id, &input)?;
+    revisions::keep(&mut tx, cipher.as_ref(), id).await?;
+    sqlx::query(
+        "UPDATE journal_entries SET title = ?, content = ?, mood = ?, updated_at = ?,
+             template_id = ?, fields = ?, nonce = ?, sealed = ?
+         WHERE id = ?",
+    )
+    .bind(&stored.title)
+    .bind(&stored.content)
+    .bind(&input.mood)
+    .bind(clock::now().to_rfc3339())
+    .bind(&input.template_id)
+    .bind(&stored.fields)
+    .bind(&stored.nonce)
+    .bind(&stored.sealed)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save journal entry: {}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to save journal entry: {}", e))?;
+    revisions::prune(pool, id).await?;
+
+    let entry = get(pool, vault, id).await?;
+    vault.index.upsert(&entry).await;
+    Ok(entry)
+}
+
+/// Delete an entry with its revisions.
+pub async fn delete(pool: &SqlitePool, vault: &JournalVault, id: i64) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to delete journal entry: {}", e))?;
+    for statement in [
+        "DELETE FROM journal_revisions WHERE entry_id = ?",
+        "DELETE FROM journal_entries WHERE id = ?",
+    ] {
+        sqlx::query(statement)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to delete journal entry: {}", e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to delete journal entry: {}", e))?;
+    vault.index.remove(id).await;
+    Ok(())
+}
+
+/// Columns an entry's text is saved in.
+struct Stored {
+    title: String,
+    content: String,
+    /// The fields as JSON, unless there are none.
+    fields: Option<String>,
+    nonce: Option<Vec<u8>>,
+    sealed: Option<Vec<u8>>,
+}
+
+/// The cipher to seal text with while the journal is encrypted, which needs
+/// it unlocked. `None` while it isn't encrypted. Read through the write's
+/// `tx`, so turning encryption on or off can't slip in before the write.
+async fn sealer(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    vault: &JournalVault,
+) -> Result<Option<XChaCha20Poly1305>, String> {
+    if !vault::is_encrypted_in(tx).await? {
+        return Ok(None);
+    }
+    vault.cipher().map(Some).ok_or_else(|| LOCKED.to_string())
+}
+
+/// How to save `input` as entry `id`: sealed with `cipher`, in the clear
+/// without one.
+fn store(
+    cipher: Option<&XChaCha20Poly1305>,
+    id: i64,
+    input: &JournalInput,
+) -> Result<Stored, String> {
+    let Some(cipher) = cipher else {
+        return Ok(Stored {
+            title: input.title.clone(),
+            content: input.content.clone(),
+            fields: vault::fields_column(&input.fields)?,
+            nonce: None,
+            sealed: None,
+        });
+    };
+    let sealed = vault::seal(
+        cipher,
+        Row::entry(id),
+        &EntryText {
+            title: input.title.clone(),
+            content: input.content.clone(),
+            fields: input.fields.clone(),
+        },
+    )?;
+    Ok(Stored {
+        title: String::new(),
+        content: String::new(),
+        fields: None,
+        nonce: Some(sealed.nonce),
+        sealed: Some(sealed.sealed),
+    })
+}
+
+/// The text of a row: the clear columns, or what `sealed` decrypts to.
+fn open_text(
+    vault: &JournalVault,
+    row: Row,
+    title: String,
+    content: String,
+    fields: Option<String>,
+    nonce: Option<Vec<u8>>,
+    sealed: Option<Vec<u8>>,
+) -> Result<EntryText, String> {
+    match (nonce, sealed) {
+        (Some(nonce), Some(sealed)) => {
+            let cipher = vault.cipher().ok_or(LOCKED)?;
+            vault::open(&cipher, row, &nonce, &sealed)
+        }
+        _ => Ok(EntryText {
+            title,
+            content,
+            fields: vault::parse_fields(fields.as_deref())?,
+        }),
+    }
+}
+
+/// The entry in `row`, decrypted if it is sealed.
+fn reveal(row: EntryRow, vault: &JournalVault) -> Result<JournalEntry, String> {
+    let EntryText {
+        title,
+        content,
+        fields,
+    } = open_text(
+        vault,
+        Row::entry(row.id),
+        row.title,
+        row.content,
+        row.fields,
+        row.nonce,
+        row.sealed,
+    )
+    .map_err(|e| format!("Journal entry {}: {}", row.id, e))?;
+    Ok(JournalEntry {
+        id: row.id,
+        title,
+        content,
+        mood: row.mood,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+        template_id: row.template_id,
+        fields,
+    })
+}
+
+fn validate(input: JournalInput) -> Result<JournalInput, String> {
+    let title = input.title.trim().to_string();
+    let content = input.content.trim().to_string();
+    if title.is_empty() && content.is_empty() && input.fields.is_empty() {
+        return Err("A journal entry needs a title, some text or an answered field".into());
+    }
+    let mood = input
+        .mood
+        .map(|m| m.trim().to_lowercase())
+        .filter(|m| !m.is_empty());
+    if let Some(mood) = mood.as_deref().filter(|m| !MOODS.contains(m)) {
+        return Err(format!("Unknown mood: {}", mood));
+    }
+    Ok(JournalInput {
+        title,
+        content,
+        mood,
+        ..input
+    })
+}
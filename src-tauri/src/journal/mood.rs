@@ -0,0 +1,465 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::MOODS;
+use crate::streak::{self, days, habits, StreakSettings};
+
+/// Days the rolling average covers unless the query says otherwise.
+const DEFAULT_WINDOW_DAYS: u32 = 7;
+
+/// Days before a reset, urge or relapse whose moods count as preceding it.
+const DEFAULT_LOOKBACK_DAYS: u32 = 3;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MoodQuery {
+    /// First and last day to include, as `YYYY-MM-DD`.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub window_days: Option<u32>,
+    pub lookback_days: Option<u32>,
+}
+
+/// Moods from journal entries and daily check-ins, scored 1 (terrible) to 5
+/// (great), over time and around resets, urges and relapses.
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodAnalytics {
+    pub window_days: u32,
+    pub lookback_days: u32,
+    /// Average score of every rating in the range.
+    pub baseline: Option<f64>,
+    /// One point per day with a rating, oldest first.
+    pub series: Vec<MoodPoint>,
+    /// Resets, urges and relapses to draw along the series.
+    pub markers: Vec<MoodMarker>,
+    pub before_resets: PrecedingMood,
+    pub before_urges: PrecedingMood,
+    pub before_relapses: PrecedingMood,
+    /// Pearson correlation between a day's score and a reset on that day or
+    /// within `lookback_days` after it. Negative when low moods come before
+    /// resets. Null without enough variation to tell.
+    pub reset_correlation: Option<f64>,
+    /// The last mood before each urge and relapse, best mood first.
+    pub by_mood: Vec<MoodOutcomes>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodPoint {
+    /// `YYYY-MM-DD`, in streak days.
+    pub day: String,
+    pub average: f64,
+    pub entries: u32,
+    /// Average over this day and the `window_days - 1` before it.
+    pub rolling_average: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerKind {
+    Reset,
+    Urge,
+    Relapse,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodMarker {
+    pub day: String,
+    pub kind: MarkerKind,
+}
+
+/// Moods recorded in the `lookback_days` before one kind of event.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrecedingMood {
+    pub events: u32,
+    /// Events with at least one rating before them.
+    pub with_mood: u32,
+    pub average: Option<f64>,
+    /// `average` minus the baseline.
+    pub difference: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodOutcomes {
+    pub mood: &'static str,
+    /// Resisted urges this was the last mood before.
+    pub urges: u32,
+    pub relapses: u32,
+    /// Share of the two that were relapses, 0–1.
+    pub relapse_share: Option<f64>,
+}
+
+/// A rated moment: when it was recorded and its score.
+type Rating = (DateTime<Utc>, f64);
+
+pub async fn compute(pool: &SqlitePool, query: MoodQuery) -> Result<MoodAnalytics, String> {
+    let window_days = query.window_days.unwrap_or(DEFAULT_WINDOW_DAYS);
+    let lookback_days = query.lookback_days.unwrap_or(DEFAULT_LOOKBACK_DAYS);
+    if !(1..=365).contains(&window_days) || !(1..=365).contains(&lookback_days) {
+        return Err("Windows must be 1 to 365 days".into());
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err("The range ends before it starts".into());
+        }
+    }
+    let settings = streak::load_settings(pool).await?;
+    let in_range = |at: &DateTime<Utc>| {
+        let day = streak::day_of(*at, &settings);
+        query.from.map_or(true, |from| day >= from) && query.to.map_or(true, |to| day <= to)
+    };
+
+    let moods: Vec<(String, String)> = sqlx::query_as(
+        "SELECT created_at, mood FROM journal_entries
+         WHERE mood IS NOT NULL AND created_at IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load journal moods: {}", e))?;
+    let check_ins: Vec<(String, String, i64)> =
+        sqlx::query_as("SELECT date, recorded_at, mood FROM check_ins")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to load check-ins: {}", e))?;
+    let mut ratings: Vec<Rating> = moods
+        .iter()
+        .filter_map(|(at, mood)| Some((parse_time(at)?, score(mood)?)))
+        .chain(check_ins.iter().filter_map(|(date, recorded_at, mood)| {
+            let date: NaiveDate = date.parse().ok()?;
+            let recorded_at = parse_time(recorded_at)?;
+            // A check-in made later for an earlier day counts at that day's end
+            let at = if streak::day_of(recorded_at, &settings) == date {
+                recorded_at
+            } else {
+                days::day_start(date + Duration::days(1), &Local, settings.day_starts_at)
+                    - Duration::seconds(1)
+            };
+            Some((at, *mood as f64)).filter(|(_, score)| (1.0..=5.0).contains(score))
+        }))
+        .filter(|(at, _)| in_range(at))
+        .collect();
+    ratings.sort_by_key(|(at, _)| *at);
+
+    // Streaks a logged relapse ended are counted as that relapse
+    let ends: Vec<(String, bool)> = sqlx::query_as(
+        "SELECT end_date, EXISTS (SELECT 1 FROM relapses WHERE relapses.streak_id = streaks.id)
+         FROM streaks WHERE habit_id = ? AND end_date IS NOT NULL",
+    )
+    .bind(habits::default_id(pool).await?)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load streak history: {}", e))?;
+    let resets: Vec<(String,)> = ends
+        .iter()
+        .filter(|(_, relapse)| !relapse)
+        .map(|(at, _)| (at.clone(),))
+        .collect();
+    let ends: Vec<(String,)> = ends.into_iter().map(|(at, _)| (at,)).collect();
+    let urges = occurrences(pool, "urges").await?;
+    let relapses = occurrences(pool, "relapses").await?;
+    let times = |rows: &[(String,)]| -> Vec<DateTime<Utc>> {
+        let mut times: Vec<_> = rows
+            .iter()
+            .filter_map(|(at,)| parse_time(at))
+            .filter(|at| in_range(at))
+            .collect();
+        times.sort();
+        times
+    };
+    let (resets, urges, relapses) = (times(&resets), times(&urges), times(&relapses));
+    let ends = times(&ends);
+
+    let baseline = average(ratings.iter().map(|(_, score)| *score));
+    let lookback = Duration::days(lookback_days as i64);
+    let series = series(&ratings, &settings, window_days);
+    let reset_days: Vec<NaiveDate> = ends
+        .iter()
+        .map(|at| streak::day_of(*at, &settings))
+        .collect();
+
+    let mut events: Vec<(DateTime<Utc>, MarkerKind)> = resets
+        .iter()
+        .map(|at| (*at, MarkerKind::Reset))
+        .chain(urges.iter().map(|at| (*at, MarkerKind::Urge)))
+        .chain(relapses.iter().map(|at| (*at, MarkerKind::Relapse)))
+        .collect();
+    events.sort_by_key(|(at, _)| *at);
+    let markers = events
+        .into_iter()
+        .map(|(at, kind)| MoodMarker {
+            day: streak::day_of(at, &settings).to_string(),
+            kind,
+        })
+        .collect();
+
+    Ok(MoodAnalytics {
+        window_days,
+        lookback_days,
+        baseline,
+        before_resets: preceding(&ratings, &resets, lookback, baseline),
+        before_urges: preceding(&ratings, &urges, lookback, baseline),
+        before_relapses: preceding(&ratings, &relapses, lookback, baseline),
+        reset_correlation: reset_correlation(&series, &reset_days, lookback_days),
+        by_mood: by_mood(&ratings, &urges, &relapses, lookback),
+        series,
+        markers,
+    })
+}
+
+/// Score of a mood: 5 for the best in `MOODS` down to 1 for the worst.
+fn score(mood: &str) -> Option<f64> {
+    MOODS
+        .iter()
+        .position(|m| *m == mood)
+        .map(|i| (MOODS.len() - i) as f64)
+}
+
+async fn occurrences(pool: &SqlitePool, table: &str) -> Result<Vec<(String,)>, String> {
+    sqlx::query_as(&format!("SELECT occurred_at FROM {}", table))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load {}: {}", table, e))
+}
+
+fn parse_time(at: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(at)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0u32), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Daily averages with a rolling average over `window_days` calendar days.
+fn series(ratings: &[Rating], settings: &StreakSettings, window_days: u32) -> Vec<MoodPoint> {
+    let mut days: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+    for (at, score) in ratings {
+        days.entry(streak::day_of(*at, settings))
+            .or_default()
+            .push(*score);
+    }
+    days.iter()
+        .map(|(day, scores)| {
+            let first = *day - Duration::days(window_days as i64 - 1);
+            let rolling = average(
+                days.range(first..=*day)
+                    .flat_map(|(_, scores)| scores.iter().copied()),
+            );
+            MoodPoint {
+                day: day.to_string(),
+                average: scores.iter().sum::<f64>() / scores.len() as f64,
+                entries: scores.len() as u32,
+                rolling_average: rolling.unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// Ratings recorded in the `lookback` before each of `events`.
+fn preceding(
+    ratings: &[Rating],
+    events: &[DateTime<Utc>],
+    lookback: Duration,
+    baseline: Option<f64>,
+) -> PrecedingMood {
+    let mut scores = Vec::new();
+    let mut with_mood = 0;
+    for at in events {
+        let before: Vec<f64> = ratings
+            .iter()
+            .filter(|(rated, _)| *rated < *at && *rated >= *at - lookback)
+            .map(|(_, score)| *score)
+            .collect();
+        if !before.is_empty() {
+            with_mood += 1;
+        }
+        scores.extend(before);
+    }
+    let average = average(scores.into_iter());
+    PrecedingMood {
+        events: events.len() as u32,
+        with_mood,
+        average,
+        difference: average.zip(baseline).map(|(a, b)| a - b),
+    }
+}
+
+/// Correlation of each day's average with whether a reset follows within
+/// `lookback_days`.
+fn reset_correlation(
+    series: &[MoodPoint],
+    reset_days: &[NaiveDate],
+    lookback_days: u32,
+) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = series
+        .iter()
+        .filter_map(|point| {
+            let day: NaiveDate = point.day.parse().ok()?;
+            let reset_follows = reset_days.iter().any(|reset| {
+                let gap = (*reset - day).num_days();
+                (0..=lookback_days as i64).contains(&gap)
+            });
+            Some((point.average, if reset_follows { 1.0 } else { 0.0 }))
+        })
+        .collect();
+    pearson(&pairs)
+}
+
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 3 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+/// Count urges and relapses by the last mood rated within `lookback` before
+/// them.
+fn by_mood(
+    ratings: &[Rating],
+    urges: &[DateTime<Utc>],
+    relapses: &[DateTime<Utc>],
+    lookback: Duration,
+) -> Vec<MoodOutcomes> {
+    let mut outcomes: Vec<MoodOutcomes> = MOODS
+        .iter()
+        .map(|mood| MoodOutcomes {
+            mood,
+            urges: 0,
+            relapses: 0,
+            relapse_share: None,
+        })
+        .collect();
+    let last_mood = |at: &DateTime<Utc>| {
+        ratings
+            .iter()
+            .rev()
+            .find(|(rated, _)| *rated < *at && *rated >= *at - lookback)
+            .map(|(_, score)| MOODS.len() - *score as usize)
+    };
+    for at in urges {
+        if let Some(i) = last_mood(at) {
+            outcomes[i].urges += 1;
+        }
+    }
+    for at in relapses {
+        if let Some(i) = last_mood(at) {
+            outcomes[i].relapses += 1;
+        }
+    }
+    for outcome in &mut outcomes {
+        let total = outcome.urges + outcome.relapses;
+        outcome.relapse_share = (total > 0).then(|| outcome.relapses as f64 / total as f64);
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(d: u32, h: u32) -> DateTime<Utc> {
+        Local
+            .with_ymd_and_hms(2024, 6, d, h, 0, 0)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn close(value: Option<f64>, expected: f64) -> bool {
+        value.is_some_and(|v| (v - expected).abs() < 1e-9)
+    }
+
+    #[test]
+    fn rolling_average_covers_the_window_in_calendar_days() {
+        let ratings = [
+            (at(1, 9), 1.0),
+            (at(1, 20), 3.0),
+            (at(2, 12), 5.0),
+            (at(4, 12), 4.0),
+        ];
+        let series = series(&ratings, &StreakSettings::default(), 3);
+        let points: Vec<(&str, f64, u32, f64)> = series
+            .iter()
+            .map(|p| (p.day.as_str(), p.average, p.entries, p.rolling_average))
+            .collect();
+        assert_eq!(
+            points,
+            [
+                ("2024-06-01", 2.0, 2, 2.0),
+                ("2024-06-02", 5.0, 1, 3.0),
+                // June 1 has left the window, June 3 has no ratings
+                ("2024-06-04", 4.0, 1, 4.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn preceding_takes_ratings_within_the_lookback() {
+        let ratings = [(at(1, 12), 1.0), (at(3, 12), 2.0), (at(4, 11), 4.0)];
+        let events = [at(4, 12), at(10, 12)];
+        let mood = preceding(&ratings, &events, Duration::days(2), Some(4.0));
+        assert_eq!(mood.events, 2);
+        assert_eq!(mood.with_mood, 1);
+        assert!(close(mood.average, 3.0));
+        assert!(close(mood.difference, -1.0));
+
+        let mood = preceding(&ratings, &[at(1, 12)], Duration::days(2), Some(4.0));
+        assert_eq!(mood.with_mood, 0);
+        assert_eq!(mood.average, None);
+        assert_eq!(mood.difference, None);
+    }
+
+    #[test]
+    fn pearson_needs_three_varying_pairs() {
+        assert_eq!(pearson(&[(1.0, 1.0), (2.0, 0.0)]), None);
+        assert_eq!(pearson(&[(3.0, 1.0), (3.0, 0.0), (3.0, 1.0)]), None);
+        assert!(close(pearson(&[(1.0, 2.0), (2.0, 4.0), (3.0, 6.0)]), 1.0));
+        assert!(close(
+            pearson(&[(1.0, 1.0), (2.0, 0.0), (3.0, 0.0), (4.0, 0.0)]),
+            -0.774_596_669_241_483_4
+        ));
+    }
+
+    #[test]
+    fn outcomes_follow_the_last_mood_before_each_event() {
+        let ratings = [
+            (at(1, 9), score("great").unwrap()),
+            (at(1, 10), score("bad").unwrap()),
+            (at(5, 9), score("good").unwrap()),
+        ];
+        let urges = [at(1, 12), at(5, 12), at(9, 12)];
+        let relapses = [at(1, 13)];
+        let outcomes = by_mood(&ratings, &urges, &relapses, Duration::days(1));
+        let counts: Vec<(&str, u32, u32, Option<f64>)> = outcomes
+            .iter()
+            .map(|o| (o.mood, o.urges, o.relapses, o.relapse_share))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("great", 0, 0, None),
+                ("good", 1, 0, Some(0.0)),
+                ("okay", 0, 0, None),
+                ("bad", 1, 1, Some(0.5)),
+                ("terrible", 0, 0, None),
+            ]
+        );
+    }
+}
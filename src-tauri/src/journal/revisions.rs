@@ -0,0 +1,451 @@
+use chacha20poly1305::XChaCha20Poly1305;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use super::templates::Fields;
+use super::vault::{self, EntryText, JournalVault, Row};
+use super::{JournalEntry, JournalInput, LOCKED};
+use crate::db;
+
+const KEY_REVISION_LIMIT: &str = "journal_revision_limit";
+
+/// Revisions kept per entry unless the user picks another limit.
+const DEFAULT_REVISION_LIMIT: u32 = 20;
+
+const MAX_REVISION_LIMIT: u32 = 500;
+
+/// Most cells the LCS table of a diff may have. Middles beyond it are shown
+/// as removed and added whole rather than matched line by line.
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// An earlier version of an entry, decrypted.
+#[derive(Debug, Clone, Serialize)]
+pub struct Revision {
+    pub id: i64,
+    pub entry_id: i64,
+    pub title: String,
+    pub content: String,
+    pub mood: Option<String>,
+    /// When this version was saved; it was current until the next one.
+    pub saved_at: String,
+    pub template_id: Option<String>,
+    pub fields: Fields,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub change: Change,
+    pub text: String,
+}
+
+/// How one version of an entry became another: title, mood and fields side
+/// by side, content line by line.
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionDiff {
+    pub title_before: String,
+    pub title_after: String,
+    pub mood_before: Option<String>,
+    pub mood_after: Option<String>,
+    pub fields_before: Fields,
+    pub fields_after: Fields,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(sqlx::FromRow)]
+struct RevisionRow {
+    id: i64,
+    entry_id: i64,
+    title: String,
+    content: String,
+    mood: Option<String>,
+    saved_at: String,
+    template_id: Option<String>,
+    fields: Option<String>,
+    nonce: Option<Vec<u8>>,
+    sealed: Option<Vec<u8>>,
+}
+
+const REVISION_COLUMNS: &str = "id, entry_id, COALESCE(title, '') AS title,
+    COALESCE(content, '') AS content, mood, saved_at, template_id, fields, nonce, sealed";
+
+/// The entry's revisions, newest first.
+pub async fn list(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    entry_id: i64,
+) -> Result<Vec<Revision>, String> {
+    super::get(pool, vault, entry_id).await?;
+    let rows: Vec<RevisionRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM journal_revisions WHERE entry_id = ?
+         ORDER BY julianday(saved_at) DESC, id DESC",
+        REVISION_COLUMNS
+    ))
+    .bind(entry_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load journal revisions: {}", e))?;
+    rows.into_iter().map(|row| reveal(row, vault)).collect()
+}
+
+/// The change from revision `from` to revision `to` of an entry. `None`
+/// stands for the entry as it is now.
+pub async fn diff(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    entry_id: i64,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<RevisionDiff, String> {
+    let before = version(pool, vault, entry_id, from).await?;
+    let after = version(pool, vault, entry_id, to).await?;
+    Ok(RevisionDiff {
+        lines: diff_lines(&before.content, &after.content),
+        title_before: before.title,
+        title_after: after.title,
+        mood_before: before.mood,
+        mood_after: after.mood,
+        fields_before: before.fields,
+        fields_after: after.fields,
+    })
+}
+
+/// Make revision `revision_id` the entry's text again. The text it replaces
+/// becomes a revision in turn, so a restore can be undone.
+pub async fn restore(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    entry_id: i64,
+    revision_id: i64,
+) -> Result<JournalEntry, String> {
+    let revision = get(pool, vault, entry_id, revision_id).await?;
+    super::update(
+        pool,
+        vault,
+        entry_id,
+        JournalInput {
+            title: revision.title,
+            content: revision.content,
+            mood: revision.mood,
+            template_id: revision.template_id,
+            fields: revision.fields,
+        },
+    )
+    .await
+}
+
+pub async fn revision_limit(pool: &SqlitePool) -> Result<u32, String> {
+    Ok(db::get_setting(pool, KEY_REVISION_LIMIT)
+        .await?
+        .and_then(|v| v.parse().ok())
+        .filter(|n| *n <= MAX_REVISION_LIMIT)
+        .unwrap_or(DEFAULT_REVISION_LIMIT))
+}
+
+/// Keep at most `limit` revisions per entry, dropping the oldest now. 0
+/// turns history off.
+pub async fn set_revision_limit(pool: &SqlitePool, limit: u32) -> Result<(), String> {
+    if limit > MAX_REVISION_LIMIT {
+        return Err(format!(
+            "At most {} revisions can be kept per entry",
+            MAX_REVISION_LIMIT
+        ));
+    }
+    db::set_setting(pool, KEY_REVISION_LIMIT, &limit.to_string()).await?;
+    let entries: Vec<(i64,)> = sqlx::query_as("SELECT DISTINCT entry_id FROM journal_revisions")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load journal revisions: {}", e))?;
+    for (entry_id,) in entries {
+        prune(pool, entry_id).await?;
+    }
+    Ok(())
+}
+
+/// Save entry `entry_id` as it is now as a revision. A sealed entry gives a
+/// sealed revision, sealed again with `cipher` for the revision's row.
+pub(super) async fn keep(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    cipher: Option<&XChaCha20Poly1305>,
+    entry_id: i64,
+) -> Result<(), String> {
+    let id = sqlx::query(
+        "INSERT INTO journal_revisions (entry_id, title, content, mood, saved_at, template_id,
+             fields, nonce, sealed)
+         SELECT id, title, content, mood, COALESCE(updated_at, created_at, ''), template_id,
+             fields, nonce, sealed
+         FROM journal_entries WHERE id = ?",
+    )
+    .bind(entry_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to save journal revision: {}", e))?
+    .last_insert_rowid();
+
+    let (nonce, sealed): (Option<Vec<u8>>, Option<Vec<u8>>) =
+        sqlx::query_as("SELECT nonce, sealed FROM journal_revisions WHERE id = ?")
+            .bind(id)
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(|e| format!("Failed to load journal revision: {}", e))?;
+    let (Some(nonce), Some(sealed)) = (nonce, sealed) else {
+        return Ok(());
+    };
+    let cipher = cipher.ok_or(LOCKED)?;
+    let text = vault::open(cipher, Row::entry(entry_id), &nonce, &sealed)
+        .map_err(|e| format!("Journal entry {}: {}", entry_id, e))?;
+    let sealed = vault::seal(cipher, Row::revision(id), &text)?;
+    sqlx::query("UPDATE journal_revisions SET nonce = ?, sealed = ? WHERE id = ?")
+        .bind(&sealed.nonce)
+        .bind(&sealed.sealed)
+        .bind(id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Failed to save journal revision: {}", e))?;
+    Ok(())
+}
+
+/// Drop the entry's oldest revisions beyond the limit.
+pub(super) async fn prune(pool: &SqlitePool, entry_id: i64) -> Result<(), String> {
+    let limit = revision_limit(pool).await?;
+    sqlx::query(
+        "DELETE FROM journal_revisions WHERE entry_id = ?1 AND id NOT IN (
+             SELECT id FROM journal_revisions WHERE entry_id = ?1
+             ORDER BY julianday(saved_at) DESC, id DESC LIMIT ?2
+         )",
+    )
+    .bind(entry_id)
+    .bind(limit as i64)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to prune journal revisions: {}", e))?;
+    Ok(())
+}
+
+async fn get(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    entry_id: i64,
+    revision_id: i64,
+) -> Result<Revision, String> {
+    let row: RevisionRow = sqlx::query_as(&format!(
+        "SELECT {} FROM journal_revisions WHERE id = ? AND entry_id = ?",
+        REVISION_COLUMNS
+    ))
+    .bind(revision_id)
+    .bind(entry_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load journal revision: {}", e))?
+    .ok_or_else(|| format!("Journal entry {} has no revision {}", entry_id, revision_id))?;
+    reveal(row, vault)
+}
+
+/// Revision `revision_id` of the entry, or the entry itself for `None`.
+async fn version(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    entry_id: i64,
+    revision_id: Option<i64>,
+) -> Result<JournalInput, String> {
+    Ok(match revision_id {
+        Some(revision_id) => {
+            let revision = get(pool, vault, entry_id, revision_id).await?;
+            JournalInput {
+                title: revision.title,
+                content: revision.content,
+                mood: revision.mood,
+                template_id: revision.template_id,
+                fields: revision.fields,
+            }
+        }
+        None => {
+            let entry = super::get(pool, vault, entry_id).await?;
+            JournalInput {
+                title: entry.title,
+                content: entry.content,
+                mood: entry.mood,
+                template_id: entry.template_id,
+                fields: entry.fields,
+            }
+        }
+    })
+}
+
+fn reveal(row: RevisionRow, vault: &JournalVault) -> Result<Revision, String> {
+    let EntryText {
+        title,
+        content,
+        fields,
+    } = super::open_text(
+        vault,
+        Row::revision(row.id),
+        row.title,
+        row.content,
+        row.fields,
+        row.nonce,
+        row.sealed,
+    )
+    .map_err(|e| format!("Journal revision {}: {}", row.id, e))?;
+    Ok(Revision {
+        id: row.id,
+        entry_id: row.entry_id,
+        title,
+        content,
+        mood: row.mood,
+        saved_at: row.saved_at,
+        template_id: row.template_id,
+        fields,
+    })
+}
+
+/// Line diff of `before` and `after` along their longest common
+/// subsequence. Lines both share at the start and end are set aside first,
+/// which keeps the table small for the usual small edit.
+fn diff_lines(before: &str, after: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let line = |change, text: &str| DiffLine {
+        change,
+        text: text.to_string(),
+    };
+    let mut lines: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|text| line(Change::Same, text))
+        .collect();
+
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        lines.extend(a.iter().map(|text| line(Change::Removed, text)));
+        lines.extend(b.iter().map(|text| line(Change::Added, text)));
+        lines.extend(
+            old[old.len() - suffix..]
+                .iter()
+                .map(|text| line(Change::Same, text)),
+        );
+        return lines;
+    }
+
+    // lcs[i][j]: length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(line(Change::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(line(Change::Removed, a[i]));
+            i += 1;
+        } else {
+            lines.push(line(Change::Added, b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|text| line(Change::Same, text)),
+    );
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The diff as `" same"`, `"-removed"` and `"+added"` lines.
+    fn diff(before: &str, after: &str) -> Vec<String> {
+        diff_lines(before, after)
+            .into_iter()
+            .map(|line| {
+                let mark = match line.change {
+                    Change::Same => ' ',
+                    Change::Added => '+',
+                    Change::Removed => '-',
+                };
+                format!("{}{}", mark, line.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_text_is_all_same() {
+        assert_eq!(diff("a\nb", "a\nb"), [" a", " b"]);
+        assert!(diff("", "").is_empty());
+    }
+
+    #[test]
+    fn inserted_lines() {
+        assert_eq!(diff("a\nc", "a\nb\nc"), [" a", "+b", " c"]);
+        assert_eq!(diff("", "a\nb"), ["+a", "+b"]);
+        assert_eq!(diff("a", "a\nb"), [" a", "+b"]);
+    }
+
+    #[test]
+    fn deleted_lines() {
+        assert_eq!(diff("a\nb\nc", "a\nc"), [" a", "-b", " c"]);
+        assert_eq!(diff("a\nb", ""), ["-a", "-b"]);
+        assert_eq!(diff("a\nb", "b"), ["-a", " b"]);
+    }
+
+    #[test]
+    fn replaced_lines() {
+        assert_eq!(diff("a\nb\nc", "a\nx\nc"), [" a", "-b", "+x", " c"]);
+        assert_eq!(diff("a", "b"), ["-a", "+b"]);
+    }
+
+    #[test]
+    fn shared_prefix_and_suffix_around_changes() {
+        assert_eq!(
+            diff("head\nb\nmid\nd\ntail", "head\nmid\nx\ntail"),
+            [" head", "-b", " mid", "-d", "+x", " tail"]
+        );
+        // Repeated lines at both ends don't overlap
+        assert_eq!(diff("a\na", "a"), [" a", "-a"]);
+        assert_eq!(diff("a", "a\na\na"), [" a", "+a", "+a"]);
+    }
+
+    #[test]
+    fn long_middles_are_replaced_whole() {
+        let before: Vec<String> = (0..1001).map(|i| format!("a {}", i)).collect();
+        let after: Vec<String> = (0..1001).map(|i| format!("b {}", i)).collect();
+        let lines = diff(
+            &format!("head\n{}\ntail", before.join("\n")),
+            &format!("head\n{}\ntail", after.join("\n")),
+        );
+        assert_eq!(lines.len(), 2 + 1001 + 1001);
+        assert_eq!(lines[0], " head");
+        assert!(lines[1..1002].iter().all(|line| line.starts_with('-')));
+        assert!(lines[1002..2003].iter().all(|line| line.starts_with('+')));
+        assert_eq!(lines[2003], " tail");
+    }
+}
@@ -0,0 +1,260 @@
+use std::sync::Mutex;
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+
+use super::vault::{self, JournalVault};
+use super::{JournalEntry, LOCKED, MOODS};
+use crate::streak::{self, days};
+
+/// Hits returned unless the query asks for fewer.
+const DEFAULT_LIMIT: u32 = 50;
+
+/// Words of context around the matches in a snippet.
+const SNIPPET_TOKENS: u32 = 16;
+
+/// Full-text index of the journal, held in an in-memory database so
+/// decrypted text never reaches the disk. Built on the first search, kept in
+/// step with every write and dropped when the journal locks.
+#[derive(Default)]
+pub struct SearchIndex {
+    pool: Mutex<Option<SqlitePool>>,
+}
+
+/// A search as typed by the user. `text` takes words, `"quoted phrases"`
+/// and `prefix*` terms, all of which must match.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub text: String,
+    pub mood: Option<String>,
+    /// First and last day to include, as `YYYY-MM-DD`.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SearchHit {
+    pub id: i64,
+    pub title: String,
+    /// Text around the matches, each wrapped in `<mark>` and `</mark>`. The
+    /// rest is the entry's own text, not HTML.
+    pub snippet: String,
+    pub mood: Option<String>,
+    pub created_at: String,
+    /// BM25 score; lower is a better match.
+    pub rank: f64,
+}
+
+impl SearchIndex {
+    /// Forget the index, e.g. when the journal locks.
+    pub fn clear(&self) {
+        self.pool.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+
+    pub(super) fn set(&self, index: SqlitePool) {
+        *self.pool.lock().unwrap_or_else(|e| e.into_inner()) = Some(index);
+    }
+
+    fn get(&self) -> Option<SqlitePool> {
+        self.pool.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Put `entry` into the index if it is built.
+    pub(super) async fn upsert(&self, entry: &JournalEntry) {
+        let Some(index) = self.get() else {
+            return;
+        };
+        if let Err(e) = insert(&index, entry).await {
+            log::error!("Dropping journal search index: {}", e);
+            self.clear();
+        }
+    }
+
+    /// Take entry `id` out of the index if it is built.
+    pub(super) async fn remove(&self, id: i64) {
+        let Some(index) = self.get() else {
+            return;
+        };
+        if let Err(e) = sqlx::query("DELETE FROM entries WHERE rowid = ?")
+            .bind(id)
+            .execute(&index)
+            .await
+        {
+            log::error!("Dropping journal search index: {}", e);
+            self.clear();
+        }
+    }
+}
+
+/// Entries matching `query`, best first. Needs the journal unlocked.
+pub async fn search(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    query: SearchQuery,
+) -> Result<Vec<SearchHit>, String> {
+    let mood = query
+        .mood
+        .map(|m| m.trim().to_lowercase())
+        .filter(|m| !m.is_empty());
+    if let Some(mood) = mood.as_deref().filter(|m| !MOODS.contains(m)) {
+        return Err(format!("Unknown mood: {}", mood));
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err("The search range ends before it starts".into());
+        }
+    }
+
+    let settings = streak::load_settings(pool).await?;
+    let after = query
+        .from
+        .map(|day| days::day_start(day, &Local, settings.day_starts_at).to_rfc3339());
+    let before = query
+        .to
+        .and_then(|day| day.succ_opt())
+        .map(|day| days::day_start(day, &Local, settings.day_starts_at).to_rfc3339());
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 500);
+    let expression = match_expression(&query.text);
+
+    let index = index(pool, vault).await?;
+    let filters = "(?1 IS NULL OR mood = ?1)
+        AND (?2 IS NULL OR julianday(created_at) >= julianday(?2))
+        AND (?3 IS NULL OR julianday(created_at) < julianday(?3))";
+    let sql = if expression.is_empty() {
+        format!(
+            "SELECT rowid AS id, title, substr(content, 1, 200) AS snippet, mood, created_at,
+                 0.0 AS rank
+             FROM entries WHERE {}
+             ORDER BY julianday(created_at) DESC LIMIT ?4",
+            filters
+        )
+    } else {
+        format!(
+            "SELECT rowid AS id, title,
+                 snippet(entries, -1, '<mark>', '</mark>', '…', {}) AS snippet,
+                 mood, created_at, bm25(entries, 4.0, 1.0) AS rank
+             FROM entries WHERE entries MATCH ?5 AND {}
+             ORDER BY bm25(entries, 4.0, 1.0) LIMIT ?4",
+            SNIPPET_TOKENS, filters
+        )
+    };
+    let mut search = sqlx::query_as(&sql)
+        .bind(&mood)
+        .bind(&after)
+        .bind(&before)
+        .bind(limit as i64);
+    if !expression.is_empty() {
+        search = search.bind(&expression);
+    }
+    search
+        .fetch_all(&index)
+        .await
+        .map_err(|e| format!("Failed to search journal: {}", e))
+}
+
+/// The index, built from every entry if there is none yet. An encrypted
+/// journal must be unlocked even if the index was left behind.
+async fn index(pool: &SqlitePool, vault: &JournalVault) -> Result<SqlitePool, String> {
+    let encrypted = vault::is_encrypted(pool).await?;
+    if encrypted && !vault.is_unlocked() {
+        vault.index.clear();
+        return Err(LOCKED.into());
+    }
+    if let Some(index) = vault.index.get() {
+        return Ok(index);
+    }
+    let entries = super::list(pool, vault).await?;
+
+    // A single connection that never closes; the database lives as long as it
+    let index = SqlitePoolOptions::new()
+        .max_connections(1)
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .map_err(|e| format!("Failed to open journal search index: {}", e))?;
+    sqlx::query(
+        "CREATE VIRTUAL TABLE entries USING fts5(
+             title, content, mood UNINDEXED, created_at UNINDEXED,
+             tokenize = 'porter unicode61 remove_diacritics 2'
+         )",
+    )
+    .execute(&index)
+    .await
+    .map_err(|e| format!("Failed to create journal search index: {}", e))?;
+    for entry in &entries {
+        insert(&index, entry).await?;
+    }
+
+    // The journal may have locked while the index was built
+    if !vault.keep_index(encrypted, index.clone()) {
+        index.close().await;
+        return Err(LOCKED.into());
+    }
+    log::info!("Journal search index built from {} entries", entries.len());
+    Ok(index)
+}
+
+async fn insert(index: &SqlitePool, entry: &JournalEntry) -> Result<(), String> {
+    sqlx::query("DELETE FROM entries WHERE rowid = ?")
+        .bind(entry.id)
+        .execute(index)
+        .await
+        .map_err(|e| format!("Failed to index journal entry {}: {}", entry.id, e))?;
+    // Text answers to template fields are searched along with the content
+    let mut content = entry.content.clone();
+    for answer in entry.fields.values().filter_map(Value::as_str) {
+        content.push_str("\n\n");
+        content.push_str(answer);
+    }
+    sqlx::query(
+        "INSERT INTO entries (rowid, title, content, mood, created_at) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(entry.id)
+    .bind(&entry.title)
+    .bind(&content)
+    .bind(&entry.mood)
+    .bind(&entry.created_at)
+    .execute(index)
+    .await
+    .map_err(|e| format!("Failed to index journal entry {}: {}", entry.id, e))?;
+    Ok(())
+}
+
+/// FTS5 query for what the user typed. Every term is quoted, so operators
+/// and punctuation are searched for rather than parsed.
+fn match_expression(text: &str) -> String {
+    let mut terms = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('"') {
+            let end = after.find('"').unwrap_or(after.len());
+            let phrase = after[..end].trim();
+            if !phrase.is_empty() {
+                terms.push(quote(phrase));
+            }
+            rest = after.get(end + 1..).unwrap_or("").trim_start();
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let stem = word.trim_end_matches('*');
+            if !stem.is_empty() {
+                let prefix = if stem.len() < word.len() { "*" } else { "" };
+                terms.push(format!("{}{}", quote(stem), prefix));
+            }
+            rest = rest[end..].trim_start();
+        }
+    }
+    terms.join(" ")
+}
+
+fn quote(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
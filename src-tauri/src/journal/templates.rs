@@ -0,0 +1,540 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::SqlitePool;
+
+use super::vault::JournalVault;
+use super::JournalInput;
+use crate::plugins::blocker::clock;
+use crate::streak;
+
+/// The templates the app ships with.
+const BUNDLED: &str = include_str!("templates.json");
+
+const MAX_FIELDS: usize = 50;
+
+const MAX_TEXT_CHARS: usize = 10_000;
+
+/// An entry's answers to its template, by field key.
+pub type Fields = Map<String, Value>;
+
+/// A structured exercise, such as a CBT thought record: the fields an entry
+/// written with it fills in alongside its content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub fields: Vec<Field>,
+    /// Shipped with the app; can't be changed or deleted.
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Field {
+    pub key: String,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub help: String,
+    /// Bounds of a `number` field, inclusive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Answers a `choice` field offers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    /// Whether a `choice` field takes several answers.
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    Text,
+    LongText,
+    Number,
+    Choice,
+    Boolean,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    Week,
+    #[default]
+    Month,
+    /// One bucket for the whole range.
+    All,
+}
+
+/// Answers to one field of one template, bucketed by when the entries were
+/// written.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AggregateQuery {
+    pub template_id: String,
+    pub field: String,
+    #[serde(default)]
+    pub period: Period,
+    /// First and last day to include, as `YYYY-MM-DD`.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Bucket {
+    /// `2024-06-01`, `2024-W22`, `2024-06` or `all`, by period.
+    pub period: String,
+    /// Entries that answered the field.
+    pub entries: u32,
+    /// Of a `number` field's answers.
+    pub average: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// How often each answer was given, for `choice` and `boolean` fields.
+    pub counts: BTreeMap<String, u32>,
+}
+
+/// The bundled templates, then the user's by name.
+pub async fn list(pool: &SqlitePool) -> Result<Vec<Template>, String> {
+    let mut templates = bundled();
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT id, definition FROM journal_templates ORDER BY name COLLATE NOCASE, id",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load journal templates: {}", e))?;
+    for (id, definition) in rows {
+        match serde_json::from_str(&definition) {
+            Ok(template) => templates.push(template),
+            Err(e) => log::error!("Skipping unreadable journal template {}: {}", id, e),
+        }
+    }
+    Ok(templates)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Template, String> {
+    list(pool)
+        .await?
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("No journal template {}", id))
+}
+
+/// Check and save a template of the user's, replacing the one with the same
+/// id. Entries written with the old version keep their answers.
+pub async fn save(pool: &SqlitePool, mut template: Template) -> Result<Template, String> {
+    template.builtin = false;
+    validate(&template)?;
+    if bundled().iter().any(|t| t.id == template.id) {
+        return Err(format!(
+            "{} is a built-in template and can't be changed",
+            template.id
+        ));
+    }
+    let definition = serde_json::to_string(&template)
+        .map_err(|e| format!("Failed to serialize journal template: {}", e))?;
+    sqlx::query(
+        "INSERT INTO journal_templates (id, name, definition, updated_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, definition = excluded.definition,
+             updated_at = excluded.updated_at",
+    )
+    .bind(&template.id)
+    .bind(&template.name)
+    .bind(&definition)
+    .bind(clock::now().to_rfc3339())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save journal template: {}", e))?;
+    Ok(template)
+}
+
+/// Delete a template of the user's that no entry uses.
+pub async fn delete(pool: &SqlitePool, id: &str) -> Result<(), String> {
+    if bundled().iter().any(|t| t.id == id) {
+        return Err(format!(
+            "{} is a built-in template and can't be deleted",
+            id
+        ));
+    }
+    let (used,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM journal_entries WHERE template_id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("Failed to delete journal template: {}", e))?;
+    if used > 0 {
+        return Err(format!(
+            "{} journal entries use this template; delete them first",
+            used
+        ));
+    }
+    let deleted = sqlx::query("DELETE FROM journal_templates WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete journal template: {}", e))?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(format!("No journal template {}", id));
+    }
+    Ok(())
+}
+
+/// `input` with its fields checked against its template and tidied: text
+/// trimmed, unanswered fields left out.
+pub(super) async fn check(pool: &SqlitePool, input: JournalInput) -> Result<JournalInput, String> {
+    let template_id = input
+        .template_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty());
+    let Some(template_id) = template_id else {
+        if !input.fields.is_empty() {
+            return Err("Only an entry written with a template has fields".into());
+        }
+        return Ok(JournalInput {
+            template_id: None,
+            ..input
+        });
+    };
+    let template = get(pool, &template_id).await?;
+    let fields = check_fields(&template, input.fields)?;
+    Ok(JournalInput {
+        template_id: Some(template_id),
+        fields,
+        ..input
+    })
+}
+
+/// Buckets of answers to `query.field`, oldest first. Reads every entry, so
+/// an encrypted journal has to be unlocked.
+pub async fn aggregate(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    query: AggregateQuery,
+) -> Result<Vec<Bucket>, String> {
+    let template = get(pool, &query.template_id).await?;
+    let field = template
+        .fields
+        .iter()
+        .find(|f| f.key == query.field)
+        .ok_or_else(|| format!("{} has no field {}", template.name, query.field))?;
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err("The range ends before it starts".into());
+        }
+    }
+    let settings = streak::load_settings(pool).await?;
+
+    let mut buckets: BTreeMap<String, Bucket> = BTreeMap::new();
+    for entry in super::list(pool, vault).await? {
+        if entry.template_id.as_deref() != Some(template.id.as_str()) {
+            continue;
+        }
+        let Some(value) = entry.fields.get(&field.key) else {
+            continue;
+        };
+        let Ok(written) = DateTime::parse_from_rfc3339(&entry.created_at) else {
+            continue;
+        };
+        let day = streak::day_of(written.with_timezone(&Utc), &settings);
+        if query.from.is_some_and(|from| day < from) || query.to.is_some_and(|to| day > to) {
+            continue;
+        }
+
+        let period = match query.period {
+            Period::Day => day.format("%Y-%m-%d").to_string(),
+            Period::Week => day.format("%G-W%V").to_string(),
+            Period::Month => day.format("%Y-%m").to_string(),
+            Period::All => "all".to_string(),
+        };
+        let bucket = buckets.entry(period.clone()).or_insert_with(|| Bucket {
+            period,
+            entries: 0,
+            average: None,
+            min: None,
+            max: None,
+            counts: BTreeMap::new(),
+        });
+        bucket.entries += 1;
+        match value {
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                // The running sum is kept in `average` until the end
+                bucket.average = Some(bucket.average.unwrap_or_default() + n);
+                bucket.min = Some(bucket.min.map_or(n, |m| m.min(n)));
+                bucket.max = Some(bucket.max.map_or(n, |m| m.max(n)));
+            }
+            Value::Bool(b) => *bucket.counts.entry(b.to_string()).or_default() += 1,
+            Value::String(s) if field.kind == FieldKind::Choice => {
+                *bucket.counts.entry(s.clone()).or_default() += 1
+            }
+            Value::Array(answers) => {
+                for answer in answers.iter().filter_map(Value::as_str) {
+                    *bucket.counts.entry(answer.to_string()).or_default() += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(buckets
+        .into_values()
+        .map(|mut bucket| {
+            bucket.average = bucket.average.map(|sum| sum / bucket.entries as f64);
+            bucket
+        })
+        .collect())
+}
+
+fn bundled() -> Vec<Template> {
+    let mut templates: Vec<Template> = serde_json::from_str(BUNDLED).unwrap_or_else(|e| {
+        log::error!("Bundled journal templates are invalid: {}", e);
+        Vec::new()
+    });
+    for template in &mut templates {
+        template.builtin = true;
+    }
+    templates
+}
+
+fn validate(template: &Template) -> Result<(), String> {
+    if !is_key(&template.id) {
+        return Err("A template id must be lowercase letters, digits and underscores".into());
+    }
+    if template.name.trim().is_empty() {
+        return Err("A template needs a name".into());
+    }
+    if template.fields.is_empty() || template.fields.len() > MAX_FIELDS {
+        return Err(format!("A template has 1 to {} fields", MAX_FIELDS));
+    }
+    let mut keys = HashSet::new();
+    for field in &template.fields {
+        validate_field(field).map_err(|e| format!("Field {:?}: {}", field.key, e))?;
+        if !keys.insert(field.key.as_str()) {
+            return Err(format!("Field {:?} is defined twice", field.key));
+        }
+    }
+    Ok(())
+}
+
+fn validate_field(field: &Field) -> Result<(), String> {
+    if !is_key(&field.key) {
+        return Err("the key must be lowercase letters, digits and underscores".into());
+    }
+    if field.label.trim().is_empty() {
+        return Err("the label is empty".into());
+    }
+    let is_number = field.kind == FieldKind::Number;
+    let is_choice = field.kind == FieldKind::Choice;
+    if !is_number && (field.min.is_some() || field.max.is_some() || field.unit.is_some()) {
+        return Err("only a number field takes min, max and unit".into());
+    }
+    if !is_choice && (!field.options.is_empty() || field.multiple) {
+        return Err("only a choice field takes options and multiple".into());
+    }
+    if is_number {
+        match (field.min, field.max) {
+            (Some(min), Some(max)) if min.is_finite() && max.is_finite() && min < max => {}
+            _ => return Err("a number field needs a min below its max".into()),
+        }
+    }
+    if is_choice {
+        let options: HashSet<&str> = field.options.iter().map(|o| o.trim()).collect();
+        if options.len() < 2 || options.len() != field.options.len() || options.contains("") {
+            return Err("a choice field needs at least 2 distinct, non-empty options".into());
+        }
+    }
+    Ok(())
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 64
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// `fields` checked against `template`. Empty answers count as unanswered.
+fn check_fields(template: &Template, mut fields: Fields) -> Result<Fields, String> {
+    if let Some(key) = fields
+        .keys()
+        .find(|key| !template.fields.iter().any(|f| &f.key == *key))
+    {
+        return Err(format!("{} has no field {}", template.name, key));
+    }
+    let mut checked = Fields::new();
+    for field in &template.fields {
+        let value = match fields.remove(&field.key) {
+            None | Some(Value::Null) => None,
+            Some(value) => {
+                check_value(field, value).map_err(|e| format!("{}: {}", field.label, e))?
+            }
+        };
+        match value {
+            Some(value) => {
+                checked.insert(field.key.clone(), value);
+            }
+            None if field.required => return Err(format!("{} is required", field.label)),
+            None => {}
+        }
+    }
+    Ok(checked)
+}
+
+/// The answer as saved, or `None` when it is empty.
+fn check_value(field: &Field, value: Value) -> Result<Option<Value>, String> {
+    match field.kind {
+        FieldKind::Text | FieldKind::LongText => {
+            let Value::String(text) = value else {
+                return Err("expected text".into());
+            };
+            let text = text.trim();
+            if text.chars().count() > MAX_TEXT_CHARS {
+                return Err(format!("at most {} characters", MAX_TEXT_CHARS));
+            }
+            if field.kind == FieldKind::Text && text.contains('\n') {
+                return Err("expected a single line".into());
+            }
+            Ok(Some(text.to_string())
+                .filter(|t| !t.is_empty())
+                .map(Value::from))
+        }
+        FieldKind::Number => {
+            let n = value.as_f64().ok_or("expected a number")?;
+            let (min, max) = (field.min.unwrap_or(f64::MIN), field.max.unwrap_or(f64::MAX));
+            if !(min..=max).contains(&n) {
+                return Err(format!("expected a number from {} to {}", min, max));
+            }
+            Ok(Some(value))
+        }
+        FieldKind::Boolean => match value {
+            Value::Bool(_) => Ok(Some(value)),
+            _ => Err("expected true or false".into()),
+        },
+        FieldKind::Choice => {
+            let answers = match value {
+                Value::String(answer) => vec![answer],
+                Value::Array(answers) if field.multiple => answers
+                    .into_iter()
+                    .map(|a| match a {
+                        Value::String(answer) => Ok(answer),
+                        _ => Err("expected answers as text".to_string()),
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => return Err("expected one of the options".into()),
+            };
+            let mut picked = Vec::new();
+            for answer in answers {
+                let answer = answer.trim().to_string();
+                if !field.options.contains(&answer) {
+                    return Err(format!("{} is not one of the options", answer));
+                }
+                if !picked.contains(&answer) {
+                    picked.push(answer);
+                }
+            }
+            Ok(match picked.len() {
+                0 => None,
+                _ if field.multiple => Some(Value::from(picked)),
+                _ => picked.pop().map(Value::from),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn thought_record() -> Template {
+        bundled()
+            .into_iter()
+            .find(|t| t.id == "cbt_thought_record")
+            .unwrap()
+    }
+
+    #[test]
+    fn bundled_templates_are_valid() {
+        let templates = bundled();
+        assert_eq!(templates.len(), 2);
+        for template in &templates {
+            validate(template).unwrap();
+            assert!(template.builtin);
+        }
+    }
+
+    #[test]
+    fn fields_are_checked_and_tidied() {
+        let template = thought_record();
+        let fields = json!({
+            "situation": "  Alone at home after work ",
+            "automatic_thought": "I deserve a break",
+            "emotion": ["bored", "lonely", "bored"],
+            "intensity": 70,
+            "evidence_for": "",
+            "alternative_thought": null
+        });
+        let Value::Object(fields) = fields else {
+            unreachable!()
+        };
+        let checked = check_fields(&template, fields).unwrap();
+        assert_eq!(
+            Value::Object(checked),
+            json!({
+                "situation": "Alone at home after work",
+                "automatic_thought": "I deserve a break",
+                "emotion": ["bored", "lonely"],
+                "intensity": 70
+            })
+        );
+    }
+
+    #[test]
+    fn bad_fields_are_rejected() {
+        let template = thought_record();
+        let check = |fields: Value| {
+            let Value::Object(fields) = fields else {
+                unreachable!()
+            };
+            check_fields(&template, fields)
+        };
+        let base = json!({
+            "situation": "s",
+            "automatic_thought": "t",
+            "emotion": "sad",
+        });
+        let with = |key: &str, value: Value| {
+            let mut fields = base.clone();
+            fields[key] = value;
+            fields
+        };
+
+        assert!(check(with("intensity", json!(50))).is_ok());
+        assert!(check(base.clone()).is_err(), "intensity is required");
+        assert!(check(with("intensity", json!(101))).is_err());
+        assert!(check(with("intensity", json!("high"))).is_err());
+        let mut unknown = with("intensity", json!(50));
+        unknown["mood"] = json!("bad");
+        assert!(check(unknown).is_err());
+        let mut not_an_option = with("intensity", json!(50));
+        not_an_option["emotion"] = json!("elated");
+        assert!(check(not_an_option).is_err());
+    }
+}
@@ -0,0 +1,677 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+use zeroize::Zeroizing;
+
+use super::search::SearchIndex;
+use super::templates::Fields;
+use crate::db::{self, Db};
+use crate::plugins::blocker::clock;
+
+const KEY_LOCK_AFTER_MINUTES: &str = "journal_lock_after_minutes";
+
+/// Idle minutes before the key is dropped, unless configured.
+const DEFAULT_LOCK_AFTER_MINUTES: u32 = 5;
+
+/// How often the idle timer looks at the vault.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// Argon2id cost for new keys: 64 MiB, 3 passes, one lane.
+const MEMORY_KIB: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const PARALLELISM: u32 = 1;
+
+const ENTRIES: &str = "journal_entries";
+const REVISIONS: &str = "journal_revisions";
+
+/// Tables holding entry text, sealed while the journal is encrypted, with
+/// what a row is called in errors.
+const SEALED_TABLES: &[(&str, &str)] = &[(ENTRIES, "entry"), (REVISIONS, "revision")];
+
+/// Sealed with the key to tell a wrong passphrase from a right one.
+const CHECK_TEXT: &[u8] = b"tamashii journal";
+
+/// The journal key while unlocked, and the search index built from the
+/// decrypted entries. Managed by the app; locking zeroes the key and drops
+/// the index.
+#[derive(Default)]
+pub struct JournalVault {
+    unlocked: Mutex<Option<Unlocked>>,
+    pub(super) index: SearchIndex,
+}
+
+struct Unlocked {
+    key: Zeroizing<[u8; 32]>,
+    last_used: Instant,
+}
+
+impl JournalVault {
+    /// A cipher with the key, which counts as use for the idle timer. `None`
+    /// while locked.
+    pub(super) fn cipher(&self) -> Option<XChaCha20Poly1305> {
+        let mut unlocked = self.unlocked.lock().unwrap_or_else(|e| e.into_inner());
+        let unlocked = unlocked.as_mut()?;
+        unlocked.last_used = Instant::now();
+        Some(XChaCha20Poly1305::new(unlocked.key.as_ref().into()))
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    pub fn lock(&self) {
+        *self.unlocked.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.index.clear();
+    }
+
+    fn set(&self, key: Zeroizing<[u8; 32]>) {
+        *self.unlocked.lock().unwrap_or_else(|e| e.into_inner()) = Some(Unlocked {
+            key,
+            last_used: Instant::now(),
+        });
+    }
+
+    /// Keep `index` as the search index unless an encrypted journal locked
+    /// while it was built. Checked under the key's lock, so locking can't slip
+    /// in between. Returns whether it was kept.
+    pub(super) fn keep_index(&self, encrypted: bool, index: SqlitePool) -> bool {
+        let unlocked = self.unlocked.lock().unwrap_or_else(|e| e.into_inner());
+        if encrypted && unlocked.is_none() {
+            return false;
+        }
+        self.index.set(index);
+        true
+    }
+
+    /// Drop the key and the index if the key went unused for `idle`. Returns
+    /// whether it did.
+    fn lock_if_idle(&self, idle: Duration) -> bool {
+        let mut unlocked = self.unlocked.lock().unwrap_or_else(|e| e.into_inner());
+        if unlocked
+            .as_ref()
+            .is_some_and(|u| u.last_used.elapsed() >= idle)
+        {
+            *unlocked = None;
+            self.index.clear();
+            return true;
+        }
+        false
+    }
+}
+
+/// Whether the journal is encrypted and can be read right now.
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    pub encrypted: bool,
+    pub unlocked: bool,
+    pub lock_after_minutes: u32,
+}
+
+/// The parts of an entry that are encrypted.
+#[derive(Serialize, Deserialize)]
+pub(super) struct EntryText {
+    pub title: String,
+    pub content: String,
+    // Left out of entries sealed before templates existed
+    #[serde(default, skip_serializing_if = "Fields::is_empty")]
+    pub fields: Fields,
+}
+
+/// The row a sealed text is kept in. It is bound into the seal, so a text
+/// copied into another row doesn't open.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Row {
+    table: &'static str,
+    id: i64,
+}
+
+impl Row {
+    pub fn entry(id: i64) -> Self {
+        Self { table: ENTRIES, id }
+    }
+
+    pub fn revision(id: i64) -> Self {
+        Self {
+            table: REVISIONS,
+            id,
+        }
+    }
+
+    fn aad(self) -> Vec<u8> {
+        format!("{}:{}", self.table, self.id).into_bytes()
+    }
+}
+
+/// An `EntryText` sealed with the journal key.
+pub(super) struct Sealed {
+    pub nonce: Vec<u8>,
+    pub sealed: Vec<u8>,
+}
+
+/// An entry or revision not sealed yet.
+#[derive(sqlx::FromRow)]
+struct ClearRow {
+    id: i64,
+    title: Option<String>,
+    content: Option<String>,
+    fields: Option<String>,
+}
+
+/// Argon2id salt and cost the key is derived with, and a check value to
+/// verify a passphrase against.
+#[derive(sqlx::FromRow)]
+struct KeyParams {
+    salt: Vec<u8>,
+    memory_kib: i64,
+    iterations: i64,
+    parallelism: i64,
+    check_nonce: Vec<u8>,
+    check_value: Vec<u8>,
+}
+
+pub async fn status(pool: &SqlitePool, vault: &JournalVault) -> Result<VaultStatus, String> {
+    Ok(VaultStatus {
+        encrypted: is_encrypted(pool).await?,
+        unlocked: vault.is_unlocked(),
+        lock_after_minutes: lock_after_minutes(pool).await?,
+    })
+}
+
+pub async fn is_encrypted(pool: &SqlitePool) -> Result<bool, String> {
+    Ok(load_params(pool).await?.is_some())
+}
+
+/// `is_encrypted`, read through `tx`.
+pub(super) async fn is_encrypted_in(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+) -> Result<bool, String> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT id FROM journal_vault WHERE id = 1")
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| format!("Failed to load journal key parameters: {}", e))?;
+    Ok(row.is_some())
+}
+
+/// Encrypt every entry and revision with a key derived from `passphrase` and
+/// keep the journal unlocked. The database is vacuumed afterwards so the
+/// clear text doesn't linger in free pages.
+pub async fn enable(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    passphrase: &str,
+) -> Result<(), String> {
+    if is_encrypted(pool).await? {
+        return Err("The journal is already encrypted".into());
+    }
+    check_passphrase(passphrase)?;
+    let (params, key) = new_key(passphrase).await?;
+    let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to encrypt journal: {}", e))?;
+    for (table, what) in SEALED_TABLES {
+        let rows: Vec<ClearRow> = sqlx::query_as(&format!(
+            "SELECT id, title, content, fields FROM {} WHERE sealed IS NULL",
+            table
+        ))
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to load journal: {}", e))?;
+        for ClearRow {
+            id,
+            title,
+            content,
+            fields,
+        } in rows
+        {
+            let text = EntryText {
+                title: title.unwrap_or_default(),
+                content: content.unwrap_or_default(),
+                fields: parse_fields(fields.as_deref())
+                    .map_err(|e| format!("Journal {} {}: {}", what, id, e))?,
+            };
+            let sealed = seal(&cipher, Row { table, id }, &text)?;
+            sqlx::query(&format!(
+                "UPDATE {} SET title = '', content = '', fields = NULL, nonce = ?, sealed = ?
+                 WHERE id = ?",
+                table
+            ))
+            .bind(&sealed.nonce)
+            .bind(&sealed.sealed)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to encrypt journal {} {}: {}", what, id, e))?;
+        }
+    }
+    save_params(&mut tx, &params).await?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to encrypt journal: {}", e))?;
+    vault.set(key);
+    log::info!("Journal encrypted");
+
+    if let Err(e) = sqlx::query("VACUUM").execute(pool).await {
+        log::warn!("Failed to vacuum the database after encrypting: {}", e);
+    }
+    Ok(())
+}
+
+pub async fn unlock(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    passphrase: &str,
+) -> Result<(), String> {
+    let params = load_params(pool)
+        .await?
+        .ok_or("The journal is not encrypted")?;
+    let key = verify(&params, passphrase).await?;
+    vault.set(key);
+    log::info!("Journal unlocked");
+    Ok(())
+}
+
+/// Re-encrypt every entry and revision under a key derived from `new`.
+pub async fn change_passphrase(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    old: &str,
+    new: &str,
+) -> Result<(), String> {
+    let params = load_params(pool)
+        .await?
+        .ok_or("The journal is not encrypted")?;
+    let old_key = verify(&params, old).await?;
+    check_passphrase(new)?;
+    let old_cipher = XChaCha20Poly1305::new(old_key.as_ref().into());
+    let (params, key) = new_key(new).await?;
+    let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to re-encrypt journal: {}", e))?;
+    for (table, what) in SEALED_TABLES {
+        for (id, nonce, sealed) in sealed_rows(&mut tx, table).await? {
+            let row = Row { table, id };
+            let text = open(&old_cipher, row, &nonce, &sealed)
+                .map_err(|e| format!("Journal {} {}: {}", what, id, e))?;
+            let sealed = seal(&cipher, row, &text)?;
+            sqlx::query(&format!(
+                "UPDATE {} SET nonce = ?, sealed = ? WHERE id = ?",
+                table
+            ))
+            .bind(&sealed.nonce)
+            .bind(&sealed.sealed)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to re-encrypt journal {} {}: {}", what, id, e))?;
+        }
+    }
+    save_params(&mut tx, &params).await?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to re-encrypt journal: {}", e))?;
+
+    vault.set(key);
+    log::info!("Journal passphrase changed");
+    Ok(())
+}
+
+/// Decrypt every entry and revision back into the clear and forget the key.
+pub async fn disable(
+    pool: &SqlitePool,
+    vault: &JournalVault,
+    passphrase: &str,
+) -> Result<(), String> {
+    let params = load_params(pool)
+        .await?
+        .ok_or("The journal is not encrypted")?;
+    let key = verify(&params, passphrase).await?;
+    let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to decrypt journal: {}", e))?;
+    for (table, what) in SEALED_TABLES {
+        for (id, nonce, sealed) in sealed_rows(&mut tx, table).await? {
+            let text = open(&cipher, Row { table, id }, &nonce, &sealed)
+                .map_err(|e| format!("Journal {} {}: {}", what, id, e))?;
+            sqlx::query(&format!(
+                "UPDATE {} SET title = ?, content = ?, fields = ?, nonce = NULL, sealed = NULL
+                 WHERE id = ?",
+                table
+            ))
+            .bind(&text.title)
+            .bind(&text.content)
+            .bind(fields_column(&text.fields)?)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to decrypt journal {} {}: {}", what, id, e))?;
+        }
+    }
+    sqlx::query("DELETE FROM journal_vault")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to decrypt journal: {}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to decrypt journal: {}", e))?;
+
+    vault.lock();
+    log::info!("Journal encryption turned off");
+    Ok(())
+}
+
+pub async fn lock_after_minutes(pool: &SqlitePool) -> Result<u32, String> {
+    Ok(db::get_setting(pool, KEY_LOCK_AFTER_MINUTES)
+        .await?
+        .and_then(|v| v.parse().ok())
+        .filter(|m| *m > 0)
+        .unwrap_or(DEFAULT_LOCK_AFTER_MINUTES))
+}
+
+pub async fn set_lock_after_minutes(pool: &SqlitePool, minutes: u32) -> Result<(), String> {
+    if !(1..=24 * 60).contains(&minutes) {
+        return Err("The journal must lock after 1 minute to 24 hours".into());
+    }
+    db::set_setting(pool, KEY_LOCK_AFTER_MINUTES, &minutes.to_string()).await
+}
+
+/// Lock the journal once its key went unused for the configured time, and
+/// emit `journal-locked`.
+pub async fn watch_idle(app_handle: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+        let pool = app_handle.state::<Db>().0.clone();
+        let minutes = match lock_after_minutes(&pool).await {
+            Ok(minutes) => minutes,
+            Err(e) => {
+                log::error!("Journal idle check failed: {}", e);
+                DEFAULT_LOCK_AFTER_MINUTES
+            }
+        };
+        let vault = app_handle.state::<JournalVault>();
+        if vault.lock_if_idle(Duration::from_secs(minutes as u64 * 60)) {
+            log::info!("Journal locked after {} idle minutes", minutes);
+            let _ = app_handle.emit("journal-locked", ());
+        }
+    }
+}
+
+/// Fields as saved in the clear: JSON, or NULL when there are none.
+pub(super) fn fields_column(fields: &Fields) -> Result<Option<String>, String> {
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(fields)
+        .map(Some)
+        .map_err(|e| format!("Failed to serialize journal fields: {}", e))
+}
+
+pub(super) fn parse_fields(column: Option<&str>) -> Result<Fields, String> {
+    match column {
+        Some(json) => serde_json::from_str(json).map_err(|e| format!("unreadable fields: {}", e)),
+        None => Ok(Fields::new()),
+    }
+}
+
+/// Seal `text` to be kept in `row`.
+pub(super) fn seal(
+    cipher: &XChaCha20Poly1305,
+    row: Row,
+    text: &EntryText,
+) -> Result<Sealed, String> {
+    let plain = Zeroizing::new(
+        serde_json::to_vec(text).map_err(|e| format!("Failed to serialize entry: {}", e))?,
+    );
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plain.as_slice(),
+                aad: &row.aad(),
+            },
+        )
+        .map_err(|_| "Failed to encrypt journal entry".to_string())?;
+    Ok(Sealed {
+        nonce: nonce.to_vec(),
+        sealed,
+    })
+}
+
+/// Open a text sealed in `row`. A text sealed for another row fails like a
+/// damaged one.
+pub(super) fn open(
+    cipher: &XChaCha20Poly1305,
+    row: Row,
+    nonce: &[u8],
+    sealed: &[u8],
+) -> Result<EntryText, String> {
+    if nonce.len() != 24 {
+        return Err("the entry is damaged".into());
+    }
+    let plain = Zeroizing::new(
+        cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: sealed,
+                    aad: &row.aad(),
+                },
+            )
+            .map_err(|_| "the entry is damaged or was sealed with another key".to_string())?,
+    );
+    serde_json::from_slice(&plain).map_err(|e| format!("the entry is unreadable: {}", e))
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!(
+            "The passphrase needs at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        ));
+    }
+    Ok(())
+}
+
+/// A fresh salt and the key `passphrase` derives with it.
+async fn new_key(passphrase: &str) -> Result<(KeyParams, Zeroizing<[u8; 32]>), String> {
+    let mut salt = vec![0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut params = KeyParams {
+        salt,
+        memory_kib: MEMORY_KIB as i64,
+        iterations: ITERATIONS as i64,
+        parallelism: PARALLELISM as i64,
+        check_nonce: Vec::new(),
+        check_value: Vec::new(),
+    };
+    let key = derive(&params, passphrase).await?;
+
+    let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    params.check_value = cipher
+        .encrypt(&nonce, CHECK_TEXT)
+        .map_err(|_| "Failed to encrypt journal key check".to_string())?;
+    params.check_nonce = nonce.to_vec();
+    Ok((params, key))
+}
+
+/// The key `passphrase` derives, if it is the right one.
+async fn verify(params: &KeyParams, passphrase: &str) -> Result<Zeroizing<[u8; 32]>, String> {
+    let key = derive(params, passphrase).await?;
+    let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+    let matches = params.check_nonce.len() == 24
+        && cipher
+            .decrypt(
+                XNonce::from_slice(&params.check_nonce),
+                params.check_value.as_slice(),
+            )
+            .is_ok_and(|text| text == CHECK_TEXT);
+    if !matches {
+        return Err("Wrong passphrase".into());
+    }
+    Ok(key)
+}
+
+/// Run Argon2id off the async runtime; it takes a good fraction of a second
+/// by design.
+async fn derive(params: &KeyParams, passphrase: &str) -> Result<Zeroizing<[u8; 32]>, String> {
+    let cost = Params::new(
+        params.memory_kib as u32,
+        params.iterations as u32,
+        params.parallelism as u32,
+        Some(32),
+    )
+    .map_err(|e| format!("Invalid key parameters: {}", e))?;
+    let salt = params.salt.clone();
+    let passphrase = Zeroizing::new(passphrase.as_bytes().to_vec());
+
+    tokio::task::spawn_blocking(move || {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, cost)
+            .hash_password_into(&passphrase, &salt, key.as_mut())
+            .map_err(|e| format!("Failed to derive journal key: {}", e))?;
+        Ok(key)
+    })
+    .await
+    .map_err(|e| format!("Failed to derive journal key: {}", e))?
+}
+
+async fn load_params(pool: &SqlitePool) -> Result<Option<KeyParams>, String> {
+    sqlx::query_as(
+        "SELECT salt, memory_kib, iterations, parallelism, check_nonce, check_value
+         FROM journal_vault WHERE id = 1",
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load journal key parameters: {}", e))
+}
+
+async fn save_params(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    params: &KeyParams,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO journal_vault
+             (id, salt, memory_kib, iterations, parallelism, check_nonce, check_value, created_at)
+         VALUES (1, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+             salt = excluded.salt, memory_kib = excluded.memory_kib,
+             iterations = excluded.iterations, parallelism = excluded.parallelism,
+             check_nonce = excluded.check_nonce, check_value = excluded.check_value,
+             created_at = excluded.created_at",
+    )
+    .bind(&params.salt)
+    .bind(params.memory_kib)
+    .bind(params.iterations)
+    .bind(params.parallelism)
+    .bind(&params.check_nonce)
+    .bind(&params.check_value)
+    .bind(clock::now().to_rfc3339())
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to save journal key parameters: {}", e))?;
+    Ok(())
+}
+
+async fn sealed_rows(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    table: &str,
+) -> Result<Vec<(i64, Vec<u8>, Vec<u8>)>, String> {
+    sqlx::query_as(&format!(
+        "SELECT id, nonce, sealed FROM {} WHERE sealed IS NOT NULL",
+        table
+    ))
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to load journal: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(byte: u8) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&[byte; 32].into())
+    }
+
+    fn text() -> EntryText {
+        let mut fields = Fields::new();
+        fields.insert("trigger".into(), "boredom".into());
+        EntryText {
+            title: "Evening".into(),
+            content: "A quiet day.\nNo urges.".into(),
+            fields,
+        }
+    }
+
+    #[test]
+    fn sealed_text_opens_again() {
+        let cipher = cipher(1);
+        let sealed = seal(&cipher, Row::entry(1), &text()).unwrap();
+        assert_eq!(sealed.nonce.len(), 24);
+        assert!(!sealed.sealed.windows(7).any(|w| w == b"Evening"));
+
+        let opened = open(&cipher, Row::entry(1), &sealed.nonce, &sealed.sealed).unwrap();
+        assert_eq!(opened.title, "Evening");
+        assert_eq!(opened.content, "A quiet day.\nNo urges.");
+        assert_eq!(opened.fields, text().fields);
+    }
+
+    #[test]
+    fn another_key_does_not_open() {
+        let sealed = seal(&cipher(1), Row::entry(1), &text()).unwrap();
+        assert!(open(&cipher(2), Row::entry(1), &sealed.nonce, &sealed.sealed).is_err());
+    }
+
+    #[test]
+    fn sealed_text_only_opens_in_its_row() {
+        let cipher = cipher(1);
+        let sealed = seal(&cipher, Row::entry(1), &text()).unwrap();
+        for row in [Row::entry(2), Row::revision(1)] {
+            assert!(open(&cipher, row, &sealed.nonce, &sealed.sealed).is_err());
+        }
+    }
+
+    #[test]
+    fn damaged_text_does_not_open() {
+        let cipher = cipher(1);
+        let mut sealed = seal(&cipher, Row::entry(1), &text()).unwrap();
+        assert!(open(&cipher, Row::entry(1), &sealed.nonce[..12], &sealed.sealed).is_err());
+        sealed.sealed[0] ^= 1;
+        assert!(open(&cipher, Row::entry(1), &sealed.nonce, &sealed.sealed).is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_takes_only_the_right_passphrase() {
+        let (params, key) = new_key("correct horse battery").await.unwrap();
+        assert_eq!(
+            *verify(&params, "correct horse battery").await.unwrap(),
+            *key
+        );
+        assert_eq!(
+            verify(&params, "correct horse battery!").await.unwrap_err(),
+            "Wrong passphrase"
+        );
+    }
+}
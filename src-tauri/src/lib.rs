@@ -1,9 +1,16 @@
+mod achievements;
+mod checkin;
 mod commands;
+mod db;
+mod journal;
 mod plugins;
 mod scheduler;
+mod streak;
 
 use tauri::Manager;
 
+use achievements::events::EventKind;
+
 #[cfg(desktop)]
 use tauri::menu::{Menu, MenuItem};
 #[cfg(desktop)]
@@ -23,7 +30,65 @@ pub fn run() {
         .plugin(plugins::blocker::init())
         .invoke_handler(tauri::generate_handler![
             commands::streak::get_streak_data,
-            commands::streak::save_streak_data,
+            commands::streak::start_streak,
+            commands::streak::reset_streak,
+            commands::streak::get_streak_history,
+            commands::streak::get_streak_analytics,
+            commands::streak::get_streak_milestones,
+            commands::streak::get_streak_settings,
+            commands::streak::set_streak_settings,
+            commands::habits::list_habits,
+            commands::habits::create_habit,
+            commands::habits::update_habit,
+            commands::habits::archive_habit,
+            commands::habits::check_in_habit,
+            commands::habits::reset_habit,
+            commands::habits::start_habit,
+            commands::habits::get_habit_history,
+            commands::habits::use_streak_freeze,
+            commands::habits::get_freeze_history,
+            commands::habits::get_grace_settings,
+            commands::habits::set_grace_settings,
+            commands::journal::list_journal_entries,
+            commands::journal::create_journal_entry,
+            commands::journal::update_journal_entry,
+            commands::journal::delete_journal_entry,
+            commands::journal::list_journal_revisions,
+            commands::journal::diff_journal_revisions,
+            commands::journal::restore_journal_revision,
+            commands::journal::get_journal_revision_limit,
+            commands::journal::set_journal_revision_limit,
+            commands::journal::list_journal_templates,
+            commands::journal::save_journal_template,
+            commands::journal::delete_journal_template,
+            commands::journal::aggregate_journal_field,
+            commands::journal::get_mood_analytics,
+            commands::journal::search_journal,
+            commands::journal::export_journal,
+            commands::journal::import_journal,
+            commands::journal::get_journal_lock_status,
+            commands::journal::enable_journal_encryption,
+            commands::journal::disable_journal_encryption,
+            commands::journal::unlock_journal,
+            commands::journal::lock_journal,
+            commands::journal::change_journal_passphrase,
+            commands::journal::set_journal_lock_timeout,
+            commands::achievements::get_achievements,
+            commands::achievements::recompute_achievements,
+            commands::panic::use_panic_button,
+            commands::recovery::log_relapse,
+            commands::recovery::log_urge,
+            commands::recovery::get_relapses,
+            commands::recovery::get_urges,
+            commands::recovery::get_trigger_tags,
+            commands::risk::get_risk_profile,
+            commands::risk::get_risk_settings,
+            commands::risk::set_risk_settings,
+            commands::checkin::get_check_in,
+            commands::checkin::save_check_in,
+            commands::checkin::list_check_ins,
+            commands::checkin::get_check_in_settings,
+            commands::checkin::set_check_in_settings,
         ]);
 
     // Autostart plugin - desktop only
@@ -37,6 +102,13 @@ pub fn run() {
 
     builder
         .setup(|app| {
+            let db = tauri::async_runtime::block_on(db::init(app.handle()))?;
+            app.manage(db);
+            app.manage(journal::vault::JournalVault::default());
+
+            achievements::engine::start(app.handle());
+            achievements::engine::publish(app.handle(), EventKind::AppOpened);
+
             // System tray - desktop only
             #[cfg(desktop)]
             {
@@ -53,6 +125,7 @@ pub fn run() {
                             if let Some(window) = app.get_webview_window("main") {
                                 let _ = window.show();
                                 let _ = window.set_focus();
+                                achievements::engine::publish(app, EventKind::AppOpened);
                             }
                         }
                         "quit" => {
@@ -69,6 +142,36 @@ pub fn run() {
                 scheduler::quote_scheduler::start_scheduler(handle).await;
             });
 
+            // Start habit reminder scheduler
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                scheduler::habit_scheduler::start_scheduler(handle).await;
+            });
+
+            // Start milestone scheduler
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                scheduler::milestone_scheduler::start_scheduler(handle).await;
+            });
+
+            // Start risk alert scheduler
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                scheduler::risk_scheduler::start_scheduler(handle).await;
+            });
+
+            // Start check-in scheduler
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                scheduler::checkin_scheduler::start_scheduler(handle).await;
+            });
+
+            // Lock the encrypted journal when it goes unused
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                journal::vault::watch_idle(handle).await;
+            });
+
             Ok(())
         })
         .run(tauri::generate_context!())
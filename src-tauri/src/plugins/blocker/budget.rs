@@ -0,0 +1,317 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use super::lock::LockManager;
+use super::models::{BudgetSettings, BudgetStatus, CategoryBudget, LockScope};
+use super::{catalog, clock, routing, settings};
+
+const KEY_BUDGETS: &str = "budgets";
+const KEY_USAGE: &str = "budgetUsage";
+
+/// Name the budgets hold the local resolver under.
+const ROUTING_USER: &str = "budgets";
+
+/// Lookups at most this far apart belong to one session, and the time
+/// between them counts as use.
+const SESSION_GAP_SECS: i64 = 5 * 60;
+
+/// Charged for the first lookup of a session. DNS shows when a visit starts
+/// but not how long the first page stays open.
+const SESSION_START_CHARGE_SECS: u64 = 60;
+
+/// How often usage is saved and pushed to the frontend.
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time used per category on one budget day. Persisted so a restart doesn't
+/// hand out a fresh budget.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Usage {
+    day: Option<NaiveDate>,
+    used: HashMap<String, u64>,
+}
+
+/// Meters budgeted categories from the lookups the local resolver reports.
+#[derive(Default)]
+pub struct BudgetMeter {
+    inner: Mutex<MeterState>,
+}
+
+#[derive(Default)]
+struct MeterState {
+    usage: Usage,
+    last_lookup: HashMap<String, DateTime<Utc>>,
+    /// Usage changed since it was last saved and pushed to the frontend.
+    dirty: bool,
+    /// A new day started since the resolver policy was last rebuilt.
+    rolled: bool,
+}
+
+pub fn load_settings<R: Runtime>(app: &AppHandle<R>) -> BudgetSettings {
+    settings::load(app, KEY_BUDGETS).unwrap_or_default()
+}
+
+/// Replace the budgets. While the blocklist lock runs they can only be
+/// tightened: no budget removed or raised, and the same day boundary.
+pub fn set_settings<R: Runtime>(
+    app: &AppHandle<R>,
+    new: BudgetSettings,
+) -> Result<Vec<BudgetStatus>, String> {
+    let new = validate(app, new)?;
+    let locked = app
+        .state::<LockManager<R>>()
+        .is_locked(LockScope::Blocklist);
+    let previous = load_settings(app);
+    if locked && !is_tighter(&previous, &new) {
+        return Err("Budgets are locked; they can only be tightened".into());
+    }
+
+    settings::save(app, KEY_BUDGETS, &new)?;
+    if let Err(e) = apply(app) {
+        settings::save(app, KEY_BUDGETS, &previous)?;
+        routing::refresh_policy(app);
+        return Err(e);
+    }
+    // Answers cached before metering started would let lookups bypass it
+    super::desktop::flush_dns_cache();
+
+    log::info!("Time budgets set for {} categories", new.budgets.len());
+    Ok(status(app))
+}
+
+pub fn status<R: Runtime>(app: &AppHandle<R>) -> Vec<BudgetStatus> {
+    let config = load_settings(app);
+    let meter = app.state::<BudgetMeter>();
+    let mut state = meter.inner.lock().unwrap();
+    roll_day(&mut state, config.day_starts_at);
+
+    let now = clock::now();
+    config
+        .budgets
+        .iter()
+        .map(|budget| status_of(&state, budget, now, config.day_starts_at))
+        .collect()
+}
+
+/// Domain → category table of metered domains, and the categories whose
+/// budget is used up. Feeds the resolver policy.
+pub fn policy<R: Runtime>(app: &AppHandle<R>) -> (HashMap<String, String>, HashSet<String>) {
+    let config = load_settings(app);
+    let mut metered = HashMap::new();
+    let mut exhausted = HashSet::new();
+    if config.budgets.is_empty() {
+        return (metered, exhausted);
+    }
+
+    let catalog = catalog::load(app);
+    let meter = app.state::<BudgetMeter>();
+    let mut state = meter.inner.lock().unwrap();
+    roll_day(&mut state, config.day_starts_at);
+
+    for budget in &config.budgets {
+        for domain in catalog.get(&budget.category).into_iter().flatten() {
+            metered.insert(domain.clone(), budget.category.clone());
+        }
+        if used(&state, &budget.category) >= limit_secs(budget) {
+            exhausted.insert(budget.category.clone());
+        }
+    }
+    (metered, exhausted)
+}
+
+/// Metering hook of the local resolver, called for every forwarded lookup
+/// of a budgeted domain. Blocks the category once its budget is used up.
+pub fn record_lookup<R: Runtime>(app: &AppHandle<R>, category: &str) {
+    let config = load_settings(app);
+    let Some(budget) = config.budgets.iter().find(|b| b.category == category) else {
+        return;
+    };
+
+    let meter = app.state::<BudgetMeter>();
+    let mut state = meter.inner.lock().unwrap();
+    roll_day(&mut state, config.day_starts_at);
+
+    let now = clock::now();
+    let charge = match state.last_lookup.get(category) {
+        Some(last) if (now - *last).num_seconds() <= SESSION_GAP_SECS => {
+            (now - *last).num_seconds().max(0) as u64
+        }
+        _ => SESSION_START_CHARGE_SECS,
+    };
+    state.last_lookup.insert(category.to_string(), now);
+
+    let limit = limit_secs(budget);
+    let used = state.usage.used.entry(category.to_string()).or_default();
+    let crossed = *used < limit && *used + charge >= limit;
+    *used += charge;
+    state.dirty = true;
+
+    if !crossed {
+        return;
+    }
+    let status = status_of(&state, budget, now, config.day_starts_at);
+    drop(state);
+
+    log::info!("Daily budget for {} used up", category);
+    routing::refresh_policy(app);
+    super::desktop::flush_dns_cache();
+    let _ = app.emit("budget-exhausted", status);
+}
+
+/// Load today's usage, route DNS through the resolver if any budget is set,
+/// and start saving usage and resetting it at the day boundary.
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    let usage: Usage = settings::load(app, KEY_USAGE).unwrap_or_default();
+    app.state::<BudgetMeter>().inner.lock().unwrap().usage = usage;
+    if let Err(e) = apply(app) {
+        log::error!("Failed to restore time budgets: {}", e);
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let config = load_settings(&app);
+            let (rolled, usage) = {
+                let meter = app.state::<BudgetMeter>();
+                let mut state = meter.inner.lock().unwrap();
+                roll_day(&mut state, config.day_starts_at);
+                let rolled = std::mem::take(&mut state.rolled);
+                let usage = std::mem::take(&mut state.dirty).then(|| state.usage.clone());
+                (rolled, usage)
+            };
+
+            if rolled {
+                log::info!("Budget day started, used time reset");
+                routing::refresh_policy(&app);
+            }
+            if let Some(usage) = usage {
+                if let Err(e) = settings::save(&app, KEY_USAGE, &usage) {
+                    log::error!("Failed to save budget usage: {}", e);
+                }
+                let _ = app.emit("budget-updated", status(&app));
+            }
+        }
+    });
+}
+
+/// Hold the resolver while any budget is set, release it otherwise.
+fn apply<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    if load_settings(app).budgets.is_empty() {
+        routing::release(app, ROUTING_USER)
+    } else {
+        routing::acquire(app, ROUTING_USER)
+    }
+}
+
+fn validate<R: Runtime>(
+    app: &AppHandle<R>,
+    mut new: BudgetSettings,
+) -> Result<BudgetSettings, String> {
+    if new.day_starts_at > 23 {
+        return Err("The budget day must start at an hour between 0 and 23".into());
+    }
+
+    let catalog = catalog::load(app);
+    let mut seen = HashSet::new();
+    for budget in &mut new.budgets {
+        budget.category = budget.category.trim().to_string();
+        if !catalog.contains_key(&budget.category) {
+            return Err(format!("Unknown category: {}", budget.category));
+        }
+        if !seen.insert(budget.category.clone()) {
+            return Err(format!(
+                "Category {} has more than one budget",
+                budget.category
+            ));
+        }
+    }
+    Ok(new)
+}
+
+/// True if `new` allows no more time than `current`.
+fn is_tighter(current: &BudgetSettings, new: &BudgetSettings) -> bool {
+    current.day_starts_at == new.day_starts_at
+        && current.budgets.iter().all(|c| {
+            new.budgets
+                .iter()
+                .any(|n| n.category == c.category && n.minutes_per_day <= c.minutes_per_day)
+        })
+}
+
+/// Start a new usage day if the boundary has passed.
+fn roll_day(state: &mut MeterState, day_starts_at: u32) {
+    let today = current_day(day_starts_at);
+    if state.usage.day == Some(today) {
+        return;
+    }
+
+    state.rolled = state.usage.day.is_some();
+    state.usage = Usage {
+        day: Some(today),
+        used: HashMap::new(),
+    };
+    state.last_lookup.clear();
+    state.dirty = true;
+}
+
+/// The budget day that is running now, in credited time so setting the
+/// clock forward doesn't hand out a fresh budget. Before `day_starts_at`
+/// o'clock the previous calendar day is still running.
+fn current_day(day_starts_at: u32) -> NaiveDate {
+    (clock::now().with_timezone(&Local) - chrono::Duration::hours(day_starts_at as i64))
+        .date_naive()
+}
+
+fn next_reset(day_starts_at: u32) -> Option<DateTime<Local>> {
+    let next = current_day(day_starts_at)
+        .succ_opt()?
+        .and_hms_opt(day_starts_at, 0, 0)?;
+    // The boundary can fall into a DST gap; use the first hour after it
+    Local.from_local_datetime(&next).earliest().or_else(|| {
+        Local
+            .from_local_datetime(&(next + chrono::Duration::hours(1)))
+            .earliest()
+    })
+}
+
+fn status_of(
+    state: &MeterState,
+    budget: &CategoryBudget,
+    now: DateTime<Utc>,
+    day_starts_at: u32,
+) -> BudgetStatus {
+    let used_seconds = used(state, &budget.category);
+    let limit = limit_secs(budget);
+    let in_session = state
+        .last_lookup
+        .get(&budget.category)
+        .is_some_and(|last| (now - *last).num_seconds() <= SESSION_GAP_SECS);
+
+    BudgetStatus {
+        category: budget.category.clone(),
+        minutes_per_day: budget.minutes_per_day,
+        used_seconds,
+        remaining_seconds: limit.saturating_sub(used_seconds),
+        exhausted: used_seconds >= limit,
+        in_session,
+        resets_at: next_reset(day_starts_at)
+            .map(|r| r.to_rfc3339())
+            .unwrap_or_default(),
+    }
+}
+
+fn used(state: &MeterState, category: &str) -> u64 {
+    state.usage.used.get(category).copied().unwrap_or(0)
+}
+
+fn limit_secs(budget: &CategoryBudget) -> u64 {
+    budget.minutes_per_day as u64 * 60
+}
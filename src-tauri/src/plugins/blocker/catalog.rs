@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use tauri::{AppHandle, Runtime};
+
+use super::settings;
+
+const KEY_CATALOG: &str = "categoryCatalog";
+
+/// Replace the category → domains catalog. The frontend owns the lists and
+/// syncs them here so the backend can block categories on its own, e.g. when
+/// the panic button escalates.
+pub fn set<R: Runtime>(
+    app: &AppHandle<R>,
+    categories: HashMap<String, Vec<String>>,
+) -> Result<(), String> {
+    let categories: HashMap<String, Vec<String>> = categories
+        .into_iter()
+        .map(|(id, domains)| (id, normalize(domains)))
+        .collect();
+    settings::save(app, KEY_CATALOG, &categories)?;
+
+    log::info!("Block catalog synced with {} categories", categories.len());
+    Ok(())
+}
+
+pub fn load<R: Runtime>(app: &AppHandle<R>) -> HashMap<String, Vec<String>> {
+    settings::load(app, KEY_CATALOG).unwrap_or_default()
+}
+
+/// Every domain of every known category.
+pub fn all_domains<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    normalize(load(app).into_values().flatten().collect())
+}
+
+/// Lowercase, trim, sort and dedupe a domain list.
+pub fn normalize(domains: Vec<String>) -> Vec<String> {
+    let mut domains: Vec<String> = domains
+        .iter()
+        .map(|d| d.trim().to_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    domains.sort();
+    domains.dedup();
+    domains
+}
@@ -0,0 +1,221 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
+
+use super::models::{ClockJump, ClockJumpKind, ClockStatus};
+use super::settings;
+
+const KEY_CHECKPOINT: &str = "clockCheckpoint";
+
+/// How often the checkpoint is taken and saved.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Wall clock and uptime may disagree by this much between checkpoints
+/// before it counts as a jump. Leaves room for NTP corrections.
+const JUMP_TOLERANCE_SECS: f64 = 120.0;
+
+/// Jumps kept for the status.
+const JUMP_HISTORY_LIMIT: usize = 50;
+
+/// Last seen wall time and uptime. Between two checkpoints the wall clock
+/// should move as far as the uptime clock, which counts suspend but can't be
+/// set by the user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Checkpoint {
+    wall: DateTime<Utc>,
+    uptime_secs: f64,
+    /// Seconds the wall clock is ahead of the credited time, summed over
+    /// every jump detected.
+    offset_secs: i64,
+    jumps: Vec<ClockJump>,
+    /// Jumps detected ever, including those dropped from `jumps`.
+    #[serde(default)]
+    detected: u64,
+}
+
+static CHECKPOINT: Mutex<Option<Checkpoint>> = Mutex::new(None);
+
+/// The current time with detected clock jumps taken out. Lock expiry and
+/// streak lengths are measured against this instead of the raw wall clock.
+pub fn now() -> DateTime<Utc> {
+    let mut checkpoint = CHECKPOINT.lock().unwrap();
+    let offset = observe(&mut checkpoint).offset_secs;
+    Utc::now() - chrono::Duration::seconds(offset)
+}
+
+pub fn status() -> ClockStatus {
+    let mut checkpoint = CHECKPOINT.lock().unwrap();
+    let checkpoint = observe(&mut checkpoint);
+    ClockStatus {
+        tampered: is_tampered(checkpoint),
+        offset_seconds: checkpoint.offset_secs,
+        jumps: checkpoint.jumps.clone(),
+    }
+}
+
+/// True while the wall clock is off from the credited time by more than
+/// normal drift.
+pub fn tampered() -> bool {
+    let mut checkpoint = CHECKPOINT.lock().unwrap();
+    is_tampered(observe(&mut checkpoint))
+}
+
+/// Load the saved checkpoint, check how the clock moved while the app was
+/// closed, and keep taking checkpoints while it runs.
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    let saved: Option<Checkpoint> = settings::load(app, KEY_CHECKPOINT);
+    let mut reported = saved.as_ref().map_or(0, |c| c.detected);
+    *CHECKPOINT.lock().unwrap() = saved;
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CHECKPOINT_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let checkpoint = {
+                let mut checkpoint = CHECKPOINT.lock().unwrap();
+                observe(&mut checkpoint).clone()
+            };
+            if let Err(e) = settings::save(&app, KEY_CHECKPOINT, &checkpoint) {
+                log::error!("Failed to save clock checkpoint: {}", e);
+            }
+
+            let new = (checkpoint.detected - reported) as usize;
+            for jump in checkpoint.jumps.iter().rev().take(new).rev() {
+                let _ = app.emit("clock-jump-detected", jump);
+            }
+            reported = checkpoint.detected;
+        }
+    });
+}
+
+/// Move the checkpoint to now, recording a jump if the wall clock moved
+/// differently from uptime since the last one.
+fn observe(checkpoint: &mut Option<Checkpoint>) -> &Checkpoint {
+    let wall = Utc::now();
+    let uptime = uptime_secs();
+    let last = checkpoint.get_or_insert_with(|| Checkpoint {
+        wall,
+        uptime_secs: uptime,
+        offset_secs: 0,
+        jumps: Vec::new(),
+        detected: 0,
+    });
+
+    let (jump, across_restart) = if uptime >= last.uptime_secs {
+        let wall_elapsed = (wall - last.wall).num_milliseconds() as f64 / 1000.0;
+        (wall_elapsed - (uptime - last.uptime_secs), false)
+    } else {
+        // The machine rebooted, so it booted after the last checkpoint. Only a
+        // clock set back can be told apart from downtime.
+        let booted_at = wall - chrono::Duration::milliseconds((uptime * 1000.0) as i64);
+        let behind = (booted_at - last.wall).num_milliseconds() as f64 / 1000.0;
+        (behind.min(0.0), true)
+    };
+
+    if jump.abs() > JUMP_TOLERANCE_SECS {
+        let jump_secs = jump.round() as i64;
+        last.offset_secs += jump_secs;
+        let event = ClockJump {
+            detected_at: (wall - chrono::Duration::seconds(last.offset_secs)).to_rfc3339(),
+            kind: if jump_secs > 0 {
+                ClockJumpKind::Forward
+            } else {
+                ClockJumpKind::Backward
+            },
+            seconds: jump_secs.abs(),
+            across_restart,
+        };
+        log::warn!(
+            "System clock jumped {:?} by {}s{}; crediting uptime instead",
+            event.kind,
+            event.seconds,
+            if across_restart {
+                " across a reboot"
+            } else {
+                ""
+            }
+        );
+        if last.jumps.len() == JUMP_HISTORY_LIMIT {
+            last.jumps.remove(0);
+        }
+        last.jumps.push(event);
+        last.detected += 1;
+    }
+
+    last.wall = wall;
+    last.uptime_secs = uptime;
+    last
+}
+
+fn is_tampered(checkpoint: &Checkpoint) -> bool {
+    checkpoint.offset_secs.unsigned_abs() as f64 > JUMP_TOLERANCE_SECS
+}
+
+/// Seconds since boot, including time spent suspended.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn uptime_secs() -> f64 {
+    std::fs::read_to_string("/proc/uptime")
+        .ok()
+        .and_then(|uptime| uptime.split_whitespace().next()?.parse().ok())
+        .unwrap_or_else(process_uptime_secs)
+}
+
+/// Seconds since boot, including time spent asleep.
+#[cfg(target_os = "windows")]
+fn uptime_secs() -> f64 {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetTickCount64() -> u64;
+    }
+    // SAFETY: takes no arguments and can't fail
+    unsafe { GetTickCount64() as f64 / 1000.0 }
+}
+
+/// Seconds since boot, including time spent asleep.
+#[cfg(target_vendor = "apple")]
+fn uptime_secs() -> f64 {
+    #[repr(C)]
+    struct TimebaseInfo {
+        numer: u32,
+        denom: u32,
+    }
+    extern "C" {
+        fn mach_continuous_time() -> u64;
+        fn mach_timebase_info(info: *mut TimebaseInfo) -> i32;
+    }
+
+    let mut timebase = TimebaseInfo { numer: 0, denom: 0 };
+    // SAFETY: `timebase` is a valid, writable `mach_timebase_info_data_t`
+    if unsafe { mach_timebase_info(&mut timebase) } != 0 || timebase.denom == 0 {
+        return process_uptime_secs();
+    }
+    // SAFETY: takes no arguments and can't fail
+    let ticks = unsafe { mach_continuous_time() };
+    ticks as f64 * timebase.numer as f64 / timebase.denom as f64 / 1e9
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "windows",
+    target_vendor = "apple"
+)))]
+fn uptime_secs() -> f64 {
+    process_uptime_secs()
+}
+
+/// Fallback that at least can't be set: time since the app started.
+#[cfg(not(target_os = "windows"))]
+fn process_uptime_secs() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+    STARTED.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
@@ -1,35 +1,106 @@
-use tauri::{AppHandle, Runtime};
+use std::collections::HashMap;
 
-use super::models::{BlockerStatus, InstalledApp, VpnStatus};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use super::lock::LockManager;
+use super::models::{
+    ApplyResult, BlockerStatus, BudgetSettings, BudgetStatus, ClockStatus, EscalationProfile,
+    EscalationStatus, InstalledApp, LockScope, LockStatus, LockdownStatus, PanicEscalationSettings,
+    ResetLockSettings, ResetLockStatus, SafeSearchEndpoint, SafeSearchStatus, VpnStatus,
+};
+use super::{catalog, clock, escalation, reset_lock, safe_search};
 
 // ─── Desktop commands (existing logic, restructured) ────────────────────────
 
 #[tauri::command]
-pub fn apply_blocklist(domains: Vec<String>) -> Result<(), String> {
+pub fn apply_blocklist<R: Runtime>(
+    app: AppHandle<R>,
+    domains: Vec<String>,
+) -> Result<ApplyResult, String> {
     #[cfg(desktop)]
     {
+        ensure_not_shrunk(&app, &domains)?;
+        let domains = escalation::blocklist_for(&app, domains)?;
         super::desktop::add_domains(&domains)?;
+        let dns_flush = super::desktop::flush_dns_cache();
+        let domains_applied = domains.len();
         super::desktop::start_watcher(domains);
+        let _ = app.emit("blocklist-applied", domains_applied);
+        Ok(ApplyResult {
+            domains_applied,
+            dns_flush,
+        })
     }
     #[cfg(not(desktop))]
     {
-        let _ = domains;
+        let _ = (app, domains);
         log::info!("apply_blocklist: desktop-only, no-op on mobile");
+        Ok(ApplyResult {
+            domains_applied: 0,
+            dns_flush: skipped_flush(),
+        })
     }
-    Ok(())
 }
 
 #[tauri::command]
-pub fn remove_blocklist() -> Result<(), String> {
+pub fn remove_blocklist<R: Runtime>(app: AppHandle<R>) -> Result<ApplyResult, String> {
+    app.state::<LockManager<R>>()
+        .ensure_unlocked(LockScope::Blocklist)?;
+    escalation::ensure_not_escalated(&app)?;
+    reset_lock::ensure_not_locked(&app)?;
+
     #[cfg(desktop)]
     {
         super::desktop::remove_domains()?;
+        super::desktop::stop_watcher();
+        Ok(ApplyResult {
+            domains_applied: 0,
+            dns_flush: super::desktop::flush_dns_cache(),
+        })
+    }
+    #[cfg(not(desktop))]
+    {
+        Ok(ApplyResult {
+            domains_applied: 0,
+            dns_flush: skipped_flush(),
+        })
+    }
+}
+
+/// Refuse a blocklist that drops any of the user's domains while the
+/// blocklist lock runs. Adding domains is always allowed.
+fn ensure_not_shrunk<R: Runtime>(app: &AppHandle<R>, domains: &[String]) -> Result<(), String> {
+    let Some(expiry) = app
+        .state::<LockManager<R>>()
+        .expires_at(LockScope::Blocklist)
+    else {
+        return Ok(());
+    };
+    let new = catalog::normalize(domains.to_vec());
+    let dropped = catalog::normalize(reset_lock::own_blocklist(app))
+        .into_iter()
+        .any(|d| new.binary_search(&d).is_err());
+    if dropped {
+        return Err(format!(
+            "Blocking is locked until {}; domains can only be added",
+            expiry.to_rfc3339()
+        ));
     }
     Ok(())
 }
 
+#[cfg(not(desktop))]
+fn skipped_flush() -> super::models::DnsFlushResult {
+    super::models::DnsFlushResult {
+        flushed: false,
+        method: None,
+        error: Some("Hosts file blocking is desktop-only".into()),
+    }
+}
+
 #[tauri::command]
-pub fn get_blocker_status() -> Result<BlockerStatus, String> {
+#[allow(clippy::needless_return)]
+pub fn get_blocker_status<R: Runtime>(app: AppHandle<R>) -> Result<BlockerStatus, String> {
     #[cfg(desktop)]
     {
         let is_admin = super::desktop::is_admin();
@@ -39,6 +110,7 @@ pub fn get_blocker_status() -> Result<BlockerStatus, String> {
             is_active,
             is_admin,
             blocked_domains,
+            safe_search: safe_search::status(&app),
         });
     }
     #[cfg(not(desktop))]
@@ -47,11 +119,13 @@ pub fn get_blocker_status() -> Result<BlockerStatus, String> {
             is_active: false,
             is_admin: false,
             blocked_domains: vec![],
+            safe_search: safe_search::status(&app),
         })
     }
 }
 
 #[tauri::command]
+#[allow(clippy::needless_return)]
 pub fn check_admin() -> Result<bool, String> {
     #[cfg(desktop)]
     {
@@ -64,16 +138,241 @@ pub fn check_admin() -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub fn extend_lock(hours: u64) -> Result<String, String> {
-    let now = chrono::Utc::now();
-    let new_expiry = now + chrono::Duration::hours(hours as i64);
-    let expiry_str = new_expiry.to_rfc3339();
-    log::info!(
-        "Lock extended by {} hours, new expiry: {}",
-        hours,
-        expiry_str
+pub fn extend_lock<R: Runtime>(
+    app: AppHandle<R>,
+    hours: u64,
+    scope: Option<LockScope>,
+) -> Result<String, String> {
+    let locks = app.state::<LockManager<R>>();
+    let expiry = locks.extend(scope.unwrap_or(LockScope::Blocklist), hours)?;
+    // `lock-applied` also follows locks the app sets itself; this one is the
+    // user's own
+    let _ = app.emit("lock-extended", expiry.to_rfc3339());
+    Ok(expiry.to_rfc3339())
+}
+
+#[tauri::command]
+pub fn get_lock_status<R: Runtime>(app: AppHandle<R>) -> Vec<LockStatus> {
+    app.state::<LockManager<R>>().status()
+}
+
+/// Clock jumps detected against uptime, and how far the wall clock is off.
+#[tauri::command]
+pub fn get_clock_status() -> ClockStatus {
+    clock::status()
+}
+
+// ─── SafeSearch (desktop hosts file, Android VPN) ───────────────────────────
+
+#[tauri::command]
+pub fn set_safe_search<R: Runtime>(
+    app: AppHandle<R>,
+    enabled: bool,
+) -> Result<SafeSearchStatus, String> {
+    safe_search::set_enabled(&app, enabled)?;
+    Ok(safe_search::status(&app))
+}
+
+#[tauri::command]
+pub fn get_safe_search_endpoints<R: Runtime>(app: AppHandle<R>) -> Vec<SafeSearchEndpoint> {
+    safe_search::endpoints(&app)
+}
+
+#[tauri::command]
+pub fn update_safe_search_endpoints<R: Runtime>(
+    app: AppHandle<R>,
+    endpoints: Vec<SafeSearchEndpoint>,
+) -> Result<SafeSearchStatus, String> {
+    safe_search::set_endpoints(&app, endpoints)?;
+    Ok(safe_search::status(&app))
+}
+
+// ─── Lockdown mode (desktop local resolver) ─────────────────────────────────
+
+#[tauri::command]
+pub fn start_lockdown<R: Runtime>(
+    app: AppHandle<R>,
+    allowlist: Vec<String>,
+    hours: u64,
+) -> Result<LockdownStatus, String> {
+    #[cfg(desktop)]
+    {
+        super::lockdown::start(&app, allowlist, hours)
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = (app, allowlist, hours);
+        Err("Lockdown mode is only available on desktop".into())
+    }
+}
+
+#[tauri::command]
+pub fn stop_lockdown<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    #[cfg(desktop)]
+    {
+        super::lockdown::stop(&app)
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Err("Lockdown mode is only available on desktop".into())
+    }
+}
+
+#[tauri::command]
+pub fn get_lockdown_status<R: Runtime>(app: AppHandle<R>) -> Result<LockdownStatus, String> {
+    #[cfg(desktop)]
+    {
+        Ok(super::lockdown::status(&app))
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Err("Lockdown mode is only available on desktop".into())
+    }
+}
+
+// ─── Time budgets (desktop local resolver) ──────────────────────────────────
+
+#[tauri::command]
+pub fn get_budget_settings<R: Runtime>(app: AppHandle<R>) -> Result<BudgetSettings, String> {
+    #[cfg(desktop)]
+    {
+        Ok(super::budget::load_settings(&app))
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Err("Time budgets are only available on desktop".into())
+    }
+}
+
+#[tauri::command]
+pub fn set_budget_settings<R: Runtime>(
+    app: AppHandle<R>,
+    settings: BudgetSettings,
+) -> Result<Vec<BudgetStatus>, String> {
+    #[cfg(desktop)]
+    {
+        super::budget::set_settings(&app, settings)
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = (app, settings);
+        Err("Time budgets are only available on desktop".into())
+    }
+}
+
+#[tauri::command]
+pub fn get_budget_status<R: Runtime>(app: AppHandle<R>) -> Result<Vec<BudgetStatus>, String> {
+    #[cfg(desktop)]
+    {
+        Ok(super::budget::status(&app))
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Err("Time budgets are only available on desktop".into())
+    }
+}
+
+// ─── Category catalog & panic escalation ────────────────────────────────────
+
+#[tauri::command]
+pub fn sync_block_catalog<R: Runtime>(
+    app: AppHandle<R>,
+    categories: HashMap<String, Vec<String>>,
+) -> Result<(), String> {
+    ensure_catalog_kept(&app, &categories)?;
+    catalog::set(&app, categories)
+}
+
+/// Refuse a catalog that drops domains from a category being blocked or
+/// metered while a lock, escalation or reset lock runs: the categories the
+/// user's blocklist covers and the escalated, reset-locked and budgeted ones.
+/// Adding domains is always allowed.
+fn ensure_catalog_kept<R: Runtime>(
+    app: &AppHandle<R>,
+    categories: &HashMap<String, Vec<String>>,
+) -> Result<(), String> {
+    let locks = app.state::<LockManager<R>>();
+    let escalated = escalation::status(app);
+    let reset = reset_lock::status(app);
+    if !locks.is_locked(LockScope::Blocklist)
+        && !locks.is_locked(LockScope::Lockdown)
+        && escalated.is_none()
+        && reset.is_none()
+    {
+        return Ok(());
+    }
+
+    let current = catalog::load(app);
+    let mut kept = escalation::enabled_categories(app);
+    if escalated.is_some_and(|e| e.profile == EscalationProfile::AllCategories) {
+        kept.extend(current.keys().cloned());
+    }
+    if let Some(reset) = reset {
+        kept.extend(reset.categories);
+    }
+    #[cfg(desktop)]
+    kept.extend(
+        super::budget::load_settings(app)
+            .budgets
+            .into_iter()
+            .map(|b| b.category),
     );
-    Ok(expiry_str)
+
+    for category in kept {
+        let Some(domains) = current.get(&category) else {
+            continue;
+        };
+        let new = catalog::normalize(categories.get(&category).cloned().unwrap_or_default());
+        if domains.iter().any(|d| new.binary_search(d).is_err()) {
+            return Err(format!(
+                "Blocking is locked; the {} category can only gain domains",
+                category
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_panic_escalation_settings<R: Runtime>(app: AppHandle<R>) -> PanicEscalationSettings {
+    escalation::load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_panic_escalation_settings<R: Runtime>(
+    app: AppHandle<R>,
+    settings: PanicEscalationSettings,
+) -> Result<(), String> {
+    escalation::save_settings(&app, &settings)
+}
+
+#[tauri::command]
+pub fn get_escalation_status<R: Runtime>(app: AppHandle<R>) -> Option<EscalationStatus> {
+    escalation::status(&app)
+}
+
+// ─── Protective lock after a streak reset ───────────────────────────────────
+
+#[tauri::command]
+pub fn get_reset_lock_settings<R: Runtime>(app: AppHandle<R>) -> ResetLockSettings {
+    reset_lock::load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_reset_lock_settings<R: Runtime>(
+    app: AppHandle<R>,
+    settings: ResetLockSettings,
+) -> Result<(), String> {
+    reset_lock::save_settings(&app, settings)
+}
+
+#[tauri::command]
+pub fn get_reset_lock_status<R: Runtime>(app: AppHandle<R>) -> Option<ResetLockStatus> {
+    reset_lock::status(&app)
 }
 
 // ─── Mobile commands (Android VPN + App blocking) ───────────────────────────
@@ -85,8 +384,13 @@ pub fn start_vpn_blocker<R: Runtime>(
 ) -> Result<(), String> {
     #[cfg(mobile)]
     {
+        ensure_not_shrunk(&app, &domains)?;
+        let domains = escalation::blocklist_for(&app, domains)?;
+        let domains_applied = domains.len();
         let state = app.state::<super::BlockerMobile<R>>();
-        return state.start_vpn(domains);
+        state.start_vpn(domains)?;
+        let _ = app.emit("blocklist-applied", domains_applied);
+        return Ok(());
     }
     #[cfg(not(mobile))]
     {
@@ -99,6 +403,10 @@ pub fn start_vpn_blocker<R: Runtime>(
 pub fn stop_vpn_blocker<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
     #[cfg(mobile)]
     {
+        app.state::<LockManager<R>>()
+            .ensure_unlocked(LockScope::Blocklist)?;
+        escalation::ensure_not_escalated(&app)?;
+        reset_lock::ensure_not_locked(&app)?;
         let state = app.state::<super::BlockerMobile<R>>();
         return state.stop_vpn();
     }
@@ -1,6 +1,10 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 use tokio::time;
 
+use super::models::{DnsFlushResult, SafeSearchEndpoint};
+
 // ─── Hosts file constants (Windows only) ────────────────────────────────────
 
 #[cfg(target_os = "windows")]
@@ -12,21 +16,25 @@ const MARKER_START: &str = "# === TAMASHII START ===";
 #[cfg(target_os = "windows")]
 const MARKER_END: &str = "# === TAMASHII END ===";
 
+#[cfg(target_os = "windows")]
+const SAFE_SEARCH_START: &str = "# === TAMASHII SAFESEARCH START ===";
+
+#[cfg(target_os = "windows")]
+const SAFE_SEARCH_END: &str = "# === TAMASHII SAFESEARCH END ===";
+
+#[cfg(target_os = "windows")]
+const ENGINE_PREFIX: &str = "# engine:";
+
 // ─── Hosts file manipulation ────────────────────────────────────────────────
 
 #[cfg(target_os = "windows")]
 pub fn add_domains(domains: &[String]) -> Result<(), String> {
-    use std::fs;
-    use std::io::Write;
-
     if domains.is_empty() {
         return remove_domains();
     }
 
-    let content = fs::read_to_string(HOSTS_PATH)
-        .map_err(|e| format!("Failed to read hosts file: {}. Are you running as admin?", e))?;
-
-    let cleaned = remove_tamashii_block(&content);
+    let content = read_hosts()?;
+    let cleaned = remove_block(&content, MARKER_START, MARKER_END);
 
     let mut block = String::new();
     block.push('\n');
@@ -45,21 +53,7 @@ pub fn add_domains(domains: &[String]) -> Result<(), String> {
     block.push_str(MARKER_END);
     block.push('\n');
 
-    let new_content = format!("{}{}", cleaned.trim_end(), block);
-
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(HOSTS_PATH)
-        .map_err(|e| {
-            format!(
-                "Failed to open hosts file for writing: {}. Are you running as admin?",
-                e
-            )
-        })?;
-
-    file.write_all(new_content.as_bytes())
-        .map_err(|e| format!("Failed to write hosts file: {}", e))?;
+    write_hosts(&format!("{}{}", cleaned.trim_end(), block))?;
 
     log::info!("Applied {} domains to hosts file", domains.len());
     Ok(())
@@ -76,27 +70,8 @@ pub fn add_domains(domains: &[String]) -> Result<(), String> {
 
 #[cfg(target_os = "windows")]
 pub fn remove_domains() -> Result<(), String> {
-    use std::fs;
-    use std::io::Write;
-
-    let content = fs::read_to_string(HOSTS_PATH)
-        .map_err(|e| format!("Failed to read hosts file: {}. Are you running as admin?", e))?;
-
-    let cleaned = remove_tamashii_block(&content);
-
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(HOSTS_PATH)
-        .map_err(|e| {
-            format!(
-                "Failed to open hosts file for writing: {}. Are you running as admin?",
-                e
-            )
-        })?;
-
-    file.write_all(cleaned.as_bytes())
-        .map_err(|e| format!("Failed to write hosts file: {}", e))?;
+    let content = read_hosts()?;
+    write_hosts(&remove_block(&content, MARKER_START, MARKER_END))?;
 
     log::info!("Removed TAMASHII block from hosts file");
     Ok(())
@@ -110,10 +85,7 @@ pub fn remove_domains() -> Result<(), String> {
 
 #[cfg(target_os = "windows")]
 pub fn get_blocked_domains() -> Result<Vec<String>, String> {
-    use std::fs;
-
-    let content = fs::read_to_string(HOSTS_PATH)
-        .map_err(|e| format!("Failed to read hosts file: {}", e))?;
+    let content = read_hosts()?;
 
     let mut domains = Vec::new();
     let mut in_block = false;
@@ -148,10 +120,7 @@ pub fn get_blocked_domains() -> Result<Vec<String>, String> {
 #[cfg(target_os = "windows")]
 pub fn is_admin() -> bool {
     use std::fs;
-    fs::OpenOptions::new()
-        .write(true)
-        .open(HOSTS_PATH)
-        .is_ok()
+    fs::OpenOptions::new().write(true).open(HOSTS_PATH).is_ok()
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -160,17 +129,48 @@ pub fn is_admin() -> bool {
 }
 
 #[cfg(target_os = "windows")]
-fn remove_tamashii_block(content: &str) -> String {
+fn read_hosts() -> Result<String, String> {
+    std::fs::read_to_string(HOSTS_PATH).map_err(|e| {
+        format!(
+            "Failed to read hosts file: {}. Are you running as admin?",
+            e
+        )
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn write_hosts(content: &str) -> Result<(), String> {
+    use std::fs;
+    use std::io::Write;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(HOSTS_PATH)
+        .map_err(|e| {
+            format!(
+                "Failed to open hosts file for writing: {}. Are you running as admin?",
+                e
+            )
+        })?;
+
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to write hosts file: {}", e))
+}
+
+/// Remove a marker-delimited TAMASHII block from hosts file content.
+#[cfg(target_os = "windows")]
+fn remove_block(content: &str, start: &str, end: &str) -> String {
     let mut result = String::new();
     let mut in_block = false;
 
     for line in content.lines() {
         let trimmed = line.trim();
-        if trimmed == MARKER_START {
+        if trimmed == start {
             in_block = true;
             continue;
         }
-        if trimmed == MARKER_END {
+        if trimmed == end {
             in_block = false;
             continue;
         }
@@ -183,28 +183,424 @@ fn remove_tamashii_block(content: &str) -> String {
     result
 }
 
+// ─── SafeSearch enforcement ─────────────────────────────────────────────────
+
+/// Pin each engine's domains to the resolved addresses of its safe-search
+/// endpoint. Engines whose endpoint can't be resolved right now are skipped;
+/// returns the engines that were written.
+#[cfg(target_os = "windows")]
+pub fn apply_safe_search(endpoints: &[SafeSearchEndpoint]) -> Result<Vec<String>, String> {
+    let content = read_hosts()?;
+    let cleaned = remove_block(&content, SAFE_SEARCH_START, SAFE_SEARCH_END);
+
+    let mut enforced = Vec::new();
+    let mut block = String::new();
+    block.push('\n');
+    block.push_str(SAFE_SEARCH_START);
+    block.push('\n');
+    for endpoint in endpoints {
+        let addrs = resolve_endpoint(&endpoint.target);
+        if addrs.is_empty() {
+            log::warn!(
+                "Could not resolve SafeSearch endpoint {} for {}",
+                endpoint.target,
+                endpoint.engine
+            );
+            continue;
+        }
+        block.push_str(&format!("{} {}\n", ENGINE_PREFIX, endpoint.engine));
+        for domain in &endpoint.domains {
+            for addr in &addrs {
+                block.push_str(&format!("{} {}\n", addr, domain));
+            }
+        }
+        enforced.push(endpoint.engine.clone());
+    }
+    block.push_str(SAFE_SEARCH_END);
+    block.push('\n');
+
+    write_hosts(&format!("{}{}", cleaned.trim_end(), block))?;
+    Ok(enforced)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn apply_safe_search(endpoints: &[SafeSearchEndpoint]) -> Result<Vec<String>, String> {
+    log::info!(
+        "Hosts file SafeSearch not available on this platform ({} engines requested)",
+        endpoints.len()
+    );
+    Ok(Vec::new())
+}
+
+#[cfg(target_os = "windows")]
+pub fn remove_safe_search() -> Result<(), String> {
+    let content = read_hosts()?;
+    write_hosts(&remove_block(&content, SAFE_SEARCH_START, SAFE_SEARCH_END))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn remove_safe_search() -> Result<(), String> {
+    Ok(())
+}
+
+/// Engines currently pinned by the SafeSearch block in the hosts file.
+#[cfg(target_os = "windows")]
+pub fn get_safe_search_engines() -> Result<Vec<String>, String> {
+    let content = read_hosts()?;
+
+    let mut engines = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed == SAFE_SEARCH_START {
+            in_block = true;
+            continue;
+        }
+        if trimmed == SAFE_SEARCH_END {
+            break;
+        }
+        if in_block {
+            if let Some(engine) = trimmed.strip_prefix(ENGINE_PREFIX) {
+                engines.push(engine.trim().to_string());
+            }
+        }
+    }
+
+    Ok(engines)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn get_safe_search_engines() -> Result<Vec<String>, String> {
+    Ok(Vec::new())
+}
+
+/// Resolve an enforcement host to at most one IPv4 and one IPv6 address.
+#[cfg(target_os = "windows")]
+fn resolve_endpoint(host: &str) -> Vec<std::net::IpAddr> {
+    use std::net::ToSocketAddrs;
+
+    let Ok(addrs) = (host, 443).to_socket_addrs() else {
+        return Vec::new();
+    };
+    let addrs: Vec<_> = addrs.map(|a| a.ip()).collect();
+
+    [
+        addrs.iter().find(|ip| ip.is_ipv4()),
+        addrs.iter().find(|ip| ip.is_ipv6()),
+    ]
+    .into_iter()
+    .flatten()
+    .copied()
+    .collect()
+}
+
+// ─── DNS cache flush ────────────────────────────────────────────────────────
+
+/// Flush the OS resolver cache so hosts file changes take effect immediately
+/// instead of after cached answers expire. The flush command is chosen by
+/// probing which tools are installed.
+pub fn flush_dns_cache() -> DnsFlushResult {
+    let Some((program, args)) = flush_candidates()
+        .iter()
+        .find(|(program, _)| find_in_path(program).is_some())
+    else {
+        log::warn!("No DNS cache flush tool found on this system");
+        return DnsFlushResult {
+            flushed: false,
+            method: None,
+            error: Some("No DNS cache flush tool found".into()),
+        };
+    };
+
+    #[allow(unused_mut)]
+    let mut method = std::iter::once(*program)
+        .chain(args.iter().copied())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let error = match hidden_command(program).args(*args).output() {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(format!(
+            "{} exited with {}: {}",
+            method,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) => Some(format!("Failed to run {}: {}", method, e)),
+    };
+
+    // macOS keeps a second cache in mDNSResponder that dscacheutil doesn't clear.
+    #[cfg(target_os = "macos")]
+    if error.is_none() {
+        match std::process::Command::new("killall")
+            .args(["-HUP", "mDNSResponder"])
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                method.push_str(" && killall -HUP mDNSResponder");
+            }
+            Ok(output) => log::warn!(
+                "Failed to signal mDNSResponder: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => log::warn!("Failed to signal mDNSResponder: {}", e),
+        }
+    }
+
+    match &error {
+        None => log::info!("Flushed DNS cache with `{}`", method),
+        Some(e) => log::error!("DNS cache flush failed: {}", e),
+    }
+
+    DnsFlushResult {
+        flushed: error.is_none(),
+        method: Some(method),
+        error,
+    }
+}
+
+/// Flush commands in order of preference for the current OS.
+fn flush_candidates() -> &'static [(&'static str, &'static [&'static str])] {
+    #[cfg(target_os = "windows")]
+    {
+        &[("ipconfig", &["/flushdns"])]
+    }
+    #[cfg(target_os = "macos")]
+    {
+        &[("dscacheutil", &["-flushcache"])]
+    }
+    #[cfg(target_os = "linux")]
+    {
+        &[
+            ("resolvectl", &["flush-caches"]),
+            ("systemd-resolve", &["--flush-caches"]),
+            ("nscd", &["--invalidate=hosts"]),
+        ]
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        &[]
+    }
+}
+
+/// Locate an executable on PATH, trying the `.exe` suffix on Windows.
+fn find_in_path(program: &str) -> Option<std::path::PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if cfg!(target_os = "windows") {
+            let candidate = dir.join(format!("{}.exe", program));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+        None
+    })
+}
+
+/// A command that won't flash a console window when run from the GUI process.
+fn hidden_command(program: &str) -> std::process::Command {
+    #[allow(unused_mut)]
+    let mut command = std::process::Command::new(program);
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    command
+}
+
+// ─── System DNS redirection (Windows only) ──────────────────────────────────
+
+/// Point the DNS servers of every connected adapter at the local resolver.
+/// Returns the previous configuration, for `restore_system_dns`.
+#[cfg(target_os = "windows")]
+pub fn redirect_system_dns() -> Result<String, String> {
+    let saved = run_powershell(
+        "Get-DnsClientServerAddress | Select-Object InterfaceIndex, ServerAddresses \
+         | ConvertTo-Json -Compress",
+    )?;
+    run_powershell(
+        "Get-NetAdapter | Where-Object Status -eq 'Up' \
+         | Set-DnsClientServerAddress -ServerAddresses 127.0.0.1, ::1",
+    )?;
+
+    log::info!("System DNS redirected to the local resolver");
+    Ok(saved)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn redirect_system_dns() -> Result<String, String> {
+    Err("Redirecting system DNS is only supported on Windows".into())
+}
+
+/// Put back the DNS servers saved by `redirect_system_dns`. Adapters with no
+/// saved servers, or if the saved configuration can't be read, go back to DHCP.
+#[cfg(target_os = "windows")]
+pub fn restore_system_dns(saved: &str) -> Result<(), String> {
+    use std::collections::BTreeMap;
+    use std::net::IpAddr;
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct SavedServers {
+        interface_index: u32,
+        #[serde(default)]
+        server_addresses: Option<Vec<String>>,
+    }
+
+    // ConvertTo-Json emits a bare object when there is a single entry
+    let entries: Vec<SavedServers> = serde_json::from_str(saved)
+        .or_else(|_| serde_json::from_str(saved).map(|entry| vec![entry]))
+        .unwrap_or_default();
+
+    // One entry per address family; merge them per adapter. Only well-formed,
+    // non-loopback addresses make it into the script.
+    let mut adapters: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for entry in entries {
+        let servers = adapters.entry(entry.interface_index).or_default();
+        servers.extend(
+            entry
+                .server_addresses
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|s| s.parse::<IpAddr>().is_ok_and(|ip| !ip.is_loopback())),
+        );
+    }
+
+    let script = if adapters.is_empty() {
+        "Get-NetAdapter | Set-DnsClientServerAddress -ResetServerAddresses".to_string()
+    } else {
+        adapters
+            .iter()
+            .map(|(index, servers)| {
+                if servers.is_empty() {
+                    format!(
+                        "Set-DnsClientServerAddress -InterfaceIndex {} -ResetServerAddresses",
+                        index
+                    )
+                } else {
+                    let list = servers
+                        .iter()
+                        .map(|s| format!("'{}'", s))
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    format!(
+                        "Set-DnsClientServerAddress -InterfaceIndex {} -ServerAddresses {}",
+                        index, list
+                    )
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+    run_powershell(&script)?;
+
+    log::info!("System DNS restored");
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn restore_system_dns(saved: &str) -> Result<(), String> {
+    let _ = saved;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn run_powershell(script: &str) -> Result<String, String> {
+    let output = hidden_command("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .output()
+        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "PowerShell failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 // ─── Tamper-protection watcher ──────────────────────────────────────────────
 
+/// Domains the watcher keeps re-applying. A new blocklist replaces the list
+/// rather than starting a second watcher that would fight the first.
+static WATCHED_DOMAINS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// SafeSearch endpoints the watcher puts back if their hosts block goes.
+static WATCHED_SAFE_SEARCH: Mutex<Vec<SafeSearchEndpoint>> = Mutex::new(Vec::new());
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
 pub fn start_watcher(domains: Vec<String>) {
+    log::info!("Blocker watcher set to {} domains", domains.len());
+    *WATCHED_DOMAINS.lock().unwrap() = domains;
+    spawn_watcher();
+}
+
+/// Keep the SafeSearch block in the hosts file; an empty list stops that.
+#[cfg(target_os = "windows")]
+pub fn watch_safe_search(endpoints: Vec<SafeSearchEndpoint>) {
+    let watching = !endpoints.is_empty();
+    *WATCHED_SAFE_SEARCH.lock().unwrap() = endpoints;
+    if watching {
+        spawn_watcher();
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn watch_safe_search(endpoints: Vec<SafeSearchEndpoint>) {
+    let _ = endpoints;
+}
+
+fn spawn_watcher() {
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
     tauri::async_runtime::spawn(async move {
         let mut interval = time::interval(Duration::from_secs(60));
-        log::info!("Blocker watcher started for {} domains", domains.len());
+        log::info!("Blocker watcher started");
 
         loop {
             interval.tick().await;
 
-            if domains.is_empty() {
-                continue;
+            let domains = watched_domains();
+            if !domains.is_empty() {
+                match add_domains(&domains) {
+                    Ok(()) => {
+                        log::debug!("Watcher re-applied {} blocked domains", domains.len());
+                    }
+                    Err(e) => {
+                        log::error!("Watcher failed to re-apply hosts file: {}", e);
+                    }
+                }
             }
 
-            match add_domains(&domains) {
-                Ok(()) => {
-                    log::debug!("Watcher re-applied {} blocked domains", domains.len());
-                }
-                Err(e) => {
-                    log::error!("Watcher failed to re-apply hosts file: {}", e);
+            // Only re-applied when missing: applying resolves every endpoint.
+            let endpoints = WATCHED_SAFE_SEARCH.lock().unwrap().clone();
+            if !endpoints.is_empty() && get_safe_search_engines().unwrap_or_default().is_empty() {
+                match apply_safe_search(&endpoints) {
+                    Ok(enforced) => log::warn!("Watcher restored SafeSearch for {:?}", enforced),
+                    Err(e) => log::error!("Watcher failed to restore SafeSearch: {}", e),
                 }
             }
         }
     });
 }
+
+/// Stop re-applying domains, e.g. after the blocklist was removed.
+pub fn stop_watcher() {
+    WATCHED_DOMAINS.lock().unwrap().clear();
+}
+
+/// The blocklist currently enforced by the watcher.
+pub fn watched_domains() -> Vec<String> {
+    WATCHED_DOMAINS.lock().unwrap().clone()
+}
@@ -0,0 +1,433 @@
+// Minimal DNS message handling for the local resolver: reading the question
+// out of a query and building the few answers the resolver sends itself.
+
+const HEADER_LEN: usize = 12;
+
+/// Longest label and longest name in wire format, per RFC 1035.
+const MAX_LABEL_LEN: usize = 63;
+const MAX_NAME_LEN: usize = 255;
+
+const TYPE_A: u16 = 1;
+const TYPE_CNAME: u16 = 5;
+const TYPE_AAAA: u16 = 28;
+
+const RCODE_SERVFAIL: u8 = 2;
+const RCODE_NXDOMAIN: u8 = 3;
+
+/// The first question of a DNS query.
+pub struct Question {
+    /// Lowercased name without the trailing dot.
+    pub name: String,
+    pub qtype: u16,
+    /// Offset just past the question section.
+    end: usize,
+}
+
+pub fn parse_question(query: &[u8]) -> Option<Question> {
+    if query.len() < HEADER_LEN {
+        return None;
+    }
+    // Must be a query (QR=0) with at least one question
+    if query[2] & 0x80 != 0 || u16::from_be_bytes([query[4], query[5]]) == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut offset = HEADER_LEN;
+    loop {
+        let len = *query.get(offset)? as usize;
+        if len == 0 {
+            offset += 1;
+            break;
+        }
+        // Compression pointers never appear in a query's first question
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        let label = query.get(offset + 1..offset + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_lowercase());
+        offset += len + 1;
+    }
+
+    let qtype = u16::from_be_bytes([*query.get(offset)?, *query.get(offset + 1)?]);
+    let end = offset + 4;
+    if end > query.len() {
+        return None;
+    }
+
+    Some(Question {
+        name: labels.join("."),
+        qtype,
+        end,
+    })
+}
+
+pub fn nxdomain(query: &[u8], question: &Question) -> Vec<u8> {
+    response_header(query, question, RCODE_NXDOMAIN, 0)
+}
+
+pub fn servfail(query: &[u8], question: &Question) -> Vec<u8> {
+    response_header(query, question, RCODE_SERVFAIL, 0)
+}
+
+/// Build a bare recursive query for `name`, or `None` if the name can't be
+/// encoded.
+pub fn build_query(id: u16, name: &str, qtype: u16) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    out.extend_from_slice(&id.to_be_bytes());
+    // Flags: RD=1; QDCOUNT=1; ANCOUNT/NSCOUNT/ARCOUNT=0
+    out.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    out.extend_from_slice(&encode_name(name)?);
+    out.extend_from_slice(&qtype.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes()); // Class IN
+    Some(out)
+}
+
+/// Answer `query` with a CNAME to `target`, followed by the A/AAAA records
+/// from `target_response`, the upstream answer for `target`. `None` if the
+/// upstream answer is incomplete or `target` can't be encoded.
+pub fn cname_response(
+    query: &[u8],
+    question: &Question,
+    target: &str,
+    target_response: &[u8],
+) -> Option<Vec<u8>> {
+    let records = address_records(target_response)?;
+    let mut out = response_header(query, question, 0, 1 + records.len() as u16);
+
+    // CNAME answer, named by pointer to the question at offset 12
+    let encoded_target = encode_name(target)?;
+    out.extend_from_slice(&0xC00Cu16.to_be_bytes());
+    out.extend_from_slice(&TYPE_CNAME.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&300u32.to_be_bytes());
+    out.extend_from_slice(&(encoded_target.len() as u16).to_be_bytes());
+    let target_offset = out.len() as u16;
+    out.extend_from_slice(&encoded_target);
+
+    // Address records, named by pointer to the CNAME target
+    for record in records {
+        out.extend_from_slice(&(0xC000 | target_offset).to_be_bytes());
+        out.extend_from_slice(&record.rtype.to_be_bytes());
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&record.ttl.to_be_bytes());
+        out.extend_from_slice(&(record.data.len() as u16).to_be_bytes());
+        out.extend_from_slice(record.data);
+    }
+
+    Some(out)
+}
+
+/// Lower the TTL of every answer record in `response` to at most `max`.
+pub fn cap_ttl(response: &mut [u8], max: u32) {
+    if response.len() < HEADER_LEN {
+        return;
+    }
+    let qdcount = u16::from_be_bytes([response[4], response[5]]);
+    let ancount = u16::from_be_bytes([response[6], response[7]]);
+
+    let mut offset = HEADER_LEN;
+    for _ in 0..qdcount {
+        let Some(end) = skip_name(response, offset) else {
+            return;
+        };
+        offset = end + 4;
+    }
+
+    for _ in 0..ancount {
+        let Some(end) = skip_name(response, offset) else {
+            return;
+        };
+        let Some(fixed) = response.get_mut(end..end + 10) else {
+            return;
+        };
+        let ttl = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+        fixed[4..8].copy_from_slice(&ttl.min(max).to_be_bytes());
+        let rdlength = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        offset = end + 10 + rdlength;
+    }
+}
+
+/// Header and question of `query`, turned into a response. Any additional
+/// records on the query (e.g. EDNS) are dropped.
+fn response_header(query: &[u8], question: &Question, rcode: u8, ancount: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(512);
+    out.extend_from_slice(&query[0..2]);
+    out.push(0x80 | (query[2] & 0x01)); // QR=1, keep RD
+    out.push(0x80 | rcode); // RA=1
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&ancount.to_be_bytes());
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&query[HEADER_LEN..question.end]);
+    out
+}
+
+struct AddressRecord<'a> {
+    rtype: u16,
+    ttl: u32,
+    data: &'a [u8],
+}
+
+/// A and AAAA records from the answer section of a response. Intermediate
+/// CNAMEs are dropped, since the rewritten answer supplies its own.
+fn address_records(response: &[u8]) -> Option<Vec<AddressRecord<'_>>> {
+    if response.len() < HEADER_LEN {
+        return None;
+    }
+    let qdcount = u16::from_be_bytes([response[4], response[5]]);
+    let ancount = u16::from_be_bytes([response[6], response[7]]);
+
+    let mut offset = HEADER_LEN;
+    for _ in 0..qdcount {
+        offset = skip_name(response, offset)? + 4;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..ancount {
+        offset = skip_name(response, offset)?;
+        let fixed = response.get(offset..offset + 10)?;
+        let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let ttl = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+        let rdlength = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let data = response.get(offset + 10..offset + 10 + rdlength)?;
+
+        if rtype == TYPE_A || rtype == TYPE_AAAA {
+            records.push(AddressRecord { rtype, ttl, data });
+        }
+        offset += 10 + rdlength;
+    }
+
+    Some(records)
+}
+
+/// Offset just past the (possibly compressed) name starting at `start`.
+fn skip_name(msg: &[u8], start: usize) -> Option<usize> {
+    let mut offset = start;
+    loop {
+        let len = *msg.get(offset)? as usize;
+        if len == 0 {
+            return Some(offset + 1);
+        }
+        if len & 0xC0 == 0xC0 {
+            return Some(offset + 2);
+        }
+        offset += len + 1;
+    }
+}
+
+/// `name` in wire format, or `None` if a label is empty or longer than 63
+/// bytes, or the name is longer than 255 bytes.
+fn encode_name(name: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(name.len() + 2);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return None;
+        }
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    if out.len() > MAX_NAME_LEN {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An upstream response to `query` whose answers are named by pointer
+    /// to the question, as resolvers send them.
+    fn response(query: &[u8], answers: &[(u16, u32, &[u8])]) -> Vec<u8> {
+        let question = parse_question(query).unwrap();
+        let mut out = response_header(query, &question, 0, answers.len() as u16);
+        for (rtype, ttl, data) in answers {
+            out.extend_from_slice(&0xC00Cu16.to_be_bytes());
+            out.extend_from_slice(&rtype.to_be_bytes());
+            out.extend_from_slice(&1u16.to_be_bytes());
+            out.extend_from_slice(&ttl.to_be_bytes());
+            out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    fn ttls(response: &[u8]) -> Vec<u32> {
+        let mut offset = skip_name(response, HEADER_LEN).unwrap() + 4;
+        let ancount = u16::from_be_bytes([response[6], response[7]]);
+        (0..ancount)
+            .map(|_| {
+                let end = skip_name(response, offset).unwrap();
+                let fixed = &response[end..end + 10];
+                offset = end + 10 + u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+                u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_question_of_a_query() {
+        let query = build_query(0x1234, "WWW.Example.COM.", TYPE_AAAA).unwrap();
+        let question = parse_question(&query).unwrap();
+        assert_eq!(question.name, "www.example.com");
+        assert_eq!(question.qtype, TYPE_AAAA);
+        assert_eq!(question.end, query.len());
+    }
+
+    #[test]
+    fn rejects_responses_and_truncated_queries() {
+        let query = build_query(1, "example.com", TYPE_A).unwrap();
+        for len in 0..query.len() {
+            assert!(parse_question(&query[..len]).is_none(), "length {}", len);
+        }
+
+        let mut answered = query.clone();
+        answered[2] |= 0x80;
+        assert!(parse_question(&answered).is_none());
+
+        let mut no_question = query.clone();
+        no_question[5] = 0;
+        assert!(parse_question(&no_question).is_none());
+    }
+
+    #[test]
+    fn rejects_a_compressed_question_name() {
+        let mut query = build_query(1, "example.com", TYPE_A).unwrap();
+        query.truncate(HEADER_LEN);
+        query.extend_from_slice(&[0xC0, 0x0C, 0, 1, 0, 1]);
+        assert!(parse_question(&query).is_none());
+    }
+
+    #[test]
+    fn nxdomain_echoes_the_query() {
+        let query = build_query(0xBEEF, "blocked.example", TYPE_A).unwrap();
+        let question = parse_question(&query).unwrap();
+        let answer = nxdomain(&query, &question);
+
+        assert_eq!(&answer[0..2], &[0xBE, 0xEF]);
+        assert_eq!(answer[2], 0x81, "QR set and RD kept");
+        assert_eq!(answer[3] & 0x0F, RCODE_NXDOMAIN);
+        assert_eq!(&answer[4..12], &[0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&answer[HEADER_LEN..], &query[HEADER_LEN..]);
+    }
+
+    #[test]
+    fn cname_response_points_at_the_target() {
+        let query = build_query(7, "www.google.com", TYPE_A).unwrap();
+        let question = parse_question(&query).unwrap();
+        let upstream_query = build_query(8, "forcesafesearch.google.com", TYPE_A).unwrap();
+        let upstream = response(
+            &upstream_query,
+            &[
+                (TYPE_CNAME, 60, &encode_name("other.google.com").unwrap()),
+                (TYPE_A, 120, &[216, 239, 38, 120]),
+            ],
+        );
+
+        let answer =
+            cname_response(&query, &question, "forcesafesearch.google.com", &upstream).unwrap();
+
+        assert_eq!(&answer[0..2], &[0, 7]);
+        assert_eq!(u16::from_be_bytes([answer[6], answer[7]]), 2);
+
+        // The CNAME, named by pointer to the question
+        let cname = question.end;
+        assert_eq!(&answer[cname..cname + 2], &[0xC0, 0x0C]);
+        assert_eq!(&answer[cname + 2..cname + 4], &TYPE_CNAME.to_be_bytes());
+        let target = encode_name("forcesafesearch.google.com").unwrap();
+        let rdlength = u16::from_be_bytes([answer[cname + 10], answer[cname + 11]]) as usize;
+        assert_eq!(&answer[cname + 12..cname + 12 + rdlength], &target[..]);
+
+        // Only the address record is carried over, named by pointer to the target
+        let records = address_records(&answer).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].rtype, TYPE_A);
+        assert_eq!(records[0].ttl, 120);
+        assert_eq!(records[0].data, &[216, 239, 38, 120]);
+        let a = cname + 12 + rdlength;
+        assert_eq!(
+            u16::from_be_bytes([answer[a], answer[a + 1]]),
+            0xC000 | (cname + 12) as u16
+        );
+    }
+
+    #[test]
+    fn cname_response_needs_a_complete_upstream_answer() {
+        let query = build_query(7, "www.bing.com", TYPE_A).unwrap();
+        let question = parse_question(&query).unwrap();
+        let upstream_query = build_query(8, "strict.bing.com", TYPE_A).unwrap();
+        let upstream = response(&upstream_query, &[(TYPE_A, 60, &[204, 79, 197, 220])]);
+
+        assert!(cname_response(&query, &question, "strict.bing.com", &upstream).is_some());
+        assert!(cname_response(
+            &query,
+            &question,
+            "strict.bing.com",
+            &upstream[..upstream.len() - 1]
+        )
+        .is_none());
+        assert!(cname_response(&query, &question, "strict.bing.com", &upstream[..4]).is_none());
+    }
+
+    #[test]
+    fn cap_ttl_lowers_only_longer_ttls() {
+        let query = build_query(1, "example.com", TYPE_A).unwrap();
+        let mut answer = response(
+            &query,
+            &[
+                (TYPE_CNAME, 3600, &encode_name("cdn.example.net").unwrap()),
+                (TYPE_A, 30, &[93, 184, 216, 34]),
+                (TYPE_A, 86400, &[93, 184, 216, 35]),
+            ],
+        );
+
+        cap_ttl(&mut answer, 60);
+        assert_eq!(ttls(&answer), vec![60, 30, 60]);
+    }
+
+    #[test]
+    fn cap_ttl_survives_truncated_responses() {
+        let query = build_query(1, "example.com", TYPE_A).unwrap();
+        let answer = response(&query, &[(TYPE_A, 3600, &[93, 184, 216, 34])]);
+        let ttl_at = query.len() + 6;
+
+        for len in 0..answer.len() {
+            let mut truncated = answer[..len].to_vec();
+            cap_ttl(&mut truncated, 60);
+            // Nothing but a complete TTL field is ever rewritten
+            if len < ttl_at + 6 {
+                assert_eq!(truncated, answer[..len], "length {}", len);
+            } else {
+                assert_eq!(&truncated[ttl_at..ttl_at + 4], &60u32.to_be_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn names_that_cannot_be_encoded() {
+        let long_label = "a".repeat(64);
+        assert!(encode_name(&"a".repeat(63)).is_some());
+        assert!(encode_name(&long_label).is_none());
+        assert!(encode_name("www..example.com").is_none());
+        assert!(encode_name("").is_none());
+
+        // 4 labels of 63 bytes encode to 257 bytes, over the limit of 255
+        let long_name = vec!["a".repeat(63); 4].join(".");
+        assert!(encode_name(&long_name).is_none());
+        let longest = [
+            "a".repeat(63),
+            "a".repeat(63),
+            "a".repeat(63),
+            "a".repeat(61),
+        ]
+        .join(".");
+        assert_eq!(encode_name(&longest).unwrap().len(), 255);
+
+        assert!(build_query(1, &long_label, TYPE_A).is_none());
+        let query = build_query(1, "www.google.com", TYPE_A).unwrap();
+        let question = parse_question(&query).unwrap();
+        let upstream = response(&query, &[(TYPE_A, 60, &[216, 239, 38, 120])]);
+        assert!(cname_response(&query, &question, &long_label, &upstream).is_none());
+    }
+}
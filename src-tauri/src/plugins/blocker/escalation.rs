@@ -0,0 +1,286 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use super::lock::LockManager;
+use super::models::{EscalationProfile, EscalationStatus, LockScope, PanicEscalationSettings};
+use super::{catalog, clock, settings};
+
+const KEY_SETTINGS: &str = "panicEscalation";
+const KEY_ACTIVE: &str = "activeEscalation";
+/// The blocklist the frontend last handed to the Android VPN.
+#[cfg(mobile)]
+const KEY_VPN_DOMAINS: &str = "vpnDomains";
+
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Persisted while an escalation runs.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActiveEscalation {
+    profile: EscalationProfile,
+    started_at: DateTime<Utc>,
+    until: DateTime<Utc>,
+    /// The user's own blocklist, restored when an all-categories escalation
+    /// ends. `None` if only lockdown was escalated.
+    previous_domains: Option<Vec<String>>,
+    /// The catalog domains blocked on top of it, taken when the escalation
+    /// started so a catalog sync can't shrink them.
+    #[serde(default)]
+    category_domains: Vec<String>,
+}
+
+pub fn load_settings<R: Runtime>(app: &AppHandle<R>) -> PanicEscalationSettings {
+    settings::load(app, KEY_SETTINGS).unwrap_or_default()
+}
+
+pub fn save_settings<R: Runtime>(
+    app: &AppHandle<R>,
+    config: &PanicEscalationSettings,
+) -> Result<(), String> {
+    if config.hours == 0 {
+        return Err("Escalation must last at least one hour".into());
+    }
+    settings::save(app, KEY_SETTINGS, config)
+}
+
+/// Apply the configured stricter profile after the panic button was pressed.
+/// Returns `None` if escalation is turned off. Pressing again while an
+/// escalation runs keeps it going for the configured hours from that press.
+pub fn escalate<R: Runtime>(app: &AppHandle<R>) -> Result<Option<EscalationStatus>, String> {
+    let config = load_settings(app);
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let now = clock::now();
+    let active = load_active(app);
+    let locks = app.state::<LockManager<R>>();
+
+    let mut category_domains = active
+        .as_ref()
+        .map(|a| a.category_domains.clone())
+        .unwrap_or_default();
+    let (until, previous_domains) = match config.profile {
+        EscalationProfile::AllCategories => {
+            let previous = active
+                .as_ref()
+                .and_then(|a| a.previous_domains.clone())
+                .unwrap_or_else(|| current_blocklist(app));
+            category_domains.extend(catalog::all_domains(app));
+            category_domains = catalog::normalize(category_domains);
+            apply_domains(app, with_domains(previous.clone(), &category_domains))?;
+            let until = locks.extend_until(
+                LockScope::Blocklist,
+                now + chrono::Duration::hours(config.hours as i64),
+            )?;
+            (until, Some(previous))
+        }
+        EscalationProfile::Lockdown => (
+            start_lockdown(
+                app,
+                &config,
+                now + chrono::Duration::hours(config.hours as i64),
+            )?,
+            active.as_ref().and_then(|a| a.previous_domains.clone()),
+        ),
+    };
+
+    let escalation = ActiveEscalation {
+        profile: config.profile,
+        started_at: active.as_ref().map_or(now, |a| a.started_at),
+        until: active.as_ref().map_or(until, |a| a.until.max(until)),
+        previous_domains,
+        category_domains,
+    };
+    settings::save(app, KEY_ACTIVE, &escalation)?;
+
+    log::info!(
+        "Panic escalation to {:?} active until {}",
+        escalation.profile,
+        escalation.until.to_rfc3339()
+    );
+    Ok(Some(to_status(&escalation)))
+}
+
+pub fn status<R: Runtime>(app: &AppHandle<R>) -> Option<EscalationStatus> {
+    load_active(app)
+        .filter(|a| a.until > clock::now())
+        .map(|a| to_status(&a))
+}
+
+/// The domains to enforce when the user applies `domains` as their blocklist.
+/// During an all-categories escalation the new list becomes the one restored
+/// afterwards, and the catalog stays blocked on top of it.
+pub fn blocklist_for<R: Runtime>(
+    app: &AppHandle<R>,
+    domains: Vec<String>,
+) -> Result<Vec<String>, String> {
+    #[cfg(mobile)]
+    settings::save(app, KEY_VPN_DOMAINS, &domains)?;
+
+    let domains = super::reset_lock::blocklist_for(app, domains)?;
+    match load_active(app).filter(|a| a.until > clock::now() && a.previous_domains.is_some()) {
+        Some(mut active) => {
+            active.previous_domains = Some(domains.clone());
+            settings::save(app, KEY_ACTIVE, &active)?;
+            Ok(with_domains(domains, &active.category_domains))
+        }
+        None => Ok(domains),
+    }
+}
+
+/// Refuse to tear the blocklist down while an escalation holds it.
+pub fn ensure_not_escalated<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    match status(app) {
+        Some(status) => Err(format!("Panic escalation is active until {}", status.until)),
+        None => Ok(()),
+    }
+}
+
+/// End escalations whose time ran out, now and while the app is running.
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let Some(active) = load_active(&app).filter(|a| a.until <= clock::now()) else {
+                continue;
+            };
+
+            match end(&app, &active) {
+                Ok(()) => {
+                    log::info!("Panic escalation ended");
+                    let _ = app.emit("panic-escalation-ended", to_status(&active));
+                }
+                Err(e) => log::error!("Failed to end panic escalation: {}", e),
+            }
+        }
+    });
+}
+
+/// Put the user's own blocklist back. Lockdown ends on its own lock.
+fn end<R: Runtime>(app: &AppHandle<R>, active: &ActiveEscalation) -> Result<(), String> {
+    if let Some(previous) = &active.previous_domains {
+        let previous = super::reset_lock::with_locked_categories(app, previous.clone());
+        if previous.is_empty() {
+            clear_domains(app)?;
+        } else {
+            apply_domains(app, previous)?;
+        }
+    }
+    settings::save(app, KEY_ACTIVE, &None::<ActiveEscalation>)
+}
+
+/// Enter lockdown with the lockdown lock running until at least `until`, so
+/// pressing the panic button again doesn't stack another period on top.
+fn start_lockdown<R: Runtime>(
+    app: &AppHandle<R>,
+    config: &PanicEscalationSettings,
+    until: DateTime<Utc>,
+) -> Result<DateTime<Utc>, String> {
+    #[cfg(desktop)]
+    {
+        super::lockdown::activate(app, config.lockdown_allowlist.clone())?;
+        app.state::<LockManager<R>>()
+            .extend_until(LockScope::Lockdown, until)
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = (app, config, until);
+        Err("Lockdown mode is only available on desktop".into())
+    }
+}
+
+fn with_domains(mut domains: Vec<String>, extra: &[String]) -> Vec<String> {
+    domains.extend(extra.iter().cloned());
+    catalog::normalize(domains)
+}
+
+/// The blocklist the user applied themselves, without the catalog an
+/// all-categories escalation adds on top.
+pub(super) fn own_blocklist<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    load_active(app)
+        .filter(|a| a.until > clock::now())
+        .and_then(|a| a.previous_domains)
+        .unwrap_or_else(|| current_blocklist(app))
+}
+
+/// Catalog categories the user's own blocklist covers completely.
+pub fn enabled_categories<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    let own = catalog::normalize(own_blocklist(app));
+    let mut enabled: Vec<String> = catalog::load(app)
+        .into_iter()
+        .filter(|(_, domains)| {
+            !domains.is_empty() && domains.iter().all(|d| own.binary_search(d).is_ok())
+        })
+        .map(|(id, _)| id)
+        .collect();
+    enabled.sort();
+    enabled
+}
+
+fn current_blocklist<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    #[cfg(desktop)]
+    {
+        let _ = app;
+        super::desktop::get_blocked_domains().unwrap_or_default()
+    }
+    #[cfg(mobile)]
+    {
+        settings::load(app, KEY_VPN_DOMAINS).unwrap_or_default()
+    }
+}
+
+pub(super) fn apply_domains<R: Runtime>(
+    app: &AppHandle<R>,
+    domains: Vec<String>,
+) -> Result<(), String> {
+    if domains.is_empty() {
+        return Err("No categories have been synced to escalate to".into());
+    }
+
+    #[cfg(desktop)]
+    {
+        let _ = app;
+        super::desktop::add_domains(&domains)?;
+        super::desktop::flush_dns_cache();
+        super::desktop::start_watcher(domains);
+        Ok(())
+    }
+    #[cfg(mobile)]
+    {
+        app.state::<super::BlockerMobile<R>>().start_vpn(domains)
+    }
+}
+
+pub(super) fn clear_domains<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    #[cfg(desktop)]
+    {
+        let _ = app;
+        super::desktop::remove_domains()?;
+        super::desktop::stop_watcher();
+        super::desktop::flush_dns_cache();
+        Ok(())
+    }
+    #[cfg(mobile)]
+    {
+        app.state::<super::BlockerMobile<R>>().stop_vpn()
+    }
+}
+
+fn load_active<R: Runtime>(app: &AppHandle<R>) -> Option<ActiveEscalation> {
+    settings::load(app, KEY_ACTIVE)
+}
+
+fn to_status(active: &ActiveEscalation) -> EscalationStatus {
+    EscalationStatus {
+        profile: active.profile,
+        started_at: active.started_at.to_rfc3339(),
+        until: active.until.to_rfc3339(),
+    }
+}
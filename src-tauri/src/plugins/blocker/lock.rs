@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use tauri::{AppHandle, Emitter, Runtime};
+
+use super::models::{LockScope, LockStatus};
+use super::{clock, settings};
+
+const KEY_LOCKS: &str = "locks";
+
+/// Owns the expiry of every blocker lock. Locks can be extended but never
+/// shortened, and a scope is locked until its expiry passes.
+pub struct LockManager<R: Runtime> {
+    app: AppHandle<R>,
+    locks: Mutex<HashMap<LockScope, DateTime<Utc>>>,
+}
+
+impl<R: Runtime> LockManager<R> {
+    pub fn load(app: &AppHandle<R>) -> Self {
+        let locks = settings::load(app, KEY_LOCKS).unwrap_or_default();
+        Self {
+            app: app.clone(),
+            locks: Mutex::new(locks),
+        }
+    }
+
+    /// Extend a lock by `hours`, counting from the current expiry if the lock
+    /// is still running, or from now if it isn't. Returns the new expiry.
+    pub fn extend(&self, scope: LockScope, hours: u64) -> Result<DateTime<Utc>, String> {
+        let mut locks = self.locks.lock().unwrap();
+        let now = clock::now();
+        let base = locks
+            .get(&scope)
+            .copied()
+            .filter(|e| *e > now)
+            .unwrap_or(now);
+        let expiry = base + Duration::hours(hours as i64);
+
+        locks.insert(scope, expiry);
+        settings::save(&self.app, KEY_LOCKS, &*locks)?;
+
+        log::info!(
+            "{:?} lock extended by {} hours, new expiry: {}",
+            scope,
+            hours,
+            expiry.to_rfc3339()
+        );
+        drop(locks);
+        self.emit_applied(scope, expiry);
+        Ok(expiry)
+    }
+
+    /// Make sure a lock runs until at least `until`, in credited time. An
+    /// expiry that is already later is left alone. Returns the resulting
+    /// expiry.
+    pub fn extend_until(
+        &self,
+        scope: LockScope,
+        until: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, String> {
+        let mut locks = self.locks.lock().unwrap();
+        if let Some(current) = locks.get(&scope).copied().filter(|e| *e >= until) {
+            return Ok(current);
+        }
+
+        locks.insert(scope, until);
+        settings::save(&self.app, KEY_LOCKS, &*locks)?;
+
+        log::info!("{:?} lock extended until {}", scope, until.to_rfc3339());
+        drop(locks);
+        self.emit_applied(scope, until);
+        Ok(until)
+    }
+
+    /// Tell the frontend a lock now runs until `expiry`.
+    fn emit_applied(&self, scope: LockScope, expiry: DateTime<Utc>) {
+        let _ = self.app.emit(
+            "lock-applied",
+            LockStatus {
+                scope,
+                is_locked: true,
+                expires_at: Some(expiry.to_rfc3339()),
+                clock_tampered: clock::tampered(),
+            },
+        );
+    }
+
+    /// The expiry of a running lock, or `None` if the scope isn't locked.
+    pub fn expires_at(&self, scope: LockScope) -> Option<DateTime<Utc>> {
+        let locks = self.locks.lock().unwrap();
+        locks.get(&scope).copied().filter(|e| *e > clock::now())
+    }
+
+    pub fn is_locked(&self, scope: LockScope) -> bool {
+        self.expires_at(scope).is_some()
+    }
+
+    /// Refuse to loosen `scope` while its lock runs.
+    pub fn ensure_unlocked(&self, scope: LockScope) -> Result<(), String> {
+        match self.expires_at(scope) {
+            Some(expiry) => Err(format!("Blocking is locked until {}", expiry.to_rfc3339())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn status(&self) -> Vec<LockStatus> {
+        let clock_tampered = clock::tampered();
+        [LockScope::Blocklist, LockScope::Lockdown]
+            .into_iter()
+            .map(|scope| {
+                let expires_at = self.expires_at(scope);
+                LockStatus {
+                    scope,
+                    is_locked: expires_at.is_some(),
+                    expires_at: expires_at.map(|e| e.to_rfc3339()),
+                    clock_tampered,
+                }
+            })
+            .collect()
+    }
+}
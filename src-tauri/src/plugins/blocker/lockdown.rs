@@ -0,0 +1,235 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use super::lock::LockManager;
+use super::models::{DeniedQuery, LockScope, LockdownStatus};
+use super::resolver::matches_domain;
+use super::{routing, settings};
+
+const KEY_LOCKDOWN: &str = "lockdown";
+
+/// Name lockdown holds the local resolver under.
+const ROUTING_USER: &str = "lockdown";
+
+/// How many denied queries `get_lockdown_status` reports.
+const RECENT_DENIED_LIMIT: usize = 50;
+
+/// Minimum gap between two `lockdown-denied` events for the same domain;
+/// browsers retry a failed name many times a second.
+const DENIED_EVENT_INTERVAL: Duration = Duration::from_secs(60);
+
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Persisted while lockdown is active.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockdownConfig {
+    allowlist: Vec<String>,
+}
+
+/// Denied-query bookkeeping, fed by the resolver's deny hook.
+#[derive(Default)]
+pub struct LockdownLog {
+    inner: Mutex<DeniedLog>,
+}
+
+#[derive(Default)]
+struct DeniedLog {
+    count: u64,
+    recent: VecDeque<DeniedQuery>,
+    last_emitted: HashMap<String, Instant>,
+}
+
+/// Enter lockdown, or tighten one that is already running, and extend the
+/// lockdown lock by `hours`. While the lock runs, the allowlist can only
+/// shrink.
+pub fn start<R: Runtime>(
+    app: &AppHandle<R>,
+    allowlist: Vec<String>,
+    hours: u64,
+) -> Result<LockdownStatus, String> {
+    activate(app, allowlist)?;
+    app.state::<LockManager<R>>()
+        .extend(LockScope::Lockdown, hours)?;
+    Ok(status(app))
+}
+
+/// Route DNS through the resolver with `allowlist` as the only names that
+/// resolve, leaving the lockdown lock to the caller.
+pub(super) fn activate<R: Runtime>(
+    app: &AppHandle<R>,
+    allowlist: Vec<String>,
+) -> Result<(), String> {
+    let allowlist = normalize(allowlist);
+    if allowlist.is_empty() {
+        return Err("Lockdown needs at least one allowed domain".into());
+    }
+
+    let locks = app.state::<LockManager<R>>();
+    let previous = load_config(app);
+    if let Some(current) = &previous {
+        if locks.is_locked(LockScope::Lockdown)
+            && !allowlist
+                .iter()
+                .all(|d| current.allowlist.iter().any(|c| matches_domain(d, c)))
+        {
+            return Err("Lockdown is locked; the allowlist can only be narrowed".into());
+        }
+    }
+
+    settings::save(
+        app,
+        KEY_LOCKDOWN,
+        &Some(LockdownConfig {
+            allowlist: allowlist.clone(),
+        }),
+    )?;
+    if let Err(e) = routing::acquire(app, ROUTING_USER) {
+        settings::save(app, KEY_LOCKDOWN, &previous)?;
+        routing::refresh_policy(app);
+        return Err(e);
+    }
+    super::desktop::flush_dns_cache();
+
+    log::info!("Lockdown active with {} allowed domains", allowlist.len());
+    Ok(())
+}
+
+/// Leave lockdown. Refused while the lockdown lock is still running.
+pub fn stop<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let locks = app.state::<LockManager<R>>();
+    if let Some(expiry) = locks.expires_at(LockScope::Lockdown) {
+        return Err(format!("Lockdown is locked until {}", expiry.to_rfc3339()));
+    }
+    deactivate(app)
+}
+
+pub fn status<R: Runtime>(app: &AppHandle<R>) -> LockdownStatus {
+    let config = load_config(app);
+    let locks = app.state::<LockManager<R>>();
+    let log = app.state::<LockdownLog>();
+    let log = log.inner.lock().unwrap();
+
+    LockdownStatus {
+        active: config.is_some() && routing::is_routing(app, ROUTING_USER),
+        allowlist: config.map(|c| c.allowlist).unwrap_or_default(),
+        expires_at: locks
+            .expires_at(LockScope::Lockdown)
+            .map(|e| e.to_rfc3339()),
+        denied_count: log.count,
+        recent_denied: log.recent.iter().cloned().collect(),
+    }
+}
+
+/// The allowlist the resolver enforces, or `None` outside lockdown.
+pub fn allowlist<R: Runtime>(app: &AppHandle<R>) -> Option<Vec<String>> {
+    load_config(app).map(|c| c.allowlist)
+}
+
+/// Pick up a lockdown that was active when the app last exited, and end
+/// lockdowns whose lock runs out while the app is running.
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    if load_config(app).is_some() {
+        let locked = app.state::<LockManager<R>>().is_locked(LockScope::Lockdown);
+        let result = if locked {
+            routing::acquire(app, ROUTING_USER)
+        } else {
+            deactivate(app)
+        };
+        if let Err(e) = result {
+            log::error!("Failed to restore lockdown: {}", e);
+        }
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let locked = app.state::<LockManager<R>>().is_locked(LockScope::Lockdown);
+            if locked || load_config(&app).is_none() {
+                continue;
+            }
+
+            match deactivate(&app) {
+                Ok(()) => {
+                    log::info!("Lockdown lock expired, lockdown ended");
+                    let _ = app.emit("lockdown-ended", ());
+                }
+                Err(e) => log::error!("Failed to end expired lockdown: {}", e),
+            }
+        }
+    });
+}
+
+fn deactivate<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let config = load_config(app);
+    settings::save(app, KEY_LOCKDOWN, &None::<LockdownConfig>)?;
+    if let Err(e) = routing::release(app, ROUTING_USER) {
+        // Keep the config so the expiry check tries again
+        settings::save(app, KEY_LOCKDOWN, &config)?;
+        routing::refresh_policy(app);
+        return Err(e);
+    }
+    super::desktop::flush_dns_cache();
+
+    log::info!("Lockdown ended");
+    Ok(())
+}
+
+/// Deny hook of the local resolver.
+pub fn record_denied<R: Runtime>(app: &AppHandle<R>, domain: &str) {
+    let denied = DeniedQuery {
+        domain: domain.to_string(),
+        at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let log = app.state::<LockdownLog>();
+    let mut log = log.inner.lock().unwrap();
+    log.count += 1;
+    if log.recent.len() == RECENT_DENIED_LIMIT {
+        log.recent.pop_front();
+    }
+    log.recent.push_back(denied.clone());
+
+    let now = Instant::now();
+    let due = log.last_emitted.get(domain).map_or(true, |last| {
+        now.duration_since(*last) >= DENIED_EVENT_INTERVAL
+    });
+    if due {
+        log.last_emitted.insert(domain.to_string(), now);
+        drop(log);
+        let _ = app.emit("lockdown-denied", denied);
+    }
+}
+
+fn load_config<R: Runtime>(app: &AppHandle<R>) -> Option<LockdownConfig> {
+    settings::load(app, KEY_LOCKDOWN)
+}
+
+/// Lowercase, trim and dedupe, dropping entries already covered by a parent
+/// domain in the list.
+fn normalize(allowlist: Vec<String>) -> Vec<String> {
+    let mut domains: Vec<String> = allowlist
+        .iter()
+        .map(|d| d.trim().trim_end_matches('.').to_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    domains.sort();
+    domains.dedup();
+
+    domains
+        .iter()
+        .filter(|d| {
+            !domains
+                .iter()
+                .any(|parent| parent != *d && matches_domain(d, parent))
+        })
+        .cloned()
+        .collect()
+}
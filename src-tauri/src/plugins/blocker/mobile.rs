@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::de::DeserializeOwned;
 use tauri::{plugin::PluginHandle, Runtime};
 
@@ -59,6 +61,10 @@ impl<R: Runtime> BlockerMobile<R> {
         self.call_void("openAccessibilitySettings", serde_json::json!({}))
     }
 
+    pub fn set_safe_search(&self, rewrites: HashMap<String, String>) -> Result<(), String> {
+        self.call_void("setSafeSearch", serde_json::json!({ "rewrites": rewrites }))
+    }
+
     pub fn save_lock_expiry(&self, expiry: String) -> Result<(), String> {
         self.call_void("saveLockExpiry", serde_json::json!({ "expiry": expiry }))
     }
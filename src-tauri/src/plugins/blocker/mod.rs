@@ -1,21 +1,35 @@
 #[cfg(desktop)]
+mod budget;
+#[cfg(desktop)]
 mod desktop;
+#[cfg(desktop)]
+mod dns;
+#[cfg(desktop)]
+mod lockdown;
 #[cfg(mobile)]
 mod mobile;
+#[cfg(desktop)]
+mod resolver;
+#[cfg(desktop)]
+mod routing;
+mod catalog;
+pub mod clock;
 mod commands;
+pub mod escalation;
+pub mod lock;
 pub mod models;
+pub mod reset_lock;
+mod safe_search;
+mod settings;
 
 #[cfg(mobile)]
 pub use mobile::BlockerMobile;
 
 use tauri::{
     plugin::{Builder, TauriPlugin},
-    Runtime,
+    Manager, Runtime,
 };
 
-#[cfg(mobile)]
-use tauri::Manager;
-
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
     Builder::new("tamashii-blocker")
         .invoke_handler(tauri::generate_handler![
@@ -25,6 +39,24 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
             commands::get_blocker_status,
             commands::check_admin,
             commands::extend_lock,
+            commands::get_lock_status,
+            commands::get_clock_status,
+            commands::set_safe_search,
+            commands::get_safe_search_endpoints,
+            commands::update_safe_search_endpoints,
+            commands::start_lockdown,
+            commands::stop_lockdown,
+            commands::get_lockdown_status,
+            commands::get_budget_settings,
+            commands::set_budget_settings,
+            commands::get_budget_status,
+            commands::sync_block_catalog,
+            commands::get_panic_escalation_settings,
+            commands::set_panic_escalation_settings,
+            commands::get_escalation_status,
+            commands::get_reset_lock_settings,
+            commands::set_reset_lock_settings,
+            commands::get_reset_lock_status,
             // Mobile commands (Android)
             commands::start_vpn_blocker,
             commands::stop_vpn_blocker,
@@ -38,11 +70,28 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
             commands::save_lock_expiry_native,
         ])
         .setup(|app, api| {
+            clock::restore(app);
+            app.manage(lock::LockManager::load(app));
+
+            #[cfg(desktop)]
+            {
+                app.manage(resolver::Resolver::new());
+                app.manage(lockdown::LockdownLog::default());
+                app.manage(budget::BudgetMeter::default());
+                routing::restore(app);
+                lockdown::restore(app);
+                budget::restore(app);
+                safe_search::restore(app);
+            }
+
             #[cfg(target_os = "android")]
             {
                 let handle = api.register_android_plugin("com.tamashii.app.blocker", "BlockerPlugin")?;
                 app.manage(BlockerMobile::new(handle));
             }
+
+            escalation::restore(app);
+            reset_lock::restore(app);
             let _ = (app, api);
             Ok(())
         })
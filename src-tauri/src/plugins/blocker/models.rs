@@ -6,6 +6,7 @@ pub struct BlockerStatus {
     pub is_active: bool,
     pub is_admin: bool,
     pub blocked_domains: Vec<String>,
+    pub safe_search: SafeSearchStatus,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,3 +24,211 @@ pub struct InstalledApp {
     pub app_name: String,
     pub icon_base64: String,
 }
+
+/// Outcome of applying or removing hosts entries.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyResult {
+    pub domains_applied: usize,
+    pub dns_flush: DnsFlushResult,
+}
+
+/// Outcome of flushing the OS resolver cache after a hosts change.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsFlushResult {
+    pub flushed: bool,
+    /// The command that was run, e.g. `ipconfig /flushdns`.
+    pub method: Option<String>,
+    pub error: Option<String>,
+}
+
+/// One row of the SafeSearch table: the domains of a search engine and the
+/// safe-search or restricted-mode host they are pinned to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeSearchEndpoint {
+    pub engine: String,
+    /// Enforcement host, e.g. `forcesafesearch.google.com`.
+    pub target: String,
+    pub domains: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeSearchStatus {
+    pub enabled: bool,
+    /// True when the backend is actually pinning at least one engine.
+    pub enforced: bool,
+    pub engines: Vec<String>,
+}
+
+/// What a lock protects. Each scope has its own expiry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LockScope {
+    /// The category and custom-domain blocklist.
+    Blocklist,
+    /// Allowlist-only lockdown mode.
+    Lockdown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub scope: LockScope,
+    pub is_locked: bool,
+    pub expires_at: Option<String>,
+    /// The system clock was moved; expiry is measured in credited time.
+    pub clock_tampered: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClockJumpKind {
+    Forward,
+    Backward,
+}
+
+/// A change of the system clock that uptime doesn't account for. Also the
+/// `clock-jump-detected` event payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockJump {
+    /// Credited time the jump was noticed.
+    pub detected_at: String,
+    pub kind: ClockJumpKind,
+    pub seconds: i64,
+    /// Noticed after a reboot, where only backward jumps can be detected.
+    pub across_restart: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockStatus {
+    /// The wall clock is off from the credited time beyond normal drift.
+    pub tampered: bool,
+    /// How far the wall clock is ahead of the credited time.
+    pub offset_seconds: i64,
+    /// Recent jumps, oldest first.
+    pub jumps: Vec<ClockJump>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockdownStatus {
+    pub active: bool,
+    pub allowlist: Vec<String>,
+    pub expires_at: Option<String>,
+    /// Queries denied since the app started.
+    pub denied_count: u64,
+    pub recent_denied: Vec<DeniedQuery>,
+}
+
+/// A lookup the lockdown resolver refused. Also the `lockdown-denied` event payload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeniedQuery {
+    pub domain: String,
+    pub at: String,
+}
+
+/// The stricter profile the panic button switches to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EscalationProfile {
+    /// Block every category in the synced catalog on top of the current list.
+    AllCategories,
+    /// Enter lockdown with the escalation allowlist. Desktop only.
+    Lockdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicEscalationSettings {
+    pub enabled: bool,
+    pub hours: u64,
+    pub profile: EscalationProfile,
+    /// Domains kept reachable when the profile is lockdown.
+    pub lockdown_allowlist: Vec<String>,
+}
+
+impl Default for PanicEscalationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hours: 2,
+            profile: EscalationProfile::AllCategories,
+            lockdown_allowlist: Vec::new(),
+        }
+    }
+}
+
+/// A running panic escalation. Also the `panic-escalation-ended` event payload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EscalationStatus {
+    pub profile: EscalationProfile,
+    pub started_at: String,
+    pub until: String,
+}
+
+/// Blocks chosen categories for a while after the streak is reset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetLockSettings {
+    pub enabled: bool,
+    /// Catalog categories to block.
+    pub categories: Vec<String>,
+    pub hours: u64,
+}
+
+impl Default for ResetLockSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            categories: Vec::new(),
+            hours: 24,
+        }
+    }
+}
+
+/// A running reset lock. Also the `reset-lock-ended` event payload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetLockStatus {
+    pub categories: Vec<String>,
+    pub started_at: String,
+    pub until: String,
+}
+
+/// Daily allowance for one category.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryBudget {
+    pub category: String,
+    pub minutes_per_day: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetSettings {
+    /// Local hour (0–23) at which used time resets.
+    pub day_starts_at: u32,
+    pub budgets: Vec<CategoryBudget>,
+}
+
+/// Usage of one budget today. Also the payload of the `budget-updated`
+/// (as a list) and `budget-exhausted` events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub category: String,
+    pub minutes_per_day: u32,
+    pub used_seconds: u64,
+    pub remaining_seconds: u64,
+    pub exhausted: bool,
+    /// True while lookups for the category keep arriving.
+    pub in_session: bool,
+    pub resets_at: String,
+}
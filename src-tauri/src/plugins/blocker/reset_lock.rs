@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use super::lock::LockManager;
+use super::models::{LockScope, ResetLockSettings, ResetLockStatus};
+use super::{catalog, clock, escalation, settings};
+
+const KEY_SETTINGS: &str = "resetLock";
+const KEY_ACTIVE: &str = "activeResetLock";
+
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Persisted while a protective lock after a reset runs.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActiveResetLock {
+    categories: Vec<String>,
+    /// The domains of `categories`, taken when the lock started so a catalog
+    /// sync can't shrink them.
+    #[serde(default)]
+    domains: Vec<String>,
+    started_at: DateTime<Utc>,
+    until: DateTime<Utc>,
+    /// The user's own blocklist, restored when the lock ends.
+    previous_domains: Vec<String>,
+}
+
+pub fn load_settings<R: Runtime>(app: &AppHandle<R>) -> ResetLockSettings {
+    settings::load(app, KEY_SETTINGS).unwrap_or_default()
+}
+
+/// Change the rule. Refused while any lock runs, so it can't be loosened
+/// from inside the lock it set.
+pub fn save_settings<R: Runtime>(
+    app: &AppHandle<R>,
+    mut config: ResetLockSettings,
+) -> Result<(), String> {
+    let locks = app.state::<LockManager<R>>();
+    if locks.is_locked(LockScope::Blocklist) || locks.is_locked(LockScope::Lockdown) {
+        return Err("The reset lock can only be changed while no lock is active".into());
+    }
+    if config.hours == 0 {
+        return Err("The reset lock must last at least one hour".into());
+    }
+
+    let known = catalog::load(app);
+    config.categories = config
+        .categories
+        .iter()
+        .map(|c| c.trim().to_string())
+        .collect();
+    config.categories.sort();
+    config.categories.dedup();
+    if let Some(unknown) = config.categories.iter().find(|c| !known.contains_key(*c)) {
+        return Err(format!("Unknown category: {}", unknown));
+    }
+    if config.enabled && config.categories.is_empty() {
+        return Err("Choose at least one category to lock after a reset".into());
+    }
+    settings::save(app, KEY_SETTINGS, &config)
+}
+
+/// Block the configured categories and lock the blocklist for the
+/// configured hours. Called when the streak service records a reset.
+/// Returns `None` if the rule is turned off.
+pub fn activate<R: Runtime>(app: &AppHandle<R>) -> Result<Option<ResetLockStatus>, String> {
+    let config = load_settings(app);
+    if !config.enabled || config.categories.is_empty() {
+        return Ok(None);
+    }
+
+    let now = clock::now();
+    let active = load_active(app);
+    let until = app.state::<LockManager<R>>().extend_until(
+        LockScope::Blocklist,
+        now + chrono::Duration::hours(config.hours as i64),
+    )?;
+
+    let catalog = catalog::load(app);
+    let mut domains: Vec<String> = config
+        .categories
+        .iter()
+        .flat_map(|category| catalog.get(category).into_iter().flatten().cloned())
+        .collect();
+    let mut categories = config.categories;
+    if let Some(active) = &active {
+        categories.extend(active.categories.iter().cloned());
+        categories.sort();
+        categories.dedup();
+        domains.extend(active.domains.iter().cloned());
+    }
+    let lock = ActiveResetLock {
+        categories,
+        domains: catalog::normalize(domains),
+        started_at: active.as_ref().map_or(now, |a| a.started_at),
+        until: active.as_ref().map_or(until, |a| a.until.max(until)),
+        previous_domains: match active {
+            Some(active) => active.previous_domains,
+            None => escalation::own_blocklist(app),
+        },
+    };
+    settings::save(app, KEY_ACTIVE, &lock)?;
+
+    let domains = escalation::blocklist_for(app, lock.previous_domains.clone())?;
+    escalation::apply_domains(app, domains)?;
+
+    log::info!(
+        "Reset lock on {:?} active until {}",
+        lock.categories,
+        lock.until.to_rfc3339()
+    );
+    Ok(Some(to_status(&lock)))
+}
+
+pub fn status<R: Runtime>(app: &AppHandle<R>) -> Option<ResetLockStatus> {
+    load_active(app)
+        .filter(|a| a.until > clock::now())
+        .map(|a| to_status(&a))
+}
+
+/// While the lock runs, `domains` becomes the list restored afterwards and
+/// the locked categories stay blocked on top of it.
+pub fn blocklist_for<R: Runtime>(
+    app: &AppHandle<R>,
+    domains: Vec<String>,
+) -> Result<Vec<String>, String> {
+    match load_active(app).filter(|a| a.until > clock::now()) {
+        Some(mut active) => {
+            active.previous_domains = domains.clone();
+            settings::save(app, KEY_ACTIVE, &active)?;
+            Ok(with_categories(&active, domains))
+        }
+        None => Ok(domains),
+    }
+}
+
+/// The blocklist the user applied themselves, without the categories a
+/// reset lock or escalation adds on top.
+pub(super) fn own_blocklist<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    match load_active(app).filter(|a| a.until > clock::now()) {
+        Some(active) => active.previous_domains,
+        None => escalation::own_blocklist(app),
+    }
+}
+
+/// `domains` plus the locked categories, without changing what is restored.
+pub fn with_locked_categories<R: Runtime>(app: &AppHandle<R>, domains: Vec<String>) -> Vec<String> {
+    match load_active(app).filter(|a| a.until > clock::now()) {
+        Some(active) => with_categories(&active, domains),
+        None => domains,
+    }
+}
+
+/// Refuse to tear the blocklist down while the reset lock holds it.
+pub fn ensure_not_locked<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    match status(app) {
+        Some(status) => Err(format!("Reset lock is active until {}", status.until)),
+        None => Ok(()),
+    }
+}
+
+/// End reset locks whose time ran out, now and while the app is running.
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let Some(active) = load_active(&app).filter(|a| a.until <= clock::now()) else {
+                continue;
+            };
+
+            match end(&app, &active) {
+                Ok(()) => {
+                    log::info!("Reset lock ended");
+                    let _ = app.emit("reset-lock-ended", to_status(&active));
+                }
+                Err(e) => log::error!("Failed to end reset lock: {}", e),
+            }
+        }
+    });
+}
+
+/// Put the user's own blocklist back, keeping any running escalation.
+fn end<R: Runtime>(app: &AppHandle<R>, active: &ActiveResetLock) -> Result<(), String> {
+    settings::save(app, KEY_ACTIVE, &None::<ActiveResetLock>)?;
+    let result =
+        escalation::blocklist_for(app, active.previous_domains.clone()).and_then(|domains| {
+            if domains.is_empty() {
+                escalation::clear_domains(app)
+            } else {
+                escalation::apply_domains(app, domains)
+            }
+        });
+    if result.is_err() {
+        // Try again on the next check
+        settings::save(app, KEY_ACTIVE, active)?;
+    }
+    result
+}
+
+fn with_categories(active: &ActiveResetLock, mut domains: Vec<String>) -> Vec<String> {
+    domains.extend(active.domains.iter().cloned());
+    catalog::normalize(domains)
+}
+
+fn load_active<R: Runtime>(app: &AppHandle<R>) -> Option<ActiveResetLock> {
+    settings::load(app, KEY_ACTIVE)
+}
+
+fn to_status(active: &ActiveResetLock) -> ResetLockStatus {
+    ResetLockStatus {
+        categories: active.categories.clone(),
+        started_at: active.started_at.to_rfc3339(),
+        until: active.until.to_rfc3339(),
+    }
+}
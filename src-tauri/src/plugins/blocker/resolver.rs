@@ -0,0 +1,258 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use tauri::async_runtime::JoinHandle;
+use tokio::net::UdpSocket;
+
+use super::dns;
+
+/// Loopback addresses the resolver listens on. The system DNS settings are
+/// pointed here while the resolver is in charge.
+const LISTEN_ADDRS: [&str; 2] = ["127.0.0.1:53", "[::1]:53"];
+
+const UPSTREAM: &str = "1.1.1.1:53";
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// TTL cap on answers for metered domains, so lookups keep arriving while a
+/// site stays in use.
+const METERED_TTL: u32 = 30;
+
+/// Callbacks the resolver reports queries to.
+#[derive(Clone)]
+pub struct ResolverHooks {
+    /// Called with the name of every query the lockdown allowlist denied.
+    pub on_denied: Arc<dyn Fn(&str) + Send + Sync>,
+    /// Called with the category of every forwarded lookup of a metered domain.
+    pub on_metered: Arc<dyn Fn(&str) + Send + Sync>,
+}
+
+/// What the resolver does with the names it is asked about.
+#[derive(Debug, Default, Clone)]
+pub struct ResolverPolicy {
+    /// When set, only these domains and their subdomains resolve.
+    pub allowlist: Option<Vec<String>>,
+    /// Domain → SafeSearch enforcement host, answered with a CNAME.
+    pub safe_search: HashMap<String, String>,
+    /// Domain → budgeted category. Lookups of these domains and their
+    /// subdomains are reported to the metering hook.
+    pub metered: HashMap<String, String>,
+    /// Categories whose daily budget is used up; their lookups are refused.
+    pub exhausted: HashSet<String>,
+}
+
+enum Verdict {
+    Forward,
+    Deny,
+    BudgetSpent,
+    Rewrite(String),
+}
+
+impl ResolverPolicy {
+    /// What to do with `name`, and the budgeted category it belongs to.
+    fn verdict(&self, name: &str) -> (Verdict, Option<String>) {
+        if let Some(allowlist) = &self.allowlist {
+            if !allowlist.iter().any(|domain| matches_domain(name, domain)) {
+                return (Verdict::Deny, None);
+            }
+        }
+
+        let category = self.metered_category(name);
+        if category
+            .as_ref()
+            .is_some_and(|c| self.exhausted.contains(c))
+        {
+            return (Verdict::BudgetSpent, None);
+        }
+
+        let verdict = match self.safe_search.get(name) {
+            Some(target) => Verdict::Rewrite(target.clone()),
+            None => Verdict::Forward,
+        };
+        (verdict, category)
+    }
+
+    /// Look `name` and each of its parent domains up in the metered table.
+    fn metered_category(&self, name: &str) -> Option<String> {
+        if self.metered.is_empty() {
+            return None;
+        }
+        let mut candidate = name;
+        loop {
+            if let Some(category) = self.metered.get(candidate) {
+                return Some(category.clone());
+            }
+            candidate = candidate.split_once('.')?.1;
+        }
+    }
+}
+
+/// True if `name` is `domain` or one of its subdomains.
+pub fn matches_domain(name: &str, domain: &str) -> bool {
+    name == domain
+        || name
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Local DNS resolver that answers on loopback port 53 according to a
+/// [`ResolverPolicy`] and forwards everything else upstream.
+pub struct Resolver {
+    policy: Arc<RwLock<ResolverPolicy>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            policy: Arc::new(RwLock::new(ResolverPolicy::default())),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.tasks.lock().unwrap().is_empty()
+    }
+
+    /// Swap the policy. Takes effect for the next query, no restart needed.
+    pub fn set_policy(&self, policy: ResolverPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    /// Bind the loopback sockets and start answering. Does nothing if the
+    /// resolver is already running. Only the IPv4 socket is required; IPv6
+    /// loopback may be disabled on the host.
+    pub fn start(&self, hooks: ResolverHooks) -> Result<(), String> {
+        let mut tasks = self.tasks.lock().unwrap();
+        if !tasks.is_empty() {
+            return Ok(());
+        }
+
+        for (i, addr) in LISTEN_ADDRS.iter().enumerate() {
+            let socket = match std::net::UdpSocket::bind(addr)
+                .and_then(|s| s.set_nonblocking(true).map(|()| s))
+            {
+                Ok(socket) => socket,
+                Err(e) if i == 0 => {
+                    return Err(format!(
+                        "Failed to bind local DNS resolver on {}: {}",
+                        addr, e
+                    ));
+                }
+                Err(e) => {
+                    log::warn!("Local DNS resolver not listening on {}: {}", addr, e);
+                    continue;
+                }
+            };
+
+            let policy = self.policy.clone();
+            let hooks = hooks.clone();
+            tasks.push(tauri::async_runtime::spawn(serve(socket, policy, hooks)));
+        }
+
+        log::info!("Local DNS resolver started");
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        let mut tasks = self.tasks.lock().unwrap();
+        if tasks.is_empty() {
+            return;
+        }
+        for task in tasks.drain(..) {
+            task.abort();
+        }
+        log::info!("Local DNS resolver stopped");
+    }
+}
+
+async fn serve(
+    socket: std::net::UdpSocket,
+    policy: Arc<RwLock<ResolverPolicy>>,
+    hooks: ResolverHooks,
+) {
+    let socket = match UdpSocket::from_std(socket) {
+        Ok(socket) => Arc::new(socket),
+        Err(e) => {
+            log::error!("Local DNS resolver failed to start: {}", e);
+            return;
+        }
+    };
+
+    let mut buf = [0u8; 4096];
+    loop {
+        // Windows reports ICMP port-unreachable from an earlier send as an
+        // error on the next receive, so errors here are not fatal.
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                log::debug!("Local DNS resolver receive error: {}", e);
+                continue;
+            }
+        };
+
+        let query = buf[..len].to_vec();
+        let Some(question) = dns::parse_question(&query) else {
+            continue;
+        };
+        let (verdict, category) = policy.read().unwrap().verdict(&question.name);
+
+        let socket = socket.clone();
+        let hooks = hooks.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Some(category) = &category {
+                (hooks.on_metered)(category);
+            }
+
+            let response = match verdict {
+                Verdict::Deny => {
+                    (hooks.on_denied)(&question.name);
+                    Some(dns::nxdomain(&query, &question))
+                }
+                Verdict::BudgetSpent => Some(dns::nxdomain(&query, &question)),
+                Verdict::Forward => forward(&query).await,
+                Verdict::Rewrite(target) => {
+                    let id = u16::from_be_bytes([query[0], query[1]]);
+                    match dns::build_query(id, &target, question.qtype) {
+                        Some(target_query) => {
+                            forward(&target_query).await.and_then(|target_response| {
+                                dns::cname_response(&query, &question, &target, &target_response)
+                            })
+                        }
+                        None => None,
+                    }
+                }
+            }
+            .map(|mut response| {
+                if category.is_some() {
+                    dns::cap_ttl(&mut response, METERED_TTL);
+                }
+                response
+            })
+            .unwrap_or_else(|| dns::servfail(&query, &question));
+
+            reply(&socket, &response, peer).await;
+        });
+    }
+}
+
+async fn forward(query: &[u8]) -> Option<Vec<u8>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await.ok()?;
+    socket.connect(UPSTREAM).await.ok()?;
+    socket.send(query).await.ok()?;
+
+    let mut buf = vec![0u8; 4096];
+    let len = tokio::time::timeout(UPSTREAM_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .ok()?
+        .ok()?;
+    buf.truncate(len);
+    Some(buf)
+}
+
+async fn reply(socket: &UdpSocket, response: &[u8], peer: SocketAddr) {
+    if let Err(e) = socket.send_to(response, peer).await {
+        log::debug!("Local DNS resolver failed to reply to {}: {}", peer, e);
+    }
+}
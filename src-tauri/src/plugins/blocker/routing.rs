@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime};
+
+use super::resolver::{Resolver, ResolverHooks, ResolverPolicy};
+use super::{budget, lockdown, safe_search, settings};
+
+const KEY_ROUTING: &str = "dnsRouting";
+
+/// Persisted while system DNS points at the local resolver.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RoutingState {
+    /// System DNS configuration from before the redirect, restored when the
+    /// last user releases it.
+    saved_dns: String,
+    /// Features that need the resolver, e.g. `lockdown` and `budgets`.
+    users: Vec<String>,
+}
+
+/// Route system DNS through the local resolver on behalf of `user`. The
+/// first user starts the resolver and redirects the system DNS.
+pub fn acquire<R: Runtime>(app: &AppHandle<R>, user: &str) -> Result<(), String> {
+    start_resolver(app)?;
+    let mut state = match load(app) {
+        Some(state) => state,
+        None => match super::desktop::redirect_system_dns() {
+            Ok(saved_dns) => RoutingState {
+                saved_dns,
+                users: Vec::new(),
+            },
+            Err(e) => {
+                app.state::<Resolver>().stop();
+                return Err(e);
+            }
+        },
+    };
+
+    if !state.users.iter().any(|u| u == user) {
+        state.users.push(user.to_string());
+    }
+    settings::save(app, KEY_ROUTING, &state)?;
+    refresh_policy(app);
+    Ok(())
+}
+
+/// Drop `user`'s claim on the resolver. The last user out restores the
+/// system DNS and stops the resolver.
+pub fn release<R: Runtime>(app: &AppHandle<R>, user: &str) -> Result<(), String> {
+    let Some(mut state) = load(app) else {
+        return Ok(());
+    };
+
+    state.users.retain(|u| u != user);
+    if state.users.is_empty() {
+        super::desktop::restore_system_dns(&state.saved_dns)?;
+        app.state::<Resolver>().stop();
+        settings::save(app, KEY_ROUTING, &None::<RoutingState>)?;
+    } else {
+        settings::save(app, KEY_ROUTING, &state)?;
+    }
+    refresh_policy(app);
+    Ok(())
+}
+
+/// True while the resolver is answering on behalf of `user`.
+pub fn is_routing<R: Runtime>(app: &AppHandle<R>, user: &str) -> bool {
+    load(app).is_some_and(|s| s.users.iter().any(|u| u == user))
+        && app.state::<Resolver>().is_running()
+}
+
+/// Restart the resolver for a redirect that was active when the app last
+/// exited. Each user decides afterwards whether it still needs it.
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    if load(app).is_some() {
+        if let Err(e) = start_resolver(app) {
+            log::error!("Failed to restore local DNS resolver: {}", e);
+        }
+    }
+    refresh_policy(app);
+}
+
+/// Rebuild the resolver policy from lockdown, SafeSearch and the budgets.
+pub fn refresh_policy<R: Runtime>(app: &AppHandle<R>) {
+    let safe_search = if safe_search::is_enabled(app) {
+        safe_search::cname_rewrites(&safe_search::endpoints(app))
+    } else {
+        HashMap::new()
+    };
+    let (metered, exhausted) = budget::policy(app);
+
+    app.state::<Resolver>().set_policy(ResolverPolicy {
+        allowlist: lockdown::allowlist(app),
+        safe_search,
+        metered,
+        exhausted,
+    });
+}
+
+fn start_resolver<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let denied = app.clone();
+    let metered = app.clone();
+    app.state::<Resolver>().start(ResolverHooks {
+        on_denied: Arc::new(move |domain| lockdown::record_denied(&denied, domain)),
+        on_metered: Arc::new(move |category| budget::record_lookup(&metered, category)),
+    })
+}
+
+fn load<R: Runtime>(app: &AppHandle<R>) -> Option<RoutingState> {
+    settings::load(app, KEY_ROUTING)
+}
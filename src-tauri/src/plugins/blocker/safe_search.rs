@@ -0,0 +1,216 @@
+use tauri::{AppHandle, Runtime};
+
+use super::models::{SafeSearchEndpoint, SafeSearchStatus};
+use super::settings;
+
+#[cfg(mobile)]
+use tauri::Manager;
+
+const KEY_ENABLED: &str = "safeSearchEnabled";
+const KEY_ENDPOINTS: &str = "safeSearchEndpoints";
+
+/// Built-in endpoint table, used until the user saves their own.
+fn default_endpoints() -> Vec<SafeSearchEndpoint> {
+    fn endpoint(engine: &str, target: &str, domains: &[&str]) -> SafeSearchEndpoint {
+        SafeSearchEndpoint {
+            engine: engine.into(),
+            target: target.into(),
+            domains: domains.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    vec![
+        endpoint(
+            "google",
+            "forcesafesearch.google.com",
+            &[
+                "google.com",
+                "www.google.com",
+                "google.co.uk",
+                "www.google.co.uk",
+                "google.ca",
+                "www.google.ca",
+                "google.co.in",
+                "www.google.co.in",
+                "google.com.au",
+                "www.google.com.au",
+                "google.de",
+                "www.google.de",
+            ],
+        ),
+        endpoint("bing", "strict.bing.com", &["bing.com", "www.bing.com"]),
+        endpoint(
+            "duckduckgo",
+            "safe.duckduckgo.com",
+            &["duckduckgo.com", "www.duckduckgo.com"],
+        ),
+        endpoint(
+            "youtube",
+            "restrict.youtube.com",
+            &[
+                "www.youtube.com",
+                "m.youtube.com",
+                "youtubei.googleapis.com",
+                "youtube.googleapis.com",
+                "www.youtube-nocookie.com",
+            ],
+        ),
+    ]
+}
+
+/// The current endpoint table: the user's saved table, or the built-in one.
+pub fn endpoints<R: Runtime>(app: &AppHandle<R>) -> Vec<SafeSearchEndpoint> {
+    settings::load(app, KEY_ENDPOINTS).unwrap_or_else(default_endpoints)
+}
+
+pub fn is_enabled<R: Runtime>(app: &AppHandle<R>) -> bool {
+    settings::load(app, KEY_ENABLED).unwrap_or(false)
+}
+
+/// Replace the endpoint table and re-enforce it if SafeSearch is on.
+/// An empty table restores the built-in defaults.
+pub fn set_endpoints<R: Runtime>(
+    app: &AppHandle<R>,
+    endpoints: Vec<SafeSearchEndpoint>,
+) -> Result<(), String> {
+    let endpoints = if endpoints.is_empty() {
+        default_endpoints()
+    } else {
+        normalize(endpoints)?
+    };
+    if !is_enabled(app) {
+        return settings::save(app, KEY_ENDPOINTS, &endpoints);
+    }
+
+    let previous = self::endpoints(app);
+    settings::save(app, KEY_ENDPOINTS, &endpoints)?;
+    if let Err(e) = enforce(app) {
+        settings::save(app, KEY_ENDPOINTS, &previous)?;
+        resync(app);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Turn SafeSearch on or off. The setting only changes once the platform
+/// backends have taken it.
+pub fn set_enabled<R: Runtime>(app: &AppHandle<R>, enabled: bool) -> Result<(), String> {
+    let previous = is_enabled(app);
+    settings::save(app, KEY_ENABLED, &enabled)?;
+    if let Err(e) = enforce(app) {
+        settings::save(app, KEY_ENABLED, &previous)?;
+        resync(app);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Put the backends back in line with the saved settings after a failed
+/// change.
+fn resync<R: Runtime>(app: &AppHandle<R>) {
+    if let Err(e) = enforce(app) {
+        log::warn!("Failed to restore SafeSearch: {}", e);
+    }
+}
+
+/// Have the hosts file watcher keep SafeSearch in place if it is on.
+#[cfg(desktop)]
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    if is_enabled(app) {
+        super::desktop::watch_safe_search(endpoints(app));
+    }
+}
+
+/// Domain → enforcement host pairs for backends that rewrite DNS answers.
+pub fn cname_rewrites(
+    endpoints: &[SafeSearchEndpoint],
+) -> std::collections::HashMap<String, String> {
+    endpoints
+        .iter()
+        .flat_map(|e| e.domains.iter().map(|d| (d.clone(), e.target.clone())))
+        .collect()
+}
+
+/// Push the current SafeSearch setting to the platform backends: the hosts
+/// file and local resolver on desktop, the VPN DNS filter on Android.
+pub fn enforce<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let endpoints = if is_enabled(app) {
+        endpoints(app)
+    } else {
+        Vec::new()
+    };
+
+    #[cfg(desktop)]
+    {
+        if endpoints.is_empty() {
+            super::desktop::remove_safe_search()?;
+        } else {
+            let enforced = super::desktop::apply_safe_search(&endpoints)?;
+            log::info!("SafeSearch enforced for {:?}", enforced);
+        }
+        super::desktop::watch_safe_search(endpoints.clone());
+        super::routing::refresh_policy(app);
+        super::desktop::flush_dns_cache();
+    }
+    #[cfg(mobile)]
+    {
+        let state = app.state::<super::BlockerMobile<R>>();
+        state.set_safe_search(cname_rewrites(&endpoints))?;
+    }
+    Ok(())
+}
+
+pub fn status<R: Runtime>(app: &AppHandle<R>) -> SafeSearchStatus {
+    let enabled = is_enabled(app);
+
+    #[cfg(desktop)]
+    let engines = super::desktop::get_safe_search_engines().unwrap_or_default();
+    #[cfg(mobile)]
+    let engines = {
+        let state = app.state::<super::BlockerMobile<R>>();
+        let vpn_running = state
+            .get_vpn_status()
+            .map(|s| s.is_running)
+            .unwrap_or(false);
+        if enabled && vpn_running {
+            endpoints(app).into_iter().map(|e| e.engine).collect()
+        } else {
+            Vec::new()
+        }
+    };
+
+    SafeSearchStatus {
+        enabled,
+        enforced: enabled && !engines.is_empty(),
+        engines,
+    }
+}
+
+/// Trim and lowercase every entry, rejecting endpoints that can't be enforced.
+fn normalize(endpoints: Vec<SafeSearchEndpoint>) -> Result<Vec<SafeSearchEndpoint>, String> {
+    endpoints
+        .into_iter()
+        .map(|e| {
+            let engine = e.engine.trim().to_lowercase();
+            let target = e.target.trim().to_lowercase();
+            let domains: Vec<String> = e
+                .domains
+                .iter()
+                .map(|d| d.trim().to_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect();
+
+            if engine.is_empty() || target.is_empty() {
+                return Err("SafeSearch endpoints need an engine and a target host".into());
+            }
+            if domains.is_empty() {
+                return Err(format!("SafeSearch endpoint '{}' has no domains", engine));
+            }
+            Ok(SafeSearchEndpoint {
+                engine,
+                target,
+                domains,
+            })
+        })
+        .collect()
+}
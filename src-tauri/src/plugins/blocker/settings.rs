@@ -0,0 +1,29 @@
+use serde::{de::DeserializeOwned, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreExt;
+
+/// Store file holding the blocker's persisted settings.
+const STORE_PATH: &str = "blocker.json";
+
+/// Read a setting from the blocker store, returning `None` if it is missing
+/// or no longer deserializes into `T`.
+pub fn load<R: Runtime, T: DeserializeOwned>(app: &AppHandle<R>, key: &str) -> Option<T> {
+    let store = app.store(STORE_PATH).ok()?;
+    serde_json::from_value(store.get(key)?).ok()
+}
+
+/// Write a setting to the blocker store and flush it to disk.
+pub fn save<R: Runtime, T: Serialize>(
+    app: &AppHandle<R>,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let store = app
+        .store(STORE_PATH)
+        .map_err(|e| format!("Failed to open blocker settings: {}", e))?;
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    store.set(key, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save blocker settings: {}", e))
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Duration as ChronoDuration, Local, Utc};
+use std::time::Duration;
+use tauri::Manager;
+use tauri_plugin_notification::NotificationExt;
+
+use super::habit_scheduler::at;
+use super::{until, RECHECK_INTERVAL};
+use crate::checkin::{self, daily_log};
+use crate::db::Db;
+use crate::plugins::blocker::clock;
+use crate::streak::{self, days};
+
+/// Start the check-in scheduler.
+/// Prompts for the day's check-in at the configured evening time unless it
+/// is done, and fills in the daily log of each day once it has ended.
+pub async fn start_scheduler(app_handle: tauri::AppHandle) {
+    log::info!("Check-in scheduler started");
+
+    let mut last_check = Local::now();
+    loop {
+        let now = Local::now();
+        let sleep_duration = match check(&app_handle, last_check, now).await {
+            Ok(duration) => duration,
+            Err(e) => {
+                log::error!("Check-in check failed: {}", e);
+                RECHECK_INTERVAL
+            }
+        };
+        last_check = now;
+        tokio::time::sleep(sleep_duration).await;
+    }
+}
+
+/// Close the days that ended and send the prompt if it fell due after
+/// `since`. Returns how long to sleep until the next prompt or day boundary.
+async fn check(
+    app_handle: &tauri::AppHandle,
+    since: DateTime<Local>,
+    now: DateTime<Local>,
+) -> Result<Duration, String> {
+    let pool = app_handle.state::<Db>().0.clone();
+    if let Err(e) = daily_log::close_past_days(&pool).await {
+        log::error!("Failed to fill in the daily log: {}", e);
+    }
+
+    let streak_settings = streak::load_settings(&pool).await?;
+    let today = streak::today(&streak_settings);
+    let next_day = days::day_start(
+        today + ChronoDuration::days(1),
+        &Local,
+        streak_settings.day_starts_at,
+    );
+    let mut next_due = until(next_day, clock::now());
+
+    let settings = checkin::load_settings(&pool).await?;
+    if let Some(time) = settings.prompt_time().filter(|_| settings.enabled) {
+        let date = now.date_naive();
+        for day in [date - ChronoDuration::days(1), date] {
+            let Some(due) = at(day, time) else {
+                continue;
+            };
+            if !(since < due && due <= now) {
+                continue;
+            }
+            // A prompt caught up late asks about the day it was due on
+            let prompted_day = streak::day_of(due.with_timezone(&Utc), &streak_settings);
+            if checkin::get(&pool, prompted_day).await?.is_none() {
+                prompt(app_handle);
+            }
+        }
+        if let Some(next) = [date, date + ChronoDuration::days(1)]
+            .into_iter()
+            .filter_map(|day| at(day, time))
+            .find(|due| *due > now)
+        {
+            next_due = next_due.min(until(next, now));
+        }
+    }
+
+    Ok(next_due)
+}
+
+fn prompt(app_handle: &tauri::AppHandle) {
+    match app_handle
+        .notification()
+        .builder()
+        .title("Tamashii - Evening Check-in")
+        .body("How did today go? Take a minute to log your mood, sleep and urges.")
+        .show()
+    {
+        Ok(()) => log::info!("Check-in prompt sent"),
+        Err(e) => log::error!("Failed to send check-in prompt: {}", e),
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveTime, TimeZone};
+use std::time::Duration;
+use tauri::Manager;
+use tauri_plugin_notification::NotificationExt;
+
+use super::{until, RECHECK_INTERVAL};
+use crate::db::Db;
+use crate::streak::habits::{self, HabitKind, HabitStatus};
+
+/// Start the habit reminder scheduler.
+/// Sends each habit's notification daily at its reminder time. Do habits
+/// already checked in that day are skipped.
+pub async fn start_scheduler(app_handle: tauri::AppHandle) {
+    log::info!("Habit reminder scheduler started");
+
+    let mut last_check = Local::now();
+    loop {
+        let now = Local::now();
+        let sleep_duration = match check(&app_handle, last_check, now).await {
+            Ok(duration) => duration,
+            Err(e) => {
+                log::error!("Habit reminder check failed: {}", e);
+                RECHECK_INTERVAL
+            }
+        };
+        last_check = now;
+        tokio::time::sleep(sleep_duration).await;
+    }
+}
+
+/// Send the reminders that fell due after `since`. Returns how long to sleep
+/// until the next one.
+async fn check(
+    app_handle: &tauri::AppHandle,
+    since: DateTime<Local>,
+    now: DateTime<Local>,
+) -> Result<Duration, String> {
+    let pool = app_handle.state::<Db>().0.clone();
+    let mut next_due: Option<DateTime<Local>> = None;
+
+    for status in habits::list(&pool).await? {
+        let Some(time) = status
+            .habit
+            .reminder_time
+            .as_deref()
+            .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+        else {
+            continue;
+        };
+
+        let today = now.date_naive();
+        for day in [today - ChronoDuration::days(1), today] {
+            let Some(due) = at(day, time) else {
+                continue;
+            };
+            if since < due && due <= now {
+                remind(app_handle, &status);
+            }
+        }
+        if let Some(next) = [today, today + ChronoDuration::days(1)]
+            .into_iter()
+            .filter_map(|day| at(day, time))
+            .find(|due| *due > now)
+        {
+            next_due = Some(next_due.map_or(next, |n| n.min(next)));
+        }
+    }
+
+    Ok(next_due.map_or(RECHECK_INTERVAL, |next| until(next, now)))
+}
+
+fn remind(app_handle: &tauri::AppHandle, status: &HabitStatus) {
+    let habit = &status.habit;
+    let body = match habit.kind {
+        HabitKind::Do if status.done_today == Some(true) => return,
+        HabitKind::Do if status.current_days > 0 => format!(
+            "You're on a {}-day run. Check in once it's done today.",
+            status.current_days
+        ),
+        HabitKind::Do => "Today is a good day to start a new run.".to_string(),
+        HabitKind::Avoid if status.start_date.is_some() => {
+            format!("Day {}. Keep going.", status.current_days)
+        }
+        HabitKind::Avoid => "Ready to start a new streak?".to_string(),
+    };
+
+    match app_handle
+        .notification()
+        .builder()
+        .title(format!("Tamashii - {}", habit.name))
+        .body(&body)
+        .show()
+    {
+        Ok(()) => {
+            log::info!("Habit reminder sent for {}", habit.name);
+        }
+        Err(e) => {
+            log::error!("Failed to send habit reminder: {}", e);
+        }
+    }
+}
+
+/// `time` on `day` locally. A time in a DST gap moves to the hour after.
+pub(super) fn at(day: chrono::NaiveDate, time: NaiveTime) -> Option<DateTime<Local>> {
+    let local = day.and_time(time);
+    Local.from_local_datetime(&local).earliest().or_else(|| {
+        Local
+            .from_local_datetime(&(local + ChronoDuration::hours(1)))
+            .earliest()
+    })
+}
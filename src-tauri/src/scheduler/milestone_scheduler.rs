@@ -0,0 +1,160 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::Notify;
+
+use super::{until, RECHECK_INTERVAL};
+use crate::achievements::engine;
+use crate::achievements::events::EventKind;
+use crate::db::{self, Db};
+use crate::plugins::blocker::clock;
+use crate::streak::{self, habits, milestones};
+
+const KEY_MILESTONE_NOTIFIED: &str = "milestone_notified";
+
+/// A milestone reached while the app was closed is still announced this long
+/// afterwards.
+const LATE_LIMIT_HOURS: i64 = 24;
+
+/// Wakes the scheduler to work the schedule out again.
+static RESCHEDULE: Notify = Notify::const_new();
+
+/// The last milestone announced, so a restart doesn't repeat it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Notified {
+    streak_id: i64,
+    days: u64,
+}
+
+/// Payload of the `streak-milestone` event.
+#[derive(Debug, Clone, Serialize)]
+struct MilestoneReached {
+    days: u64,
+    at: String,
+}
+
+/// Start the milestone scheduler.
+/// Sends a notification the moment the main streak reaches each milestone,
+/// and records the freeze tokens every habit's streak earns.
+/// Runs from the backend, so it works with only the tray icon open.
+pub async fn start_scheduler(app_handle: tauri::AppHandle) {
+    log::info!("Milestone scheduler started");
+
+    loop {
+        let sleep_duration = match check(&app_handle).await {
+            Ok(duration) => duration,
+            Err(e) => {
+                log::error!("Milestone check failed: {}", e);
+                RECHECK_INTERVAL
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(sleep_duration) => {}
+            _ = RESCHEDULE.notified() => {
+                log::info!("Milestone schedule recomputed");
+            }
+        }
+    }
+}
+
+/// Work the schedule out again, e.g. after the streak was reset or a freeze
+/// moved the milestones.
+pub fn reschedule() {
+    RESCHEDULE.notify_one();
+}
+
+/// Award the freeze tokens streaks have earned and announce the latest
+/// milestone reached if it is new. Returns how long to sleep until the next
+/// one.
+async fn check(app_handle: &tauri::AppHandle) -> Result<Duration, String> {
+    let pool = app_handle.state::<Db>().0.clone();
+    if let Err(e) = habits::award_freezes(&pool).await {
+        log::error!("Failed to award freeze tokens: {}", e);
+    }
+    let habit_id = habits::default_id(&pool).await?;
+    let Some(running) = streak::current(&pool, habit_id).await? else {
+        return Ok(RECHECK_INTERVAL);
+    };
+    let schedule = milestones::schedule(&pool, habit_id).await?;
+    let now = clock::now();
+
+    let notified: Option<Notified> = db::get_setting(&pool, KEY_MILESTONE_NOTIFIED)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok());
+    let announced = notified
+        .filter(|n| n.streak_id == running.id)
+        .map_or(0, |n| n.days);
+
+    if let Some((days, at)) = schedule
+        .iter()
+        .rev()
+        .find(|(days, at)| *at <= now && *days > announced)
+    {
+        // Too late to celebrate if the app was closed for long; just catch up
+        if now - *at <= ChronoDuration::hours(LATE_LIMIT_HOURS) {
+            notify(app_handle, *days, *at);
+        }
+        let value = serde_json::to_string(&Notified {
+            streak_id: running.id,
+            days: *days,
+        })
+        .map_err(|e| format!("Failed to serialize milestone: {}", e))?;
+        db::set_setting(&pool, KEY_MILESTONE_NOTIFIED, &value).await?;
+    }
+
+    let Some((days, next)) = schedule.iter().find(|(_, at)| *at > now) else {
+        return Ok(RECHECK_INTERVAL);
+    };
+    log::info!("Next milestone: {} days at {}", days, next.to_rfc3339());
+    Ok(until(*next, now))
+}
+
+fn notify(app_handle: &tauri::AppHandle, days: u64, at: DateTime<Utc>) {
+    engine::publish(app_handle, EventKind::StreakTick);
+    let _ = app_handle.emit(
+        "streak-milestone",
+        MilestoneReached {
+            days,
+            at: at.to_rfc3339(),
+        },
+    );
+
+    match app_handle
+        .notification()
+        .builder()
+        .title(format!("Tamashii - {} {} strong", days, plural(days)))
+        .body(message(days))
+        .show()
+    {
+        Ok(()) => {
+            log::info!("Milestone notification sent for {} days", days);
+        }
+        Err(e) => {
+            log::error!("Failed to send milestone notification: {}", e);
+        }
+    }
+}
+
+fn message(days: u64) -> &'static str {
+    match days {
+        1 => "Your first full day. Every streak starts with this one.",
+        3 => "Three days in. The hardest stretch is behind you.",
+        7 => "A whole week. You're building something real.",
+        14 => "Two weeks strong. New habits are taking root.",
+        30 => "A full month. Look how far you've come.",
+        60 => "Sixty days. This is who you are now.",
+        90 => "Ninety days, a true reboot. Be proud of yourself.",
+        180 => "Half a year. Your dedication is inspiring.",
+        _ => "A whole year. Take a moment to celebrate it.",
+    }
+}
+
+fn plural(days: u64) -> &'static str {
+    if days == 1 {
+        "day"
+    } else {
+        "days"
+    }
+}
@@ -1 +1,21 @@
+pub mod checkin_scheduler;
+pub mod habit_scheduler;
+pub mod milestone_scheduler;
 pub mod quote_scheduler;
+pub mod risk_scheduler;
+
+use chrono::{DateTime, TimeZone};
+use std::time::Duration;
+
+/// Longest sleep between two looks of a scheduler at what it works from, so
+/// new data and changed settings are picked up.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Time until `at`, capped at the recheck interval and at least a second.
+fn until<Tz: TimeZone>(at: DateTime<Tz>, now: DateTime<Tz>) -> Duration {
+    (at - now)
+        .to_std()
+        .unwrap_or_default()
+        .min(RECHECK_INTERVAL)
+        .max(Duration::from_secs(1))
+}
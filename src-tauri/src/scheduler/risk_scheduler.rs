@@ -0,0 +1,139 @@
+use chrono::{DateTime, Duration as ChronoDuration, Local, Utc};
+use std::time::Duration;
+use tauri::Manager;
+use tauri_plugin_notification::NotificationExt;
+
+use super::{until, RECHECK_INTERVAL};
+use crate::db::{self, Db};
+use crate::plugins::blocker::clock;
+use crate::plugins::blocker::lock::LockManager;
+use crate::plugins::blocker::models::LockScope;
+use crate::streak::risk::{self, RiskWindow};
+
+/// Start of the window the last alert was sent for, so a restart doesn't
+/// repeat it.
+const KEY_RISK_ALERTED: &str = "risk_alerted";
+
+/// Supportive messages for the run-up to a high-risk window.
+const MESSAGES: &[&str] = &[
+    "This is usually a hard time for you. Plan something else for the next few hours.",
+    "A tough stretch is coming up. You've made it through before; you can do it again.",
+    "Heads up: urges tend to show up around now. Reach out to someone or step outside.",
+    "You know this time of day is tricky. Be kind to yourself and stay ahead of it.",
+];
+
+/// Start the risk alert scheduler.
+/// Sends a supportive notification shortly before each high-risk window of the
+/// risk profile, and optionally keeps the blocklist locked through it.
+pub async fn start_scheduler(app_handle: tauri::AppHandle) {
+    log::info!("Risk alert scheduler started");
+
+    loop {
+        let sleep_duration = match check(&app_handle).await {
+            Ok(duration) => duration,
+            Err(e) => {
+                log::error!("Risk alert check failed: {}", e);
+                RECHECK_INTERVAL
+            }
+        };
+        tokio::time::sleep(sleep_duration).await;
+    }
+}
+
+/// Send the alert for the next window if its lead time has begun. Returns how
+/// long to sleep before checking again.
+async fn check(app_handle: &tauri::AppHandle) -> Result<Duration, String> {
+    let pool = app_handle.state::<Db>().0.clone();
+    let settings = risk::load_settings(&pool).await?;
+    if !settings.enabled {
+        return Ok(RECHECK_INTERVAL);
+    }
+
+    let profile = risk::profile(&pool, &settings).await?;
+    let Some(window) = profile.windows.first() else {
+        return Ok(RECHECK_INTERVAL);
+    };
+    let start = parse(&window.next_start)?;
+    let alert_at = start - ChronoDuration::minutes(settings.lead_minutes as i64);
+    // The lock manager measures expiry in credited time
+    let now = clock::now();
+
+    if now < alert_at {
+        log::info!(
+            "Next risk alert at {}",
+            alert_at.with_timezone(&Local).to_rfc3339()
+        );
+        return Ok(until(alert_at, now));
+    }
+
+    let alerted = db::get_setting(&pool, KEY_RISK_ALERTED)
+        .await?
+        .and_then(|v| parse(&v).ok());
+    if alerted != Some(start) {
+        db::set_setting(&pool, KEY_RISK_ALERTED, &start.to_rfc3339()).await?;
+        let locked_until = if settings.extend_lock {
+            extend_lock(app_handle, window)
+        } else {
+            None
+        };
+        notify(app_handle, window, start, locked_until);
+    }
+    Ok(until(start, now))
+}
+
+/// Keep the blocklist locked until the window ends. Returns the lock expiry.
+fn extend_lock(app_handle: &tauri::AppHandle, window: &RiskWindow) -> Option<DateTime<Utc>> {
+    let end = parse(&window.next_end).ok()?;
+    match app_handle
+        .state::<LockManager<tauri::Wry>>()
+        .extend_until(LockScope::Blocklist, end)
+    {
+        Ok(expiry) => Some(expiry),
+        Err(e) => {
+            log::error!("Failed to extend lock for risk window: {}", e);
+            None
+        }
+    }
+}
+
+fn notify(
+    app_handle: &tauri::AppHandle,
+    window: &RiskWindow,
+    start: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+) {
+    // Rotate by the window so the same stretch doesn't always get the same words
+    let message = MESSAGES[(window.weekday * 24 + window.start_hour) as usize % MESSAGES.len()];
+    let mut body = format!(
+        "{} (risky from {})",
+        message,
+        start.with_timezone(&Local).format("%H:%M")
+    );
+    if let Some(until) = locked_until {
+        body.push_str(&format!(
+            " Blocking stays locked until {}.",
+            until.with_timezone(&Local).format("%H:%M")
+        ));
+    }
+
+    match app_handle
+        .notification()
+        .builder()
+        .title("Tamashii - Stay Strong")
+        .body(&body)
+        .show()
+    {
+        Ok(()) => {
+            log::info!("Risk alert sent for window starting {}", start.to_rfc3339());
+        }
+        Err(e) => {
+            log::error!("Failed to send risk alert: {}", e);
+        }
+    }
+}
+
+fn parse(at: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(at)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| format!("Could not parse time: {}", at))
+}
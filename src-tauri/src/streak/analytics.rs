@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use super::{days, grace};
+use super::{StreakRecord, StreakSettings};
+use crate::plugins::blocker::clock;
+
+/// Histogram bucket lower bounds in days, matching the streak milestones.
+const BUCKETS: &[u64] = &[0, 1, 3, 7, 14, 30, 60, 90, 180, 365];
+
+/// A trend slope smaller than this, in days per streak, counts as flat.
+const FLAT_SLOPE: f64 = 0.1;
+
+pub const DEFAULT_TREND_LENGTH: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreakAnalytics {
+    /// Number of streaks that ended in a reset.
+    pub completed_streaks: u64,
+    pub mean_days: f64,
+    pub median_days: f64,
+    pub histogram: Vec<HistogramBucket>,
+    /// Longest clean run inside each calendar month, oldest month first.
+    /// The running streak is included.
+    pub longest_by_month: Vec<MonthlyBest>,
+    pub trend: StreakTrend,
+    pub current_days: u64,
+    /// Share of completed streaks, in percent, that the current streak is
+    /// at least as long as. Null without a running streak or history.
+    pub current_percentile: Option<f64>,
+    pub last_reset_at: Option<String>,
+    pub seconds_since_last_reset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramBucket {
+    pub min_days: u64,
+    /// Inclusive; null for the open-ended last bucket.
+    pub max_days: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthlyBest {
+    /// `YYYY-MM`
+    pub month: String,
+    pub days: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreakTrend {
+    /// Lengths of the last completed streaks, oldest first.
+    pub recent_days: Vec<u64>,
+    /// Least-squares slope over `recent_days`, in days per streak.
+    pub slope: f64,
+    /// `improving`, `declining` or `flat`.
+    pub direction: &'static str,
+}
+
+pub async fn compute(pool: &SqlitePool, trend_length: usize) -> Result<StreakAnalytics, String> {
+    let settings = super::load_settings(pool).await?;
+    let habit_id = super::habits::default_id(pool).await?;
+    let mut history = super::history(pool, habit_id).await?;
+    // Oldest first from here on
+    history.reverse();
+    let current = super::current(pool, habit_id).await?;
+    let now = clock::now();
+
+    let lengths: Vec<u64> = history
+        .iter()
+        .map(|s| s.days.unwrap_or(0).max(0) as u64)
+        .collect();
+    let current_days = match &current {
+        Some(running) => super::clean_days_of(pool, running, &settings, now).await?,
+        None => 0,
+    };
+
+    let last_reset = history
+        .iter()
+        .filter_map(|s| s.end_date.as_deref())
+        .filter_map(|e| DateTime::parse_from_rfc3339(e).ok())
+        .map(|e| e.with_timezone(&Utc))
+        .max();
+
+    let mut runs = Vec::new();
+    for record in history.iter().chain(current.as_ref()) {
+        if let Some(run) = run_of(pool, record, &settings, now).await? {
+            runs.push(run);
+        }
+    }
+
+    let recent_days = lengths[lengths.len().saturating_sub(trend_length)..].to_vec();
+    let slope = slope(&recent_days);
+
+    Ok(StreakAnalytics {
+        completed_streaks: lengths.len() as u64,
+        mean_days: mean(&lengths),
+        median_days: median(&lengths),
+        histogram: histogram(&lengths),
+        longest_by_month: longest_by_month(&runs),
+        trend: StreakTrend {
+            recent_days,
+            slope,
+            direction: if slope > FLAT_SLOPE {
+                "improving"
+            } else if slope < -FLAT_SLOPE {
+                "declining"
+            } else {
+                "flat"
+            },
+        },
+        current_days,
+        current_percentile: current
+            .as_ref()
+            .and_then(|_| percentile(&lengths, current_days)),
+        last_reset_at: last_reset.map(|r| r.to_rfc3339()),
+        seconds_since_last_reset: last_reset.map(|r| (now - r).num_seconds().max(0)),
+    })
+}
+
+fn mean(lengths: &[u64]) -> f64 {
+    if lengths.is_empty() {
+        return 0.0;
+    }
+    lengths.iter().sum::<u64>() as f64 / lengths.len() as f64
+}
+
+fn median(lengths: &[u64]) -> f64 {
+    if lengths.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = lengths.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) as f64 / 2.0
+    } else {
+        sorted[mid] as f64
+    }
+}
+
+fn histogram(lengths: &[u64]) -> Vec<HistogramBucket> {
+    BUCKETS
+        .iter()
+        .enumerate()
+        .map(|(i, min)| {
+            let max = BUCKETS.get(i + 1).map(|next| next - 1);
+            HistogramBucket {
+                min_days: *min,
+                max_days: max,
+                count: lengths
+                    .iter()
+                    .filter(|d| **d >= *min && max.map_or(true, |m| **d <= m))
+                    .count() as u64,
+            }
+        })
+        .collect()
+}
+
+/// Share of `lengths`, in percent, that `days` is at least as long as.
+fn percentile(lengths: &[u64], days: u64) -> Option<f64> {
+    if lengths.is_empty() {
+        return None;
+    }
+    let shorter = lengths.iter().filter(|d| **d <= days).count();
+    Some(shorter as f64 / lengths.len() as f64 * 100.0)
+}
+
+fn slope(values: &[u64]) -> f64 {
+    let n = values.len() as f64;
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<u64>() as f64 / n;
+    let (mut num, mut den) = (0.0, 0.0);
+    for (x, y) in values.iter().enumerate() {
+        let dx = x as f64 - mean_x;
+        num += dx * (*y as f64 - mean_y);
+        den += dx * dx;
+    }
+    num / den
+}
+
+/// The local streak days a streak ran, and the days a freeze token covered.
+struct Run {
+    start: NaiveDate,
+    /// The day it ended on, or today while it runs. Not counted.
+    end: NaiveDate,
+    frozen: Vec<NaiveDate>,
+}
+
+async fn run_of(
+    pool: &SqlitePool,
+    record: &StreakRecord,
+    settings: &StreakSettings,
+    now: DateTime<Utc>,
+) -> Result<Option<Run>, String> {
+    let Some(start) = super::start_day(record, settings) else {
+        return Ok(None);
+    };
+    let end = record
+        .end_date
+        .as_deref()
+        .and_then(|e| DateTime::parse_from_rfc3339(e).ok())
+        .map_or(now, |e| e.with_timezone(&Utc));
+    Ok(Some(Run {
+        start,
+        end: days::streak_day(end, &Local, settings.day_starts_at),
+        frozen: grace::frozen_in_streak(pool, record.id).await?,
+    }))
+}
+
+/// Clip every run to the months it spans and keep the longest piece per
+/// month. Frozen days don't count, as in `clean_days_of`.
+fn longest_by_month(runs: &[Run]) -> Vec<MonthlyBest> {
+    let mut best: BTreeMap<(i32, u32), u64> = BTreeMap::new();
+
+    for run in runs {
+        let mut month_start = run.start.with_day(1).unwrap_or(run.start);
+        while month_start <= run.end {
+            let next_month = next_month(month_start);
+            let (from, to) = (run.start.max(month_start), run.end.min(next_month));
+            let frozen = run
+                .frozen
+                .iter()
+                .filter(|day| **day >= from && **day < to)
+                .count() as u64;
+            let days = ((to - from).num_days().max(0) as u64).saturating_sub(frozen);
+            let entry = best
+                .entry((month_start.year(), month_start.month()))
+                .or_default();
+            *entry = (*entry).max(days);
+            month_start = next_month;
+        }
+    }
+
+    best.into_iter()
+        .map(|((year, month), days)| MonthlyBest {
+            month: format!("{:04}-{:02}", year, month),
+            days,
+        })
+        .collect()
+}
+
+fn next_month(first: NaiveDate) -> NaiveDate {
+    let (year, month) = if first.month() == 12 {
+        (first.year() + 1, 1)
+    } else {
+        (first.year(), first.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(NaiveDate::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn run(start: NaiveDate, end: NaiveDate, frozen: &[NaiveDate]) -> Run {
+        Run {
+            start,
+            end,
+            frozen: frozen.to_vec(),
+        }
+    }
+
+    fn months(best: &[MonthlyBest]) -> Vec<(&str, u64)> {
+        best.iter().map(|b| (b.month.as_str(), b.days)).collect()
+    }
+
+    #[test]
+    fn mean_and_median_of_lengths() {
+        assert_eq!(mean(&[]), 0.0);
+        assert_eq!(median(&[]), 0.0);
+        assert_eq!(mean(&[1, 2, 6]), 3.0);
+        assert_eq!(median(&[6, 1, 2]), 2.0);
+        assert_eq!(median(&[7, 1, 2, 4]), 3.0);
+    }
+
+    #[test]
+    fn histogram_buckets_include_both_bounds() {
+        let histogram = histogram(&[0, 1, 2, 3, 6, 7, 364, 365, 1000]);
+        let counts: Vec<(u64, Option<u64>, u64)> = histogram
+            .iter()
+            .map(|b| (b.min_days, b.max_days, b.count))
+            .collect();
+        assert_eq!(counts[0], (0, Some(0), 1));
+        assert_eq!(counts[1], (1, Some(2), 2));
+        assert_eq!(counts[2], (3, Some(6), 2));
+        assert_eq!(counts[3], (7, Some(13), 1));
+        assert_eq!(counts[8], (180, Some(364), 1));
+        assert_eq!(counts[9], (365, None, 2));
+        assert_eq!(histogram.iter().map(|b| b.count).sum::<u64>(), 9);
+    }
+
+    #[test]
+    fn slope_of_recent_lengths() {
+        assert_eq!(slope(&[]), 0.0);
+        assert_eq!(slope(&[5]), 0.0);
+        assert_eq!(slope(&[4, 4, 4]), 0.0);
+        assert!((slope(&[1, 3, 5, 7]) - 2.0).abs() < 1e-9);
+        assert!((slope(&[9, 6, 3]) + 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn percentile_counts_ties_as_shorter() {
+        assert_eq!(percentile(&[], 5), None);
+        assert_eq!(percentile(&[1, 5, 10, 20], 5), Some(50.0));
+        assert_eq!(percentile(&[1, 5, 10, 20], 0), Some(0.0));
+        assert_eq!(percentile(&[1, 5, 10, 20], 30), Some(100.0));
+    }
+
+    #[test]
+    fn runs_are_split_at_month_ends() {
+        let best = longest_by_month(&[
+            run(date(2024, 1, 20), date(2024, 3, 5), &[]),
+            run(date(2024, 3, 10), date(2024, 3, 25), &[]),
+        ]);
+        assert_eq!(
+            months(&best),
+            [("2024-01", 12), ("2024-02", 29), ("2024-03", 15)]
+        );
+    }
+
+    #[test]
+    fn frozen_days_are_not_clean() {
+        let best = longest_by_month(&[run(
+            date(2024, 1, 25),
+            date(2024, 2, 10),
+            &[date(2024, 1, 28), date(2024, 2, 3), date(2024, 2, 10)],
+        )]);
+        // The end day isn't counted, so neither is its freeze
+        assert_eq!(months(&best), [("2024-01", 6), ("2024-02", 8)]);
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+
+/// Where a streak started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreakStart {
+    /// An RFC 3339 timestamp, the format the backend writes.
+    At(DateTime<Utc>),
+    /// A date-only `YYYY-MM-DD` string. The streak began when that day began.
+    Day(NaiveDate),
+}
+
+impl StreakStart {
+    pub fn parse(date_str: &str) -> Result<Self, String> {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(date_str) {
+            return Ok(Self::At(dt.with_timezone(&Utc)));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+            return Ok(Self::Day(date));
+        }
+        Err(format!("Could not parse date: {}", date_str))
+    }
+}
+
+/// The streak day `instant` falls on in `tz`. Times before `day_starts_at`
+/// o'clock still belong to the previous day.
+pub fn streak_day<Tz: TimeZone>(instant: DateTime<Utc>, tz: &Tz, day_starts_at: u32) -> NaiveDate {
+    (instant.with_timezone(tz).naive_local() - Duration::hours(day_starts_at as i64)).date()
+}
+
+/// The instant streak day `date` begins in `tz`. A boundary that falls into
+/// a DST gap moves to the first valid hour after it.
+pub fn day_start<Tz: TimeZone>(date: NaiveDate, tz: &Tz, day_starts_at: u32) -> DateTime<Utc> {
+    let boundary =
+        date.and_time(NaiveTime::from_hms_opt(day_starts_at, 0, 0).unwrap_or(NaiveTime::MIN));
+    (0..3)
+        .find_map(|shift| {
+            tz.from_local_datetime(&(boundary + Duration::hours(shift)))
+                .earliest()
+        })
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&boundary))
+}
+
+/// Whole days a streak has run at `now`, read off the wall clock of `tz`. A
+/// day is complete when the clock shows the start time again, so DST days of
+/// 23 or 25 hours count as one day, and a streak started late in the evening
+/// does not gain a day at midnight. Date-only starts begin at `day_starts_at`
+/// o'clock.
+///
+/// Both ends are converted with the zone in effect now, so the count follows
+/// the user when they travel or change their zone.
+pub fn days_since<Tz: TimeZone>(
+    start: StreakStart,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    day_starts_at: u32,
+) -> u64 {
+    let start = match start {
+        StreakStart::At(instant) => instant.with_timezone(tz).naive_local(),
+        StreakStart::Day(date) => {
+            date.and_time(NaiveTime::from_hms_opt(day_starts_at, 0, 0).unwrap_or(NaiveTime::MIN))
+        }
+    };
+    elapsed_days(start, now.with_timezone(tz).naive_local())
+}
+
+/// The first instant `days_since` reaches `days`: the start time on the wall
+/// clock, `days` days later. If that time falls into a DST gap, the first
+/// minute after the gap.
+pub fn reached_at<Tz: TimeZone>(
+    start: StreakStart,
+    days: u64,
+    tz: &Tz,
+    day_starts_at: u32,
+) -> DateTime<Utc> {
+    let start = match start {
+        StreakStart::At(instant) => instant.with_timezone(tz).naive_local(),
+        StreakStart::Day(date) => {
+            date.and_time(NaiveTime::from_hms_opt(day_starts_at, 0, 0).unwrap_or(NaiveTime::MIN))
+        }
+    };
+    let target = start + Duration::days(days as i64);
+    if let Some(at) = tz.from_local_datetime(&target).earliest() {
+        return at.with_timezone(&Utc);
+    }
+    let minute = target
+        .with_second(0)
+        .and_then(|t| t.with_nanosecond(0))
+        .unwrap_or(target);
+    (1..=3 * 60)
+        .find_map(|shift| {
+            tz.from_local_datetime(&(minute + Duration::minutes(shift)))
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&target))
+}
+
+fn elapsed_days(start: NaiveDateTime, now: NaiveDateTime) -> u64 {
+    if now <= start {
+        return 0;
+    }
+    let mut days = (now.date() - start.date()).num_days();
+    if now.time() < start.time() {
+        days -= 1;
+    }
+    days.max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::New_York;
+    use chrono_tz::Asia::Tokyo;
+    use chrono_tz::Europe::Berlin;
+
+    fn at<Tz: TimeZone>(tz: &Tz, y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        tz.with_ymd_and_hms(y, m, d, h, min, 0)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn late_evening_start_does_not_gain_a_day_at_midnight() {
+        let start = StreakStart::At(at(&Berlin, 2024, 6, 1, 23, 50));
+        assert_eq!(
+            days_since(start, at(&Berlin, 2024, 6, 2, 0, 0), &Berlin, 0),
+            0
+        );
+        assert_eq!(
+            days_since(start, at(&Berlin, 2024, 6, 2, 23, 49), &Berlin, 0),
+            0
+        );
+        assert_eq!(
+            days_since(start, at(&Berlin, 2024, 6, 2, 23, 50), &Berlin, 0),
+            1
+        );
+    }
+
+    #[test]
+    fn spring_forward_day_counts_as_one_day() {
+        // Clocks jump from 02:00 to 03:00 on 2024-03-10; the day lasts 23 hours
+        let start = StreakStart::At(at(&New_York, 2024, 3, 9, 12, 0));
+        let now = at(&New_York, 2024, 3, 10, 12, 0);
+        assert_eq!((now - at(&New_York, 2024, 3, 9, 12, 0)).num_hours(), 23);
+        assert_eq!(days_since(start, now, &New_York, 0), 1);
+        assert_eq!(
+            days_since(start, at(&New_York, 2024, 3, 10, 11, 59), &New_York, 0),
+            0
+        );
+    }
+
+    #[test]
+    fn fall_back_day_counts_as_one_day() {
+        // Clocks go back from 02:00 to 01:00 on 2024-11-03; the day lasts 25 hours
+        let start = StreakStart::At(at(&New_York, 2024, 11, 2, 12, 0));
+        let after_24h = at(&New_York, 2024, 11, 2, 12, 0) + Duration::hours(24);
+        assert_eq!(days_since(start, after_24h, &New_York, 0), 0);
+        assert_eq!(
+            days_since(start, at(&New_York, 2024, 11, 3, 12, 0), &New_York, 0),
+            1
+        );
+    }
+
+    #[test]
+    fn start_inside_repeated_hour() {
+        // 01:30 happens twice on 2024-11-03; start at the first one (EDT)
+        let first = New_York
+            .with_ymd_and_hms(2024, 11, 3, 1, 30, 0)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc);
+        let second = New_York
+            .with_ymd_and_hms(2024, 11, 3, 1, 30, 0)
+            .latest()
+            .unwrap()
+            .with_timezone(&Utc);
+        let start = StreakStart::At(first);
+        assert_eq!(days_since(start, second, &New_York, 0), 0);
+        assert_eq!(
+            days_since(start, at(&New_York, 2024, 11, 4, 1, 30), &New_York, 0),
+            1
+        );
+    }
+
+    #[test]
+    fn day_starts_at_shifts_streak_day() {
+        let early = at(&Berlin, 2024, 6, 2, 3, 59);
+        let later = at(&Berlin, 2024, 6, 2, 4, 0);
+        assert_eq!(streak_day(early, &Berlin, 4), date(2024, 6, 1));
+        assert_eq!(streak_day(later, &Berlin, 4), date(2024, 6, 2));
+        assert_eq!(streak_day(early, &Berlin, 0), date(2024, 6, 2));
+    }
+
+    #[test]
+    fn streak_day_across_dst_transitions() {
+        // 02:30 does not exist on 2024-03-10; 01:59 EST is still the 9th with a 2 AM boundary
+        assert_eq!(
+            streak_day(at(&New_York, 2024, 3, 10, 1, 59), &New_York, 2),
+            date(2024, 3, 9)
+        );
+        assert_eq!(
+            streak_day(at(&New_York, 2024, 3, 10, 3, 0), &New_York, 2),
+            date(2024, 3, 10)
+        );
+        // Both 01:30s of 2024-11-03 fall before a 2 AM boundary
+        let second = New_York
+            .with_ymd_and_hms(2024, 11, 3, 1, 30, 0)
+            .latest()
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(streak_day(second, &New_York, 2), date(2024, 11, 2));
+    }
+
+    #[test]
+    fn day_start_skips_dst_gap() {
+        // 02:00 does not exist on 2024-03-10 in New York
+        assert_eq!(
+            day_start(date(2024, 3, 10), &New_York, 2),
+            at(&New_York, 2024, 3, 10, 3, 0)
+        );
+        assert_eq!(
+            day_start(date(2024, 3, 11), &New_York, 2),
+            at(&New_York, 2024, 3, 11, 2, 0)
+        );
+    }
+
+    #[test]
+    fn date_only_start_begins_at_day_boundary() {
+        let start = StreakStart::parse("2024-03-09").unwrap();
+        assert_eq!(
+            days_since(start, at(&New_York, 2024, 3, 10, 3, 59), &New_York, 4),
+            0
+        );
+        assert_eq!(
+            days_since(start, at(&New_York, 2024, 3, 10, 4, 0), &New_York, 4),
+            1
+        );
+        assert_eq!(
+            days_since(start, at(&New_York, 2024, 3, 10, 0, 0), &New_York, 0),
+            1
+        );
+    }
+
+    #[test]
+    fn count_follows_zone_changes() {
+        // 23.5 hours across the European DST change on 2024-03-31
+        let start = StreakStart::At(at(&Utc, 2024, 3, 30, 12, 0));
+        let now = at(&Utc, 2024, 3, 31, 11, 30);
+        // Berlin: 13:00 CET to 13:30 CEST, a full day on the wall clock
+        assert_eq!(days_since(start, now, &Berlin, 0), 1);
+        // Tokyo has no DST: 21:00 to 20:30, not a day yet
+        assert_eq!(days_since(start, now, &Tokyo, 0), 0);
+    }
+
+    #[test]
+    fn future_start_counts_zero() {
+        let start = StreakStart::At(at(&Berlin, 2024, 6, 2, 12, 0));
+        assert_eq!(
+            days_since(start, at(&Berlin, 2024, 6, 1, 12, 0), &Berlin, 0),
+            0
+        );
+    }
+
+    #[test]
+    fn reached_at_is_when_the_count_ticks_over() {
+        let start = StreakStart::At(at(&Berlin, 2024, 6, 1, 23, 50));
+        let reached = reached_at(start, 7, &Berlin, 0);
+        assert_eq!(reached, at(&Berlin, 2024, 6, 8, 23, 50));
+        assert_eq!(days_since(start, reached, &Berlin, 0), 7);
+        assert_eq!(
+            days_since(start, reached - Duration::seconds(1), &Berlin, 0),
+            6
+        );
+    }
+
+    #[test]
+    fn reached_at_skips_dst_gap() {
+        // 02:30 does not exist on 2024-03-10 in New York
+        let start = StreakStart::At(at(&New_York, 2024, 3, 9, 2, 30));
+        let reached = reached_at(start, 1, &New_York, 0);
+        assert_eq!(reached, at(&New_York, 2024, 3, 10, 3, 0));
+        assert_eq!(days_since(start, reached, &New_York, 0), 1);
+        assert_eq!(
+            days_since(start, reached - Duration::seconds(1), &New_York, 0),
+            0
+        );
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert!(StreakStart::parse("yesterday").is_err());
+        assert!(matches!(
+            StreakStart::parse("2024-06-01T10:00:00+02:00"),
+            Ok(StreakStart::At(_))
+        ));
+    }
+}
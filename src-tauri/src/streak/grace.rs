@@ -0,0 +1,341 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::habits::{self, HabitKind};
+use crate::db;
+use crate::plugins::blocker::clock;
+
+const KEY_GRACE_TOKENS: &str = "grace_tokens";
+
+/// An exception can be declared at most this many days ahead.
+const MAX_DAYS_AHEAD: i64 = 7;
+
+/// How freeze tokens are earned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraceSettings {
+    pub enabled: bool,
+    /// Clean days a streak needs for each token.
+    pub days_per_token: u32,
+    /// Most unspent tokens a habit can hold. Milestones reached at the cap
+    /// are recorded as forfeited.
+    pub max_tokens: u32,
+}
+
+impl Default for GraceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            days_per_token: 30,
+            max_tokens: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreezeKind {
+    Earned,
+    /// A milestone reached while holding the most tokens allowed.
+    Forfeited,
+    Spent,
+}
+
+impl FreezeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Earned => "earned",
+            Self::Forfeited => "forfeited",
+            Self::Spent => "spent",
+        }
+    }
+
+    fn parse(kind: &str) -> Self {
+        match kind {
+            "earned" => Self::Earned,
+            "forfeited" => Self::Forfeited,
+            _ => Self::Spent,
+        }
+    }
+}
+
+/// One row of a habit's token ledger.
+#[derive(Debug, Clone, Serialize)]
+pub struct FreezeEntry {
+    pub id: i64,
+    pub habit_id: i64,
+    pub kind: FreezeKind,
+    pub recorded_at: String,
+    /// Earned and forfeited: the clean days the streak had reached.
+    pub milestone: Option<u64>,
+    /// Spent: the day the exception covers, `YYYY-MM-DD`.
+    pub day: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FreezeRow {
+    id: i64,
+    habit_id: i64,
+    kind: String,
+    recorded_at: String,
+    milestone: Option<i64>,
+    day: Option<String>,
+    note: Option<String>,
+}
+
+impl From<FreezeRow> for FreezeEntry {
+    fn from(row: FreezeRow) -> Self {
+        Self {
+            id: row.id,
+            habit_id: row.habit_id,
+            kind: FreezeKind::parse(&row.kind),
+            recorded_at: row.recorded_at,
+            milestone: row.milestone.map(|m| m.max(0) as u64),
+            day: row.day,
+            note: row.note,
+        }
+    }
+}
+
+const FREEZE_COLUMNS: &str = "id, habit_id, kind, recorded_at, milestone, day, note";
+
+pub async fn load_settings(pool: &SqlitePool) -> Result<GraceSettings, String> {
+    Ok(db::get_setting(pool, KEY_GRACE_TOKENS)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default())
+}
+
+pub async fn save_settings(pool: &SqlitePool, settings: &GraceSettings) -> Result<(), String> {
+    if settings.days_per_token == 0 {
+        return Err("A token needs at least one clean day".into());
+    }
+    let value = serde_json::to_string(settings)
+        .map_err(|e| format!("Failed to serialize freeze settings: {}", e))?;
+    db::set_setting(pool, KEY_GRACE_TOKENS, &value).await
+}
+
+/// Unspent tokens of a habit.
+pub async fn balance(pool: &SqlitePool, habit_id: i64) -> Result<u32, String> {
+    let (balance,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(CASE kind WHEN 'earned' THEN 1 WHEN 'spent' THEN -1 ELSE 0 END), 0)
+         FROM streak_freezes WHERE habit_id = ?",
+    )
+    .bind(habit_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to load freeze tokens: {}", e))?;
+    Ok(balance.max(0) as u32)
+}
+
+/// Tokens earned, forfeited and spent, newest first.
+pub async fn ledger(pool: &SqlitePool, habit_id: i64) -> Result<Vec<FreezeEntry>, String> {
+    let rows: Vec<FreezeRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM streak_freezes WHERE habit_id = ? ORDER BY id DESC",
+        FREEZE_COLUMNS
+    ))
+    .bind(habit_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load freeze history: {}", e))?;
+    Ok(rows.into_iter().map(FreezeEntry::from).collect())
+}
+
+/// Record a token for each milestone the streak that began at `run_start`
+/// reached with `clean_days`. Milestones already recorded are skipped, so
+/// this can run on every scheduler tick.
+pub(super) async fn award(
+    pool: &SqlitePool,
+    habit_id: i64,
+    run_start: &str,
+    clean_days: u64,
+) -> Result<(), String> {
+    let settings = load_settings(pool).await?;
+    if !settings.enabled || settings.days_per_token == 0 {
+        return Ok(());
+    }
+    let per_token = settings.days_per_token as u64;
+    if clean_days < per_token {
+        return Ok(());
+    }
+
+    let (recorded,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(MAX(milestone), 0) FROM streak_freezes
+         WHERE habit_id = ? AND run_start = ? AND kind <> 'spent'",
+    )
+    .bind(habit_id)
+    .bind(run_start)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to load freeze tokens: {}", e))?;
+
+    let mut tokens = balance(pool, habit_id).await?;
+    let now = clock::now().to_rfc3339();
+    for milestone in (1..=clean_days / per_token).map(|n| n * per_token) {
+        if milestone <= recorded.max(0) as u64 {
+            continue;
+        }
+        let kind = if tokens < settings.max_tokens {
+            tokens += 1;
+            FreezeKind::Earned
+        } else {
+            FreezeKind::Forfeited
+        };
+        sqlx::query(
+            "INSERT INTO streak_freezes (habit_id, kind, recorded_at, run_start, milestone)
+             VALUES (?, ?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        )
+        .bind(habit_id)
+        .bind(kind.as_str())
+        .bind(&now)
+        .bind(run_start)
+        .bind(milestone as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save freeze token: {}", e))?;
+        log::info!(
+            "Freeze token {:?} for habit {} at {} clean days",
+            kind,
+            habit_id,
+            milestone
+        );
+    }
+    Ok(())
+}
+
+/// Spend a token on `day` (`YYYY-MM-DD`, default today). A do habit's run
+/// carries on over the day; an avoid habit's streak stays running instead of
+/// being reset. Either way the day is not counted as clean.
+pub async fn spend(
+    pool: &SqlitePool,
+    habit_id: i64,
+    day: Option<String>,
+    note: Option<String>,
+) -> Result<FreezeEntry, String> {
+    if !load_settings(pool).await?.enabled {
+        return Err("Streak freezes are turned off".into());
+    }
+    let habit = habits::get(pool, habit_id).await?;
+    if habit.archived_at.is_some() {
+        return Err(format!("{} is archived", habit.name));
+    }
+
+    let settings = super::load_settings(pool).await?;
+    let today = super::today(&settings);
+    let day = match day {
+        Some(day) => NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+            .map_err(|_| format!("Could not parse date: {}", day))?,
+        None => today,
+    };
+    if day > today + Duration::days(MAX_DAYS_AHEAD) {
+        return Err(format!(
+            "Exceptions can be declared at most {} days ahead",
+            MAX_DAYS_AHEAD
+        ));
+    }
+
+    let streak_id = match habit.kind {
+        HabitKind::Avoid => {
+            let running = super::current(pool, habit_id)
+                .await?
+                .ok_or("No streak is running")?;
+            if super::start_day(&running, &settings).map_or(true, |start| day < start) {
+                return Err("The exception falls before the running streak began".into());
+            }
+            Some(running.id)
+        }
+        HabitKind::Do => {
+            let (checked_in,): (bool,) = sqlx::query_as(
+                "SELECT EXISTS (SELECT 1 FROM habit_check_ins WHERE habit_id = ? AND date = ?)",
+            )
+            .bind(habit_id)
+            .bind(day.to_string())
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("Failed to load check-ins: {}", e))?;
+            if checked_in {
+                return Err(format!("{} is checked in and needs no freeze", day));
+            }
+            None
+        }
+    };
+
+    // One statement, so two spends at once can't both take the last token
+    // or cover the same day
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let result = sqlx::query(
+        "INSERT INTO streak_freezes (habit_id, kind, recorded_at, day, streak_id, note)
+         SELECT ?1, 'spent', ?2, ?3, ?4, ?5
+         WHERE NOT EXISTS (
+                 SELECT 1 FROM streak_freezes WHERE habit_id = ?1 AND kind = 'spent' AND day = ?3
+             )
+             AND (SELECT COALESCE(SUM(CASE kind WHEN 'earned' THEN 1 WHEN 'spent' THEN -1
+                     ELSE 0 END), 0)
+                  FROM streak_freezes WHERE habit_id = ?1) > 0",
+    )
+    .bind(habit_id)
+    .bind(clock::now().to_rfc3339())
+    .bind(day.to_string())
+    .bind(streak_id)
+    .bind(&note)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to spend freeze token: {}", e))?;
+    if result.rows_affected() == 0 {
+        if frozen_days(pool, habit_id).await?.contains(&day) {
+            return Err(format!("{} is already covered by a freeze", day));
+        }
+        return Err("No freeze tokens left".into());
+    }
+    let id = result.last_insert_rowid();
+
+    log::info!("Freeze token spent on {} for habit {}", day, habit.name);
+    sqlx::query_as::<_, FreezeRow>(&format!(
+        "SELECT {} FROM streak_freezes WHERE id = ?",
+        FREEZE_COLUMNS
+    ))
+    .bind(id)
+    .fetch_one(pool)
+    .await
+    .map(FreezeEntry::from)
+    .map_err(|e| format!("Failed to load freeze token: {}", e))
+}
+
+/// Days a habit spent tokens on, oldest first.
+pub(super) async fn frozen_days(
+    pool: &SqlitePool,
+    habit_id: i64,
+) -> Result<Vec<NaiveDate>, String> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT day FROM streak_freezes WHERE habit_id = ? AND kind = 'spent' ORDER BY day",
+    )
+    .bind(habit_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load freeze tokens: {}", e))?;
+    Ok(parse_days(&rows))
+}
+
+/// Days tokens were spent on during the avoid streak `streak_id`, oldest
+/// first.
+pub(crate) async fn frozen_in_streak(
+    pool: &SqlitePool,
+    streak_id: i64,
+) -> Result<Vec<NaiveDate>, String> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT day FROM streak_freezes WHERE streak_id = ? AND kind = 'spent' ORDER BY day",
+    )
+    .bind(streak_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load freeze tokens: {}", e))?;
+    Ok(parse_days(&rows))
+}
+
+fn parse_days(rows: &[(String,)]) -> Vec<NaiveDate> {
+    rows.iter()
+        .filter_map(|(day,)| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        .collect()
+}
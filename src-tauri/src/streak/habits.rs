@@ -0,0 +1,592 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::days::StreakStart;
+use super::{grace, StreakSettings};
+use crate::plugins::blocker::clock;
+
+/// A missed check-in can be made up this many days later, no more.
+const CHECK_IN_DAYS_BACK: i64 = 1;
+
+/// What keeping a habit means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HabitKind {
+    /// Something to stay away from. Streaks run until reset, like the
+    /// original single streak.
+    Avoid,
+    /// Something to do every day. The streak is the run of consecutive days
+    /// with a check-in.
+    Do,
+}
+
+impl HabitKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Avoid => "avoid",
+            Self::Do => "do",
+        }
+    }
+
+    fn parse(kind: &str) -> Self {
+        match kind {
+            "do" => Self::Do,
+            _ => Self::Avoid,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Habit {
+    pub id: i64,
+    pub name: String,
+    pub kind: HabitKind,
+    pub created_at: String,
+    /// Local `HH:MM` of the daily reminder, or null for none.
+    pub reminder_time: Option<String>,
+    /// The habit the single streak of earlier versions became. Shares its
+    /// streaks with the streak commands and can't be archived.
+    pub is_default: bool,
+    pub archived_at: Option<String>,
+}
+
+/// A habit with its streak worked out.
+#[derive(Debug, Clone, Serialize)]
+pub struct HabitStatus {
+    #[serde(flatten)]
+    pub habit: Habit,
+    /// For avoid habits the start of the running streak; for do habits the
+    /// first day of the current run. Null without either.
+    pub start_date: Option<String>,
+    /// Clean days of the current streak. Days covered by a freeze token are
+    /// left out.
+    pub current_days: u64,
+    /// The best streak, the running one included.
+    pub best_days: u64,
+    /// Checked in today. Null for avoid habits.
+    pub done_today: Option<bool>,
+    /// Days of the current streak covered by a freeze token.
+    pub frozen_days: u64,
+    /// Unspent freeze tokens.
+    pub freeze_tokens: u32,
+}
+
+/// One streak of a habit. The running one has no `end_date`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HabitRun {
+    /// RFC 3339 time for avoid habits, `YYYY-MM-DD` for do habits.
+    pub start_date: String,
+    pub end_date: Option<String>,
+    /// Clean days, without the frozen ones.
+    pub days: u64,
+    pub reset_reason: Option<String>,
+    /// Days covered by a freeze token, `YYYY-MM-DD`.
+    pub frozen_days: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewHabit {
+    pub name: String,
+    pub kind: HabitKind,
+    pub reminder_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HabitChanges {
+    pub name: String,
+    pub reminder_time: Option<String>,
+}
+
+/// Consecutive days of a do habit, each checked in or frozen.
+struct Run {
+    first: NaiveDate,
+    last: NaiveDate,
+    /// Days checked in.
+    done: u64,
+    frozen: Vec<NaiveDate>,
+}
+
+#[derive(sqlx::FromRow)]
+struct HabitRow {
+    id: i64,
+    name: String,
+    kind: String,
+    created_at: String,
+    reminder_time: Option<String>,
+    is_default: bool,
+    archived_at: Option<String>,
+}
+
+impl From<HabitRow> for Habit {
+    fn from(row: HabitRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            kind: HabitKind::parse(&row.kind),
+            created_at: row.created_at,
+            reminder_time: row.reminder_time,
+            is_default: row.is_default,
+            archived_at: row.archived_at,
+        }
+    }
+}
+
+const HABIT_COLUMNS: &str = "id, name, kind, created_at, reminder_time, is_default, archived_at";
+
+/// Id of the default habit, created when the database is opened.
+pub async fn default_id(pool: &SqlitePool) -> Result<i64, String> {
+    sqlx::query_as::<_, (i64,)>("SELECT id FROM habits WHERE is_default = 1 LIMIT 1")
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load default habit: {}", e))?
+        .map(|(id,)| id)
+        .ok_or_else(|| "The default habit is missing".into())
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> Result<Habit, String> {
+    sqlx::query_as::<_, HabitRow>(&format!(
+        "SELECT {} FROM habits WHERE id = ?",
+        HABIT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load habit: {}", e))?
+    .map(Habit::from)
+    .ok_or_else(|| format!("No habit with id {}", id))
+}
+
+/// Habits that aren't archived, oldest first.
+pub async fn active(pool: &SqlitePool) -> Result<Vec<Habit>, String> {
+    let rows: Vec<HabitRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM habits WHERE archived_at IS NULL ORDER BY is_default DESC, id",
+        HABIT_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load habits: {}", e))?;
+    Ok(rows.into_iter().map(Habit::from).collect())
+}
+
+/// Every habit that isn't archived, with its streak.
+pub async fn list(pool: &SqlitePool) -> Result<Vec<HabitStatus>, String> {
+    let settings = super::load_settings(pool).await?;
+    let mut statuses = Vec::new();
+    for habit in active(pool).await? {
+        statuses.push(status_of(pool, habit, &settings).await?);
+    }
+    Ok(statuses)
+}
+
+pub async fn status(pool: &SqlitePool, id: i64) -> Result<HabitStatus, String> {
+    let settings = super::load_settings(pool).await?;
+    status_of(pool, get(pool, id).await?, &settings).await
+}
+
+/// Add a habit. An avoid habit's first streak starts right away.
+pub async fn create(pool: &SqlitePool, new: NewHabit) -> Result<HabitStatus, String> {
+    let name = validate_name(&new.name)?;
+    let reminder_time = validate_reminder(new.reminder_time.as_deref())?;
+
+    let id = sqlx::query(
+        "INSERT INTO habits (name, kind, created_at, reminder_time) VALUES (?, ?, ?, ?)",
+    )
+    .bind(&name)
+    .bind(new.kind.as_str())
+    .bind(clock::now().to_rfc3339())
+    .bind(&reminder_time)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save habit: {}", e))?
+    .last_insert_rowid();
+
+    if new.kind == HabitKind::Avoid {
+        super::start(pool, id).await?;
+    }
+    log::info!("Habit {} ({:?}) created", name, new.kind);
+    status(pool, id).await
+}
+
+/// Rename a habit or change its reminder.
+pub async fn update(
+    pool: &SqlitePool,
+    id: i64,
+    changes: HabitChanges,
+) -> Result<HabitStatus, String> {
+    let name = validate_name(&changes.name)?;
+    let reminder_time = validate_reminder(changes.reminder_time.as_deref())?;
+    get(pool, id).await?;
+
+    sqlx::query("UPDATE habits SET name = ?, reminder_time = ? WHERE id = ?")
+        .bind(&name)
+        .bind(&reminder_time)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save habit: {}", e))?;
+    status(pool, id).await
+}
+
+/// Stop tracking a habit. Its history is kept.
+pub async fn archive(pool: &SqlitePool, id: i64) -> Result<(), String> {
+    let habit = get(pool, id).await?;
+    if habit.is_default {
+        return Err("The main streak can't be archived".into());
+    }
+    if habit.archived_at.is_some() {
+        return Ok(());
+    }
+
+    if let Some(running) = super::current(pool, id).await? {
+        super::end(pool, running, Some("Habit archived".into()), clock::now()).await?;
+    }
+    sqlx::query("UPDATE habits SET archived_at = ? WHERE id = ?")
+        .bind(clock::now().to_rfc3339())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to archive habit: {}", e))?;
+    log::info!("Habit {} archived", habit.name);
+    Ok(())
+}
+
+/// Mark a do habit as done on `date` (`YYYY-MM-DD`, default today). Only
+/// yesterday can be made up, and nothing before the habit was created.
+pub async fn check_in(
+    pool: &SqlitePool,
+    id: i64,
+    date: Option<String>,
+) -> Result<HabitStatus, String> {
+    let habit = tracked(pool, id, HabitKind::Do).await?;
+    let settings = super::load_settings(pool).await?;
+    let today = super::today(&settings);
+    let date = match date {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|_| format!("Could not parse date: {}", date))?,
+        None => today,
+    };
+    if date > today {
+        return Err("Check-ins can't be made for future days".into());
+    }
+    if date < today - chrono::Duration::days(CHECK_IN_DAYS_BACK) {
+        return Err(format!(
+            "Check-ins can be made up at most {} day later",
+            CHECK_IN_DAYS_BACK
+        ));
+    }
+    let created = match StreakStart::parse(&habit.created_at) {
+        Ok(StreakStart::At(at)) => super::day_of(at, &settings),
+        Ok(StreakStart::Day(day)) => day,
+        Err(_) => today,
+    };
+    if date < created {
+        return Err("Check-ins can't be made for days before the habit was created".into());
+    }
+
+    sqlx::query(
+        "INSERT INTO habit_check_ins (habit_id, date, checked_at) VALUES (?, ?, ?)
+         ON CONFLICT(habit_id, date) DO NOTHING",
+    )
+    .bind(id)
+    .bind(date.to_string())
+    .bind(clock::now().to_rfc3339())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save check-in: {}", e))?;
+    award_freezes_of(pool, &habit, &settings).await?;
+    status_of(pool, habit, &settings).await
+}
+
+/// End the running streak of an avoid habit.
+pub async fn reset(
+    pool: &SqlitePool,
+    id: i64,
+    reason: Option<String>,
+) -> Result<HabitStatus, String> {
+    tracked(pool, id, HabitKind::Avoid).await?;
+    super::reset(pool, id, reason).await?;
+    status(pool, id).await
+}
+
+/// Start a new streak of an avoid habit after a reset.
+pub async fn start(pool: &SqlitePool, id: i64) -> Result<HabitStatus, String> {
+    tracked(pool, id, HabitKind::Avoid).await?;
+    super::start(pool, id).await?;
+    status(pool, id).await
+}
+
+/// Streaks of a habit, newest first, the running one included.
+pub async fn history(pool: &SqlitePool, id: i64) -> Result<Vec<HabitRun>, String> {
+    let habit = get(pool, id).await?;
+    match habit.kind {
+        HabitKind::Avoid => {
+            let settings = super::load_settings(pool).await?;
+            let now = clock::now();
+            let mut runs = Vec::new();
+            if let Some(running) = super::current(pool, id).await? {
+                runs.push(HabitRun {
+                    days: super::clean_days_of(pool, &running, &settings, now).await?,
+                    frozen_days: frozen_strings(grace::frozen_in_streak(pool, running.id).await?),
+                    start_date: running.start_date,
+                    end_date: None,
+                    reset_reason: None,
+                });
+            }
+            for record in super::history(pool, id).await? {
+                runs.push(HabitRun {
+                    frozen_days: frozen_strings(grace::frozen_in_streak(pool, record.id).await?),
+                    start_date: record.start_date,
+                    end_date: record.end_date,
+                    days: record.days.unwrap_or(0).max(0) as u64,
+                    reset_reason: record.reset_reason,
+                });
+            }
+            Ok(runs)
+        }
+        HabitKind::Do => {
+            let settings = super::load_settings(pool).await?;
+            let today = super::today(&settings);
+            let frozen = grace::frozen_days(pool, id).await?;
+            let mut runs: Vec<HabitRun> =
+                check_in_runs(&check_in_dates(pool, id).await?, &frozen, today)
+                    .into_iter()
+                    .map(|run| HabitRun {
+                        start_date: run.first.to_string(),
+                        end_date: (!is_running(run.last, today)).then(|| run.last.to_string()),
+                        days: run.done,
+                        reset_reason: None,
+                        frozen_days: frozen_strings(run.frozen),
+                    })
+                    .collect();
+            runs.reverse();
+            Ok(runs)
+        }
+    }
+}
+
+/// Record the freeze tokens the running streak of every tracked habit has
+/// earned. Runs from the milestone scheduler and after a check-in, so that
+/// reading a streak never writes to the token ledger.
+pub async fn award_freezes(pool: &SqlitePool) -> Result<(), String> {
+    let settings = super::load_settings(pool).await?;
+    for habit in active(pool).await? {
+        award_freezes_of(pool, &habit, &settings).await?;
+    }
+    Ok(())
+}
+
+async fn award_freezes_of(
+    pool: &SqlitePool,
+    habit: &Habit,
+    settings: &StreakSettings,
+) -> Result<(), String> {
+    match habit.kind {
+        HabitKind::Avoid => {
+            let Some(running) = super::current(pool, habit.id).await? else {
+                return Ok(());
+            };
+            let days = super::clean_days_of(pool, &running, settings, clock::now()).await?;
+            grace::award(pool, habit.id, &running.start_date, days).await
+        }
+        HabitKind::Do => {
+            let today = super::today(settings);
+            let dates = check_in_dates(pool, habit.id).await?;
+            let frozen = grace::frozen_days(pool, habit.id).await?;
+            let runs = check_in_runs(&dates, &frozen, today);
+            match runs.last().filter(|run| is_running(run.last, today)) {
+                Some(run) => grace::award(pool, habit.id, &run.first.to_string(), run.done).await,
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+async fn status_of(
+    pool: &SqlitePool,
+    habit: Habit,
+    settings: &StreakSettings,
+) -> Result<HabitStatus, String> {
+    match habit.kind {
+        HabitKind::Avoid => {
+            let summary = super::summary(pool, habit.id).await?;
+            Ok(HabitStatus {
+                habit,
+                start_date: summary.start_date,
+                current_days: summary.current_days,
+                best_days: summary.best_streak,
+                done_today: None,
+                frozen_days: summary.frozen_days,
+                freeze_tokens: summary.freeze_tokens,
+            })
+        }
+        HabitKind::Do => {
+            let today = super::today(settings);
+            let dates = check_in_dates(pool, habit.id).await?;
+            let frozen = grace::frozen_days(pool, habit.id).await?;
+            let runs = check_in_runs(&dates, &frozen, today);
+            let current = runs.last().filter(|run| is_running(run.last, today));
+            Ok(HabitStatus {
+                start_date: current.map(|run| run.first.to_string()),
+                current_days: current.map_or(0, |run| run.done),
+                best_days: runs.iter().map(|run| run.done).max().unwrap_or(0),
+                done_today: Some(dates.last() == Some(&today)),
+                frozen_days: current.map_or(0, |run| run.frozen.len() as u64),
+                freeze_tokens: grace::balance(pool, habit.id).await?,
+                habit,
+            })
+        }
+    }
+}
+
+/// The habit `id`, if it is of `kind` and not archived.
+async fn tracked(pool: &SqlitePool, id: i64, kind: HabitKind) -> Result<Habit, String> {
+    let habit = get(pool, id).await?;
+    if habit.archived_at.is_some() {
+        return Err(format!("{} is archived", habit.name));
+    }
+    if habit.kind != kind {
+        return Err(match kind {
+            HabitKind::Avoid => format!("{} is a daily habit; check in instead", habit.name),
+            HabitKind::Do => format!("{} is an avoid habit; it has no check-ins", habit.name),
+        });
+    }
+    Ok(habit)
+}
+
+/// Days a do habit was checked in on, oldest first.
+async fn check_in_dates(pool: &SqlitePool, id: i64) -> Result<Vec<NaiveDate>, String> {
+    let rows: Vec<(String,)> =
+        sqlx::query_as("SELECT date FROM habit_check_ins WHERE habit_id = ? ORDER BY date")
+            .bind(id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to load check-ins: {}", e))?;
+    Ok(rows
+        .iter()
+        .filter_map(|(date,)| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .collect())
+}
+
+/// Runs of consecutive days, oldest first. A frozen day up to `today` carries
+/// a run on without counting towards it, but can't start one.
+fn check_in_runs(dates: &[NaiveDate], frozen: &[NaiveDate], today: NaiveDate) -> Vec<Run> {
+    let mut days: Vec<(NaiveDate, bool)> = dates
+        .iter()
+        .map(|date| (*date, false))
+        .chain(frozen.iter().filter(|d| **d <= today).map(|d| (*d, true)))
+        .collect();
+    // A check-in sorts before a freeze on the same day and wins
+    days.sort();
+    days.dedup_by_key(|(date, _)| *date);
+
+    let mut runs: Vec<Run> = Vec::new();
+    for (date, is_frozen) in days {
+        match runs.last_mut() {
+            Some(run) if run.last.succ_opt() == Some(date) => {
+                run.last = date;
+                if is_frozen {
+                    run.frozen.push(date);
+                } else {
+                    run.done += 1;
+                }
+            }
+            _ if is_frozen => {}
+            _ => runs.push(Run {
+                first: date,
+                last: date,
+                done: 1,
+                frozen: Vec::new(),
+            }),
+        }
+    }
+    runs
+}
+
+/// A run is still going if it reached today or yesterday; today isn't over
+/// yet.
+fn is_running(last: NaiveDate, today: NaiveDate) -> bool {
+    last == today || last.succ_opt() == Some(today)
+}
+
+fn frozen_strings(days: Vec<NaiveDate>) -> Vec<String> {
+    days.iter().map(NaiveDate::to_string).collect()
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err("A habit needs a name".into());
+    }
+    Ok(name)
+}
+
+/// Normalize a reminder to `HH:MM`.
+fn validate_reminder(reminder: Option<&str>) -> Result<Option<String>, String> {
+    reminder
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(|r| {
+            NaiveTime::parse_from_str(r, "%H:%M")
+                .map(|t| t.format("%H:%M").to_string())
+                .map_err(|_| format!("Reminder time must be HH:MM, got {}", r))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, d).unwrap()
+    }
+
+    fn spans(runs: &[Run]) -> Vec<(NaiveDate, NaiveDate, u64, Vec<NaiveDate>)> {
+        runs.iter()
+            .map(|r| (r.first, r.last, r.done, r.frozen.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn gaps_split_runs() {
+        let runs = check_in_runs(&[date(1), date(2), date(4), date(5), date(6)], &[], date(6));
+        assert_eq!(
+            spans(&runs),
+            vec![(date(1), date(2), 2, vec![]), (date(4), date(6), 3, vec![]),]
+        );
+    }
+
+    #[test]
+    fn freeze_carries_a_run_without_counting() {
+        let runs = check_in_runs(&[date(1), date(2), date(4)], &[date(3)], date(4));
+        assert_eq!(spans(&runs), vec![(date(1), date(4), 3, vec![date(3)])]);
+    }
+
+    #[test]
+    fn freeze_cannot_start_a_run() {
+        let runs = check_in_runs(&[date(3), date(4)], &[date(1), date(2)], date(4));
+        assert_eq!(spans(&runs), vec![(date(3), date(4), 2, vec![])]);
+
+        assert!(check_in_runs(&[], &[date(1)], date(1)).is_empty());
+    }
+
+    #[test]
+    fn check_in_wins_over_a_freeze_on_the_same_day() {
+        let runs = check_in_runs(&[date(1), date(2)], &[date(2)], date(2));
+        assert_eq!(spans(&runs), vec![(date(1), date(2), 2, vec![])]);
+    }
+
+    #[test]
+    fn future_freezes_are_ignored() {
+        let runs = check_in_runs(&[date(1)], &[date(2), date(3)], date(1));
+        assert_eq!(spans(&runs), vec![(date(1), date(1), 1, vec![])]);
+    }
+
+    #[test]
+    fn running_until_a_day_is_missed() {
+        assert!(is_running(date(5), date(5)));
+        assert!(is_running(date(4), date(5)));
+        assert!(!is_running(date(3), date(5)));
+    }
+}
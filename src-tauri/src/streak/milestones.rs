@@ -0,0 +1,75 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use super::days::{self, StreakStart};
+use super::{grace, StreakSettings};
+use crate::plugins::blocker::clock;
+
+/// Clean days worth a notification.
+pub const MILESTONES: &[u64] = &[1, 3, 7, 14, 30, 60, 90, 180, 365];
+
+/// When the running streak reaches a milestone.
+#[derive(Debug, Clone, Serialize)]
+pub struct Milestone {
+    pub days: u64,
+    /// RFC 3339 credited time the streak reaches `days` clean days.
+    pub at: String,
+    pub reached: bool,
+}
+
+/// Milestones of the running streak of `habit_id` with the credited time
+/// each is or was reached, in order. Empty without a running streak.
+pub async fn schedule(
+    pool: &SqlitePool,
+    habit_id: i64,
+) -> Result<Vec<(u64, DateTime<Utc>)>, String> {
+    let Some(running) = super::current(pool, habit_id).await? else {
+        return Ok(Vec::new());
+    };
+    let Ok(start) = StreakStart::parse(&running.start_date) else {
+        log::warn!("Streak {} has an unreadable start date", running.id);
+        return Ok(Vec::new());
+    };
+    let settings = super::load_settings(pool).await?;
+    let frozen = grace::frozen_in_streak(pool, running.id).await?;
+
+    Ok(MILESTONES
+        .iter()
+        .map(|days| (*days, reached_at(start, *days, &frozen, &settings)))
+        .collect())
+}
+
+pub async fn list(pool: &SqlitePool, habit_id: i64) -> Result<Vec<Milestone>, String> {
+    let now = clock::now();
+    Ok(schedule(pool, habit_id)
+        .await?
+        .into_iter()
+        .map(|(days, at)| Milestone {
+            days,
+            at: at.to_rfc3339(),
+            reached: at <= now,
+        })
+        .collect())
+}
+
+/// When the streak reaches `clean_days`. Each frozen day finished by then
+/// pushes it back by a day.
+pub(crate) fn reached_at(
+    start: StreakStart,
+    clean_days: u64,
+    frozen: &[NaiveDate],
+    settings: &StreakSettings,
+) -> DateTime<Utc> {
+    let mut skipped = 0;
+    loop {
+        let at = days::reached_at(start, clean_days + skipped, &Local, settings.day_starts_at);
+        let today = super::day_of(at, settings);
+        let finished = frozen.iter().filter(|day| **day < today).count() as u64;
+        // Only grows as `at` moves later, and never past the frozen days
+        if finished <= skipped {
+            return at;
+        }
+        skipped = finished;
+    }
+}
@@ -0,0 +1,311 @@
+pub mod analytics;
+pub mod days;
+pub mod grace;
+pub mod habits;
+pub mod milestones;
+pub mod recovery;
+pub mod risk;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::db;
+use crate::plugins::blocker::clock;
+use days::StreakStart;
+
+const KEY_DAY_STARTS_AT: &str = "day_starts_at";
+
+/// Data structure representing the user's streak information.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakData {
+    /// ISO 8601 date string of when the current streak started, or null if not active.
+    pub start_date: Option<String>,
+    /// The best (longest) streak in days, the running one included.
+    pub best_streak: u64,
+    /// Total number of streak resets.
+    pub total_resets: u64,
+    /// Current streak length in clean days (computed). Days covered by a
+    /// freeze token are left out.
+    pub current_days: u64,
+    /// Days of the current streak covered by a freeze token.
+    pub frozen_days: u64,
+    /// Unspent freeze tokens.
+    pub freeze_tokens: u32,
+    /// The system clock was moved; days are counted in credited time.
+    pub clock_tampered: bool,
+}
+
+/// User preferences for streak day maths.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreakSettings {
+    /// Local hour (0–23) at which a new day begins. Resets and logs made
+    /// before this hour count towards the previous day.
+    pub day_starts_at: u32,
+}
+
+/// One row of the `streaks` table. The running streak has no `end_date`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StreakRecord {
+    pub id: i64,
+    pub habit_id: i64,
+    pub start_date: String,
+    pub end_date: Option<String>,
+    pub days: Option<i64>,
+    pub reset_reason: Option<String>,
+}
+
+/// The running streak of an avoid habit, if any.
+pub async fn current(pool: &SqlitePool, habit_id: i64) -> Result<Option<StreakRecord>, String> {
+    sqlx::query_as(
+        "SELECT id, habit_id, start_date, end_date, days, reset_reason FROM streaks
+         WHERE habit_id = ? AND end_date IS NULL ORDER BY id DESC LIMIT 1",
+    )
+    .bind(habit_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load current streak: {}", e))
+}
+
+/// `current`, read through `tx`.
+pub(crate) async fn current_in(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    habit_id: i64,
+) -> Result<Option<StreakRecord>, String> {
+    sqlx::query_as(
+        "SELECT id, habit_id, start_date, end_date, days, reset_reason FROM streaks
+         WHERE habit_id = ? AND end_date IS NULL ORDER BY id DESC LIMIT 1",
+    )
+    .bind(habit_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to load current streak: {}", e))
+}
+
+/// Start a streak now. Does nothing if one is already running.
+pub async fn start(pool: &SqlitePool, habit_id: i64) -> Result<StreakRecord, String> {
+    if let Some(running) = current(pool, habit_id).await? {
+        return Ok(running);
+    }
+
+    let start_date = clock::now().to_rfc3339();
+    sqlx::query("INSERT INTO streaks (habit_id, start_date) VALUES (?, ?)")
+        .bind(habit_id)
+        .bind(&start_date)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to start streak: {}", e))?;
+
+    log::info!("Streak of habit {} started at {}", habit_id, start_date);
+    current(pool, habit_id)
+        .await?
+        .ok_or_else(|| "Streak was not saved".into())
+}
+
+/// End the running streak, recording its length and why it ended.
+/// Returns the completed streak.
+pub async fn reset(
+    pool: &SqlitePool,
+    habit_id: i64,
+    reason: Option<String>,
+) -> Result<StreakRecord, String> {
+    let running = current(pool, habit_id)
+        .await?
+        .ok_or("No streak is running")?;
+    end(pool, running, reason, clock::now()).await
+}
+
+/// End `running` at `now`. Only the default habit marks the day as reset in
+/// the daily log.
+pub(crate) async fn end(
+    pool: &SqlitePool,
+    running: StreakRecord,
+    reason: Option<String>,
+    now: DateTime<Utc>,
+) -> Result<StreakRecord, String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to reset streak: {}", e))?;
+    let ended = end_in(pool, &mut tx, running, reason, now).await?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to reset streak: {}", e))?;
+
+    log::info!(
+        "Streak reset after {} days (reason: {:?})",
+        ended.days.unwrap_or(0),
+        ended.reset_reason
+    );
+    Ok(ended)
+}
+
+/// `end`, writing through `tx` so the caller can commit it together with
+/// its own changes.
+pub(crate) async fn end_in(
+    pool: &SqlitePool,
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    running: StreakRecord,
+    reason: Option<String>,
+    now: DateTime<Utc>,
+) -> Result<StreakRecord, String> {
+    let settings = load_settings(pool).await?;
+    let days = clean_days_of(pool, &running, &settings, now).await? as i64;
+    let reason = reason
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty());
+
+    let ended = sqlx::query(
+        "UPDATE streaks SET end_date = ?, days = ?, reset_reason = ?
+         WHERE id = ? AND end_date IS NULL",
+    )
+    .bind(now.to_rfc3339())
+    .bind(days)
+    .bind(&reason)
+    .bind(running.id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to reset streak: {}", e))?;
+    if ended.rows_affected() == 0 {
+        return Err("The streak has already ended".into());
+    }
+    sqlx::query(
+        "INSERT INTO daily_logs (date, was_reset)
+         SELECT ?, 1 WHERE EXISTS (SELECT 1 FROM habits WHERE id = ? AND is_default = 1)
+         ON CONFLICT(date) DO UPDATE SET was_reset = 1",
+    )
+    .bind(day_of(now, &settings).to_string())
+    .bind(running.habit_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to update daily log: {}", e))?;
+
+    Ok(StreakRecord {
+        end_date: Some(now.to_rfc3339()),
+        days: Some(days),
+        reset_reason: reason,
+        ..running
+    })
+}
+
+/// Completed streaks of an avoid habit, newest first.
+pub async fn history(pool: &SqlitePool, habit_id: i64) -> Result<Vec<StreakRecord>, String> {
+    sqlx::query_as(
+        "SELECT id, habit_id, start_date, end_date, days, reset_reason FROM streaks
+         WHERE habit_id = ? AND end_date IS NOT NULL ORDER BY end_date DESC",
+    )
+    .bind(habit_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load streak history: {}", e))
+}
+
+/// Current streak plus best streak and reset count, both derived from the
+/// stored history.
+pub async fn summary(pool: &SqlitePool, habit_id: i64) -> Result<StreakData, String> {
+    let running = current(pool, habit_id).await?;
+    let settings = load_settings(pool).await?;
+    let (best_completed, total_resets): (i64, i64) = sqlx::query_as(
+        "SELECT COALESCE(MAX(days), 0), COUNT(*) FROM streaks
+         WHERE habit_id = ? AND end_date IS NOT NULL",
+    )
+    .bind(habit_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to load streak history: {}", e))?;
+
+    let (current_days, frozen_days) = match &running {
+        Some(running) => {
+            let now = clock::now();
+            let today = day_of(now, &settings);
+            let frozen = grace::frozen_in_streak(pool, running.id)
+                .await?
+                .into_iter()
+                .filter(|day| *day <= today)
+                .count() as u64;
+            let days = clean_days_of(pool, running, &settings, now).await?;
+            (days, frozen)
+        }
+        None => (0, 0),
+    };
+
+    Ok(StreakData {
+        start_date: running.map(|r| r.start_date),
+        best_streak: current_days.max(best_completed.max(0) as u64),
+        total_resets: total_resets as u64,
+        current_days,
+        frozen_days,
+        freeze_tokens: grace::balance(pool, habit_id).await?,
+        clock_tampered: clock::tampered(),
+    })
+}
+
+pub async fn load_settings(pool: &SqlitePool) -> Result<StreakSettings, String> {
+    let day_starts_at = db::get_setting(pool, KEY_DAY_STARTS_AT)
+        .await?
+        .and_then(|v| v.parse().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(0);
+    Ok(StreakSettings { day_starts_at })
+}
+
+pub async fn save_settings(pool: &SqlitePool, settings: &StreakSettings) -> Result<(), String> {
+    if settings.day_starts_at > 23 {
+        return Err("The day must start at an hour between 0 and 23".into());
+    }
+    db::set_setting(pool, KEY_DAY_STARTS_AT, &settings.day_starts_at.to_string()).await
+}
+
+/// The day it is now for logging purposes, in the local timezone and
+/// honouring the configured day start.
+pub fn today(settings: &StreakSettings) -> NaiveDate {
+    day_of(clock::now(), settings)
+}
+
+/// The day `instant` counts towards for logging purposes.
+pub fn day_of(instant: DateTime<Utc>, settings: &StreakSettings) -> NaiveDate {
+    days::streak_day(instant, &Local, settings.day_starts_at)
+}
+
+/// Whole days `record` has run by `now`.
+fn days_of(record: &StreakRecord, settings: &StreakSettings, now: DateTime<Utc>) -> u64 {
+    let Ok(start) = StreakStart::parse(&record.start_date) else {
+        log::warn!("Streak {} has an unreadable start date", record.id);
+        return 0;
+    };
+    days::days_since(start, now, &Local, settings.day_starts_at)
+}
+
+/// Clean days `record` has run by `now`: its whole days, less the finished
+/// days a freeze token covered.
+pub(crate) async fn clean_days_of(
+    pool: &SqlitePool,
+    record: &StreakRecord,
+    settings: &StreakSettings,
+    now: DateTime<Utc>,
+) -> Result<u64, String> {
+    let today = day_of(now, settings);
+    let frozen = grace::frozen_in_streak(pool, record.id)
+        .await?
+        .into_iter()
+        .filter(|day| *day < today)
+        .count() as u64;
+    Ok(days_of(record, settings, now).saturating_sub(frozen))
+}
+
+/// The instant a streak starting at `start` began. Date-only starts begin
+/// when that day starts.
+pub fn began_at(start: StreakStart, settings: &StreakSettings) -> DateTime<Utc> {
+    match start {
+        StreakStart::At(at) => at,
+        StreakStart::Day(date) => days::day_start(date, &Local, settings.day_starts_at),
+    }
+}
+
+/// The day `record` began on.
+fn start_day(record: &StreakRecord, settings: &StreakSettings) -> Option<NaiveDate> {
+    match StreakStart::parse(&record.start_date).ok()? {
+        StreakStart::At(at) => Some(days::streak_day(at, &Local, settings.day_starts_at)),
+        StreakStart::Day(date) => Some(date),
+    }
+}
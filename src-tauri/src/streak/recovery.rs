@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+use super::{days, StreakRecord, StreakSettings};
+use crate::plugins::blocker::clock;
+
+/// Triggers offered before the user has tagged anything themselves.
+const DEFAULT_TRIGGERS: &[&str] = &[
+    "boredom",
+    "stress",
+    "late night",
+    "alone",
+    "tired",
+    "anxious",
+    "social media",
+];
+
+/// A relapse, or an urge that was resisted. Each kind has its own table and
+/// trigger table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Relapse,
+    Urge,
+}
+
+impl EntryKind {
+    fn table(self) -> &'static str {
+        match self {
+            Self::Relapse => "relapses",
+            Self::Urge => "urges",
+        }
+    }
+
+    fn trigger_table(self) -> &'static str {
+        match self {
+            Self::Relapse => "relapse_triggers",
+            Self::Urge => "urge_triggers",
+        }
+    }
+}
+
+/// A relapse or urge as entered by the user.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewEntry {
+    /// RFC 3339 time it happened. Defaults to now.
+    pub occurred_at: Option<String>,
+    /// How strong it was, 1–10.
+    pub intensity: u8,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub triggers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryEntry {
+    pub id: i64,
+    pub kind: EntryKind,
+    pub occurred_at: String,
+    pub intensity: u8,
+    pub notes: Option<String>,
+    pub triggers: Vec<String>,
+    /// The streak running when it happened. For a relapse, the streak it ended.
+    pub streak_id: Option<i64>,
+}
+
+/// Narrows a list query. Empty fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EntryFilter {
+    /// Entries tagged with any of these triggers.
+    #[serde(default)]
+    pub triggers: Vec<String>,
+    /// Inclusive lower bound: an RFC 3339 time, or a `YYYY-MM-DD` day.
+    pub from: Option<String>,
+    /// Inclusive upper bound: an RFC 3339 time, or a `YYYY-MM-DD` day.
+    pub to: Option<String>,
+}
+
+/// How often a trigger was tagged.
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggerCount {
+    pub tag: String,
+    pub relapses: u64,
+    pub urges: u64,
+}
+
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    id: i64,
+    occurred_at: String,
+    intensity: i64,
+    notes: Option<String>,
+    streak_id: Option<i64>,
+}
+
+/// Record a relapse. A running streak of the default habit that started
+/// before it ends at the time of the relapse, with the notes as the reason.
+pub async fn log_relapse(pool: &SqlitePool, entry: NewEntry) -> Result<RecoveryEntry, String> {
+    let occurred_at = occurred_at(&entry)?;
+    let habit_id = super::habits::default_id(pool).await?;
+    let settings = super::load_settings(pool).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to save entry: {}", e))?;
+    let streak_id = match super::current_in(&mut tx, habit_id).await? {
+        Some(running) if started_before(&running, occurred_at, &settings) => {
+            let id = running.id;
+            super::end_in(pool, &mut tx, running, entry.notes.clone(), occurred_at).await?;
+            Some(id)
+        }
+        _ => None,
+    };
+    let logged = insert_in(&mut tx, EntryKind::Relapse, entry, occurred_at, streak_id).await?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to save entry: {}", e))?;
+
+    log::info!("Relapse logged with triggers {:?}", logged.triggers);
+    Ok(logged)
+}
+
+/// Record an urge that was resisted.
+pub async fn log_urge(pool: &SqlitePool, entry: NewEntry) -> Result<RecoveryEntry, String> {
+    let occurred_at = occurred_at(&entry)?;
+    let habit_id = super::habits::default_id(pool).await?;
+    let settings = super::load_settings(pool).await?;
+    let streak_id = super::current(pool, habit_id)
+        .await?
+        .filter(|running| started_before(running, occurred_at, &settings))
+        .map(|running| running.id);
+    insert(pool, EntryKind::Urge, entry, occurred_at, streak_id).await
+}
+
+/// Entries of `kind` matching `filter`, newest first.
+pub async fn list(
+    pool: &SqlitePool,
+    kind: EntryKind,
+    filter: &EntryFilter,
+) -> Result<Vec<RecoveryEntry>, String> {
+    let settings = super::load_settings(pool).await?;
+    let from = filter
+        .from
+        .as_deref()
+        .map(|b| parse_bound(b, false, settings.day_starts_at))
+        .transpose()?;
+    let to = filter
+        .to
+        .as_deref()
+        .map(|b| parse_bound(b, true, settings.day_starts_at))
+        .transpose()?;
+    let triggers = normalize_triggers(&filter.triggers);
+
+    let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+        "SELECT id, occurred_at, intensity, notes, streak_id FROM {} e WHERE 1 = 1",
+        kind.table()
+    ));
+    if let Some(from) = from {
+        query
+            .push(" AND julianday(e.occurred_at) >= julianday(")
+            .push_bind(from.to_rfc3339())
+            .push(")");
+    }
+    if let Some(to) = to {
+        query
+            .push(" AND julianday(e.occurred_at) <= julianday(")
+            .push_bind(to.to_rfc3339())
+            .push(")");
+    }
+    if !triggers.is_empty() {
+        query.push(format!(
+            " AND EXISTS (SELECT 1 FROM {} t WHERE t.entry_id = e.id AND t.tag IN (",
+            kind.trigger_table()
+        ));
+        let mut tags = query.separated(", ");
+        for tag in &triggers {
+            tags.push_bind(tag.clone());
+        }
+        query.push("))");
+    }
+    query.push(" ORDER BY julianday(e.occurred_at) DESC");
+
+    let rows: Vec<EntryRow> = query
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load {}: {}", kind.table(), e))?;
+
+    let mut tags = load_triggers(pool, kind, rows.iter().map(|r| r.id)).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| RecoveryEntry {
+            triggers: tags.remove(&row.id).unwrap_or_default(),
+            id: row.id,
+            kind,
+            occurred_at: row.occurred_at,
+            intensity: row.intensity.clamp(0, u8::MAX as i64) as u8,
+            notes: row.notes,
+            streak_id: row.streak_id,
+        })
+        .collect())
+}
+
+/// Every trigger in use with its counts, followed by unused defaults.
+pub async fn trigger_counts(pool: &SqlitePool) -> Result<Vec<TriggerCount>, String> {
+    let mut counts: BTreeMap<String, TriggerCount> = BTreeMap::new();
+    for kind in [EntryKind::Relapse, EntryKind::Urge] {
+        let rows: Vec<(String, i64)> = sqlx::query_as(&format!(
+            "SELECT tag, COUNT(*) FROM {} GROUP BY tag",
+            kind.trigger_table()
+        ))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load triggers: {}", e))?;
+
+        for (tag, count) in rows {
+            let entry = counts.entry(tag.clone()).or_insert(TriggerCount {
+                tag,
+                relapses: 0,
+                urges: 0,
+            });
+            match kind {
+                EntryKind::Relapse => entry.relapses = count as u64,
+                EntryKind::Urge => entry.urges = count as u64,
+            }
+        }
+    }
+
+    let mut counts: Vec<TriggerCount> = counts.into_values().collect();
+    counts.sort_by_key(|c| std::cmp::Reverse(c.relapses + c.urges));
+    for tag in DEFAULT_TRIGGERS {
+        if !counts.iter().any(|c| c.tag == *tag) {
+            counts.push(TriggerCount {
+                tag: tag.to_string(),
+                relapses: 0,
+                urges: 0,
+            });
+        }
+    }
+    Ok(counts)
+}
+
+async fn insert(
+    pool: &SqlitePool,
+    kind: EntryKind,
+    entry: NewEntry,
+    occurred_at: DateTime<Utc>,
+    streak_id: Option<i64>,
+) -> Result<RecoveryEntry, String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to save entry: {}", e))?;
+    let logged = insert_in(&mut tx, kind, entry, occurred_at, streak_id).await?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to save entry: {}", e))?;
+
+    log::info!("{:?} logged with triggers {:?}", kind, logged.triggers);
+    Ok(logged)
+}
+
+async fn insert_in(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    kind: EntryKind,
+    entry: NewEntry,
+    occurred_at: DateTime<Utc>,
+    streak_id: Option<i64>,
+) -> Result<RecoveryEntry, String> {
+    let notes = entry
+        .notes
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+    let triggers = normalize_triggers(&entry.triggers);
+    let occurred_at = occurred_at.to_rfc3339();
+
+    let id = sqlx::query(&format!(
+        "INSERT INTO {} (occurred_at, intensity, notes, streak_id) VALUES (?, ?, ?, ?)",
+        kind.table()
+    ))
+    .bind(&occurred_at)
+    .bind(entry.intensity as i64)
+    .bind(&notes)
+    .bind(streak_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to save entry: {}", e))?
+    .last_insert_rowid();
+
+    for tag in &triggers {
+        sqlx::query(&format!(
+            "INSERT INTO {} (entry_id, tag) VALUES (?, ?)",
+            kind.trigger_table()
+        ))
+        .bind(id)
+        .bind(tag)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Failed to save triggers: {}", e))?;
+    }
+
+    Ok(RecoveryEntry {
+        id,
+        kind,
+        occurred_at,
+        intensity: entry.intensity,
+        notes,
+        triggers,
+        streak_id,
+    })
+}
+
+async fn load_triggers(
+    pool: &SqlitePool,
+    kind: EntryKind,
+    ids: impl Iterator<Item = i64>,
+) -> Result<HashMap<i64, Vec<String>>, String> {
+    let ids: Vec<i64> = ids.collect();
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    if ids.is_empty() {
+        return Ok(tags);
+    }
+
+    let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+        "SELECT entry_id, tag FROM {} WHERE entry_id IN (",
+        kind.trigger_table()
+    ));
+    let mut list = query.separated(", ");
+    for id in ids {
+        list.push_bind(id);
+    }
+    query.push(") ORDER BY tag");
+
+    let rows: Vec<(i64, String)> = query
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load triggers: {}", e))?;
+    for (id, tag) in rows {
+        tags.entry(id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Validate `entry` and resolve when it happened.
+fn occurred_at(entry: &NewEntry) -> Result<DateTime<Utc>, String> {
+    if !(1..=10).contains(&entry.intensity) {
+        return Err("Intensity must be between 1 and 10".into());
+    }
+    let Some(at) = &entry.occurred_at else {
+        return Ok(clock::now());
+    };
+    let at = DateTime::parse_from_rfc3339(at)
+        .map_err(|_| format!("Could not parse time: {}", at))?
+        .with_timezone(&Utc);
+    if at > clock::now() {
+        return Err("Entries can't be logged in the future".into());
+    }
+    Ok(at)
+}
+
+/// Whether `running` began by `at`. Date-only starts begin at the configured
+/// day start.
+fn started_before(running: &StreakRecord, at: DateTime<Utc>, settings: &StreakSettings) -> bool {
+    match days::StreakStart::parse(&running.start_date) {
+        Ok(start) => super::began_at(start, settings) <= at,
+        Err(_) => true,
+    }
+}
+
+/// A filter bound as an instant. Whole days run from the configured day
+/// start; an upper bound day includes all of that day.
+fn parse_bound(bound: &str, upper: bool, day_starts_at: u32) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(bound) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(bound, "%Y-%m-%d")
+        .map_err(|_| format!("Could not parse date: {}", bound))?;
+    if upper {
+        let next = date.succ_opt().ok_or("Date out of range")?;
+        Ok(days::day_start(next, &Local, day_starts_at) - chrono::Duration::nanoseconds(1))
+    } else {
+        Ok(days::day_start(date, &Local, day_starts_at))
+    }
+}
+
+/// Lowercase, trim and collapse whitespace, then dedupe.
+fn normalize_triggers(triggers: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = triggers
+        .iter()
+        .map(|t| {
+            t.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        })
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, Datelike, Duration, Local, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::days;
+use crate::db;
+use crate::plugins::blocker::clock;
+
+const KEY_RISK_ALERTS: &str = "risk_alerts";
+
+const HOURS_PER_WEEK: usize = 7 * 24;
+
+/// A slot needs at least this many times the average events per slot to
+/// count as high-risk, on top of `min_events`.
+const PEAK_FACTOR: f64 = 2.0;
+
+/// Alerts before high-risk windows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskSettings {
+    pub enabled: bool,
+    /// How long before a window starts the notification is sent.
+    pub lead_minutes: u32,
+    /// Also keep the blocklist locked until the window ends.
+    pub extend_lock: bool,
+    /// Fewest resets and urges an hour of the week needs to be high-risk.
+    pub min_events: u32,
+    /// Only events from this many recent days are analysed.
+    pub lookback_days: u32,
+}
+
+impl Default for RiskSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lead_minutes: 30,
+            extend_lock: false,
+            min_events: 3,
+            lookback_days: 180,
+        }
+    }
+}
+
+/// When resets and urges happen, by local day of week and hour.
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskProfile {
+    pub resets: u64,
+    pub urges: u64,
+    /// Events per hour, `grid[weekday][hour]`, Monday first.
+    pub grid: Vec<Vec<u32>>,
+    /// Events per slot a high-risk hour needs.
+    pub threshold: u32,
+    /// High-risk windows, soonest first.
+    pub windows: Vec<RiskWindow>,
+}
+
+/// A run of consecutive high-risk hours. Can cross midnight.
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskWindow {
+    /// 0 = Monday … 6 = Sunday.
+    pub weekday: u32,
+    pub start_hour: u32,
+    pub hours: u32,
+    /// Resets and urges that fell into the window.
+    pub events: u32,
+    /// RFC 3339 time the window next begins.
+    pub next_start: String,
+    /// RFC 3339 time that occurrence ends.
+    pub next_end: String,
+}
+
+pub async fn load_settings(pool: &SqlitePool) -> Result<RiskSettings, String> {
+    Ok(db::get_setting(pool, KEY_RISK_ALERTS)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default())
+}
+
+pub async fn save_settings(pool: &SqlitePool, settings: &RiskSettings) -> Result<(), String> {
+    if settings.lead_minutes > 24 * 60 {
+        return Err("Alerts can be sent at most a day ahead".into());
+    }
+    if settings.min_events == 0 {
+        return Err("A high-risk hour needs at least one event".into());
+    }
+    if settings.lookback_days == 0 {
+        return Err("The analysis needs at least one day of history".into());
+    }
+    let value = serde_json::to_string(settings)
+        .map_err(|e| format!("Failed to serialize risk settings: {}", e))?;
+    db::set_setting(pool, KEY_RISK_ALERTS, &value).await
+}
+
+/// Bucket the resets and urges of the last `lookback_days` by local weekday
+/// and hour, and merge the busiest hours into windows.
+pub async fn profile(pool: &SqlitePool, settings: &RiskSettings) -> Result<RiskProfile, String> {
+    let now = clock::now();
+    let since = (now - Duration::days(settings.lookback_days as i64)).to_rfc3339();
+
+    let resets: Vec<(String,)> = sqlx::query_as(
+        "SELECT end_date FROM streaks
+         WHERE habit_id = ? AND end_date IS NOT NULL AND julianday(end_date) >= julianday(?)",
+    )
+    .bind(super::habits::default_id(pool).await?)
+    .bind(&since)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load streak history: {}", e))?;
+    let urges: Vec<(String,)> = sqlx::query_as(
+        "SELECT occurred_at FROM urges WHERE julianday(occurred_at) >= julianday(?)",
+    )
+    .bind(&since)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load urges: {}", e))?;
+
+    let mut slots = [0u32; HOURS_PER_WEEK];
+    for (at,) in resets.iter().chain(&urges) {
+        let Ok(at) = DateTime::parse_from_rfc3339(at) else {
+            continue;
+        };
+        let local = at.with_timezone(&Local);
+        slots[slot(local.weekday().num_days_from_monday(), local.hour())] += 1;
+    }
+
+    let threshold = threshold(resets.len() + urges.len(), settings.min_events);
+
+    let mut windows: Vec<(DateTime<Utc>, RiskWindow)> = windows(&slots, threshold)
+        .into_iter()
+        .map(|(start, hours)| {
+            let (weekday, start_hour) = ((start / 24) as u32, (start % 24) as u32);
+            let next_start = next_occurrence(weekday, start_hour, now);
+            let window = RiskWindow {
+                weekday,
+                start_hour,
+                hours: hours as u32,
+                events: (start..start + hours)
+                    .map(|i| slots[i % HOURS_PER_WEEK])
+                    .sum(),
+                next_start: next_start.to_rfc3339(),
+                next_end: (next_start + Duration::hours(hours as i64)).to_rfc3339(),
+            };
+            (next_start, window)
+        })
+        .collect();
+    windows.sort_by_key(|(next_start, _)| *next_start);
+
+    Ok(RiskProfile {
+        resets: resets.len() as u64,
+        urges: urges.len() as u64,
+        grid: slots.chunks(24).map(|day| day.to_vec()).collect(),
+        threshold,
+        windows: windows.into_iter().map(|(_, window)| window).collect(),
+    })
+}
+
+/// Events a slot needs to be high-risk, out of `total` in the week.
+fn threshold(total: usize, min_events: u32) -> u32 {
+    let average = total as f64 / HOURS_PER_WEEK as f64;
+    min_events.max((average * PEAK_FACTOR).ceil() as u32)
+}
+
+fn slot(weekday: u32, hour: u32) -> usize {
+    weekday as usize * 24 + hour as usize
+}
+
+/// Runs of slots at or above `threshold` as `(first slot, length)`. The week
+/// wraps, so Sunday night runs on into Monday morning.
+fn windows(slots: &[u32; HOURS_PER_WEEK], threshold: u32) -> Vec<(usize, usize)> {
+    let high = |i: usize| slots[i % HOURS_PER_WEEK] >= threshold;
+    let Some(quiet) = (0..HOURS_PER_WEEK).find(|i| !high(*i)) else {
+        return vec![(0, HOURS_PER_WEEK)];
+    };
+
+    let mut runs = Vec::new();
+    let mut run: Option<(usize, usize)> = None;
+    for offset in 1..=HOURS_PER_WEEK {
+        let i = (quiet + offset) % HOURS_PER_WEEK;
+        if high(i) {
+            run = Some(run.map_or((i, 1), |(start, len)| (start, len + 1)));
+        } else if let Some(done) = run.take() {
+            runs.push(done);
+        }
+    }
+    runs
+}
+
+/// The next time it is `hour` o'clock on `weekday` locally, after `now`.
+fn next_occurrence(weekday: u32, hour: u32, now: DateTime<Utc>) -> DateTime<Utc> {
+    let today = now.with_timezone(&Local).date_naive();
+    let ahead = (weekday + 7 - today.weekday().num_days_from_monday()) % 7;
+    let date = today + Duration::days(ahead as i64);
+    let start = days::day_start(date, &Local, hour);
+    if start > now {
+        start
+    } else {
+        days::day_start(date + Duration::days(7), &Local, hour)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn week(events: &[(u32, u32, u32)]) -> [u32; HOURS_PER_WEEK] {
+        let mut slots = [0; HOURS_PER_WEEK];
+        for (weekday, hour, count) in events {
+            slots[slot(*weekday, *hour)] = *count;
+        }
+        slots
+    }
+
+    #[test]
+    fn threshold_is_twice_the_average_or_the_minimum() {
+        assert_eq!(threshold(0, 3), 3);
+        // 168 events average one per slot
+        assert_eq!(threshold(HOURS_PER_WEEK, 1), 2);
+        assert_eq!(threshold(HOURS_PER_WEEK, 5), 5);
+        assert_eq!(threshold(HOURS_PER_WEEK * 3 / 2, 1), 3);
+        // Rounded up
+        assert_eq!(threshold(HOURS_PER_WEEK + 1, 1), 3);
+    }
+
+    #[test]
+    fn adjacent_hours_merge_into_one_window() {
+        let slots = week(&[(2, 18, 3), (2, 19, 4), (2, 20, 3), (2, 22, 5), (4, 9, 2)]);
+        assert_eq!(windows(&slots, 3), [(slot(2, 18), 3), (slot(2, 22), 1)]);
+    }
+
+    #[test]
+    fn windows_wrap_from_sunday_into_monday() {
+        let slots = week(&[(6, 22, 3), (6, 23, 3), (0, 0, 3), (0, 1, 3), (3, 12, 3)]);
+        assert_eq!(windows(&slots, 3), [(slot(3, 12), 1), (slot(6, 22), 4)]);
+    }
+
+    #[test]
+    fn a_week_without_quiet_hours_is_one_window() {
+        assert_eq!(windows(&[2; HOURS_PER_WEEK], 2), [(0, HOURS_PER_WEEK)]);
+        assert!(windows(&[1; HOURS_PER_WEEK], 2).is_empty());
+    }
+}
@@ -3,6 +3,8 @@ import { RouterProvider } from "react-router";
 import { router } from "./router";
 import { useAppStore } from "./store/appStore";
 import { applyTheme } from "./lib/theme";
+import { blocklists } from "./lib/blocklists";
+import { syncBlockCatalog } from "./lib/androidBlocker";
 
 export default function App() {
   const theme = useAppStore((s) => s.theme);
@@ -21,5 +23,15 @@ export default function App() {
     }
   }, []);
 
+  // Give the backend the category lists it blocks on panic escalation
+  useEffect(() => {
+    const catalog = Object.fromEntries(
+      Object.entries(blocklists).map(([id, list]) => [id, list.domains]),
+    );
+    syncBlockCatalog(catalog).catch((e) =>
+      console.warn("Failed to sync block catalog:", e),
+    );
+  }, []);
+
   return <RouterProvider router={router} />;
 }
@@ -1,4 +1,5 @@
 import { useState, useEffect, useCallback, useRef } from "react";
+import { listen } from "@tauri-apps/api/event";
 import { Card } from "../shared/Card";
 import { Button } from "../shared/Button";
 import { useBlockerStore } from "../../store/blockerStore";
@@ -8,8 +9,11 @@ import {
   stopAppBlocker,
   removeBlocklist,
   extendLockNative,
+  getClockStatus,
+  getLockStatus,
   saveLockExpiryNative,
 } from "../../lib/androidBlocker";
+import type { LockStatus as LockStatusPayload } from "../../types";
 
 interface TimeRemaining {
   hours: number;
@@ -18,10 +22,14 @@ interface TimeRemaining {
   expired: boolean;
 }
 
-function calcTimeRemaining(expiresAt: string | null): TimeRemaining {
+/** `clockOffsetMs` is how far the wall clock runs ahead of credited time. */
+function calcTimeRemaining(
+  expiresAt: string | null,
+  clockOffsetMs: number,
+): TimeRemaining {
   if (!expiresAt) return { hours: 0, minutes: 0, seconds: 0, expired: true };
 
-  const now = Date.now();
+  const now = Date.now() - clockOffsetMs;
   const target = new Date(expiresAt).getTime();
   const diff = target - now;
 
@@ -52,13 +60,14 @@ export function LockStatus() {
     setAppBlockerActive,
   } = useBlockerStore();
 
+  const [clockOffsetMs, setClockOffsetMs] = useState(0);
   const [remaining, setRemaining] = useState<TimeRemaining>(() =>
-    calcTimeRemaining(lockExpiresAt),
+    calcTimeRemaining(lockExpiresAt, clockOffsetMs),
   );
   const hasExpiredRef = useRef(false);
 
   const updateTimer = useCallback(() => {
-    const r = calcTimeRemaining(lockExpiresAt);
+    const r = calcTimeRemaining(lockExpiresAt, clockOffsetMs);
     setRemaining(r);
 
     // Handle expiry — stop services
@@ -66,7 +75,26 @@ export function LockStatus() {
       hasExpiredRef.current = true;
       handleExpiry();
     }
-  }, [lockExpiresAt]); // eslint-disable-line react-hooks/exhaustive-deps
+  }, [lockExpiresAt, clockOffsetMs]); // eslint-disable-line react-hooks/exhaustive-deps
+
+  // Lock expiries are in credited time, which leaves out clock jumps
+  useEffect(() => {
+    getClockStatus()
+      .then((clock) => setClockOffsetMs(clock.offsetSeconds * 1000))
+      .catch((err) => console.error("Error loading clock status:", err));
+  }, []);
+
+  // Locks the backend applies on its own, e.g. through a risk window
+  useEffect(() => {
+    const unlisten = listen<LockStatusPayload>("lock-applied", (event) => {
+      if (event.payload.scope === "blocklist") {
+        setLockStatus(true, event.payload.expiresAt);
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [setLockStatus]);
 
   useEffect(() => {
     hasExpiredRef.current = false;
@@ -75,7 +103,33 @@ export function LockStatus() {
     return () => clearInterval(interval);
   }, [updateTimer]);
 
+  function checkAgainLater() {
+    setTimeout(() => {
+      hasExpiredRef.current = false;
+    }, 30_000);
+  }
+
   async function handleExpiry() {
+    // Only tear down once the backend agrees the lock is over; the clock
+    // may have been set forward since the offset was loaded.
+    try {
+      const [locks, clock] = await Promise.all([
+        getLockStatus(),
+        getClockStatus(),
+      ]);
+      const blocklist = locks.find((lock) => lock.scope === "blocklist");
+      if (blocklist?.isLocked) {
+        setClockOffsetMs(clock.offsetSeconds * 1000);
+        setLockStatus(true, blocklist.expiresAt);
+        checkAgainLater();
+        return;
+      }
+    } catch (err) {
+      console.error("Error checking lock status on expiry:", err);
+      checkAgainLater();
+      return;
+    }
+
     try {
       if (android) {
         await stopVpnBlocker();
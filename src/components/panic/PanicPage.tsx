@@ -1,9 +1,11 @@
+import { useEffect } from "react";
 import { useNavigate } from "react-router";
 import { motion } from "framer-motion";
 import { X } from "lucide-react";
 import { BreathingExercise } from "./BreathingExercise";
 import { GroundingExercise } from "./GroundingExercise";
 import { ProductiveActions } from "./ProductiveActions";
+import { pressPanicButton } from "../../lib/androidBlocker";
 
 /** Stagger animation variants shared by all sections. */
 const stagger = {
@@ -27,6 +29,17 @@ const REASONS: string[] = [
 export function PanicPage() {
   const navigate = useNavigate();
 
+  // Log the press and let the backend tighten blocking if configured
+  useEffect(() => {
+    pressPanicButton()
+      .then((result) => {
+        if (result.escalation_error) {
+          console.warn("Panic escalation failed:", result.escalation_error);
+        }
+      })
+      .catch((e) => console.warn("Failed to log panic event:", e));
+  }, []);
+
   return (
     <div className="relative flex flex-col gap-8 max-w-xl mx-auto pb-12">
       {/* ---- Close button ---- */}
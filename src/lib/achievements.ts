@@ -1,5 +1,8 @@
 // Achievement definitions for FunTime productivity app
 
+import { invoke } from '@tauri-apps/api/core';
+import type { AchievementStatus, RecomputeDiff } from '../types';
+
 export interface Achievement {
   id: string;
   name: string;
@@ -272,3 +275,20 @@ export const achievements: Achievement[] = [
     progress: 0,
   },
 ];
+
+/**
+ * Progress saved by the backend achievement engine, which unlocks
+ * achievements and emits `achievement-unlocked` as events come in.
+ */
+export async function getAchievementProgress(): Promise<AchievementStatus[]> {
+  return invoke('get_achievements');
+}
+
+/**
+ * Replay the whole event and streak history through the achievement rules.
+ * Call without `confirm` to preview the diff, then with the preview's
+ * `fingerprint` once confirmed. Saving fails if the changes moved meanwhile.
+ */
+export async function recomputeAchievements(confirm?: string): Promise<RecomputeDiff> {
+  return invoke('recompute_achievements', { confirm: confirm ?? null });
+}
@@ -1,5 +1,22 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { InstalledApp, VpnStatus } from "../types";
+import type {
+  ApplyResult,
+  BudgetSettings,
+  BudgetStatus,
+  ClockStatus,
+  EscalationStatus,
+  InstalledApp,
+  LockdownStatus,
+  LockScope,
+  LockStatus,
+  PanicEscalationSettings,
+  PanicResult,
+  ResetLockSettings,
+  ResetLockStatus,
+  SafeSearchEndpoint,
+  SafeSearchStatus,
+  VpnStatus,
+} from "../types";
 
 const PLUGIN = "plugin:tamashii-blocker|";
 
@@ -59,12 +76,12 @@ export async function saveLockExpiryNative(expiry: string): Promise<void> {
 
 // ─── Desktop commands ─────────────────────────────────────────────────────
 
-export async function applyBlocklist(domains: string[]): Promise<void> {
-  await invoke(`${PLUGIN}apply_blocklist`, { domains });
+export async function applyBlocklist(domains: string[]): Promise<ApplyResult> {
+  return invoke(`${PLUGIN}apply_blocklist`, { domains });
 }
 
-export async function removeBlocklist(): Promise<void> {
-  await invoke(`${PLUGIN}remove_blocklist`);
+export async function removeBlocklist(): Promise<ApplyResult> {
+  return invoke(`${PLUGIN}remove_blocklist`);
 }
 
 export async function getBlockerStatus() {
@@ -75,6 +92,109 @@ export async function checkAdmin(): Promise<boolean> {
   return invoke(`${PLUGIN}check_admin`);
 }
 
-export async function extendLockNative(hours: number): Promise<string> {
-  return invoke(`${PLUGIN}extend_lock`, { hours });
+export async function extendLockNative(
+  hours: number,
+  scope: LockScope = "blocklist",
+): Promise<string> {
+  return invoke(`${PLUGIN}extend_lock`, { hours, scope });
+}
+
+export async function getLockStatus(): Promise<LockStatus[]> {
+  return invoke(`${PLUGIN}get_lock_status`);
+}
+
+/** Clock jumps detected against uptime. Locks and streaks ignore them. */
+export async function getClockStatus(): Promise<ClockStatus> {
+  return invoke(`${PLUGIN}get_clock_status`);
+}
+
+// ─── SafeSearch ───────────────────────────────────────────────────────────
+
+export async function setSafeSearch(enabled: boolean): Promise<SafeSearchStatus> {
+  return invoke(`${PLUGIN}set_safe_search`, { enabled });
+}
+
+export async function getSafeSearchEndpoints(): Promise<SafeSearchEndpoint[]> {
+  return invoke(`${PLUGIN}get_safe_search_endpoints`);
+}
+
+export async function updateSafeSearchEndpoints(
+  endpoints: SafeSearchEndpoint[],
+): Promise<SafeSearchStatus> {
+  return invoke(`${PLUGIN}update_safe_search_endpoints`, { endpoints });
+}
+
+// ─── Lockdown mode (desktop) ──────────────────────────────────────────────
+
+export async function startLockdown(
+  allowlist: string[],
+  hours: number,
+): Promise<LockdownStatus> {
+  return invoke(`${PLUGIN}start_lockdown`, { allowlist, hours });
+}
+
+export async function stopLockdown(): Promise<void> {
+  await invoke(`${PLUGIN}stop_lockdown`);
+}
+
+export async function getLockdownStatus(): Promise<LockdownStatus> {
+  return invoke(`${PLUGIN}get_lockdown_status`);
+}
+
+// ─── Time budgets (desktop) ───────────────────────────────────────────────
+
+export async function getBudgetSettings(): Promise<BudgetSettings> {
+  return invoke(`${PLUGIN}get_budget_settings`);
+}
+
+export async function setBudgetSettings(
+  settings: BudgetSettings,
+): Promise<BudgetStatus[]> {
+  return invoke(`${PLUGIN}set_budget_settings`, { settings });
+}
+
+export async function getBudgetStatus(): Promise<BudgetStatus[]> {
+  return invoke(`${PLUGIN}get_budget_status`);
+}
+
+// ─── Category catalog & panic escalation ──────────────────────────────────
+
+export async function syncBlockCatalog(
+  categories: Record<string, string[]>,
+): Promise<void> {
+  await invoke(`${PLUGIN}sync_block_catalog`, { categories });
+}
+
+export async function getPanicEscalationSettings(): Promise<PanicEscalationSettings> {
+  return invoke(`${PLUGIN}get_panic_escalation_settings`);
+}
+
+export async function setPanicEscalationSettings(
+  settings: PanicEscalationSettings,
+): Promise<void> {
+  await invoke(`${PLUGIN}set_panic_escalation_settings`, { settings });
+}
+
+export async function getEscalationStatus(): Promise<EscalationStatus | null> {
+  return invoke(`${PLUGIN}get_escalation_status`);
+}
+
+// ─── Protective lock after a streak reset ─────────────────────────────────
+
+export async function getResetLockSettings(): Promise<ResetLockSettings> {
+  return invoke(`${PLUGIN}get_reset_lock_settings`);
+}
+
+/** Fails while any lock is active. */
+export async function setResetLockSettings(settings: ResetLockSettings): Promise<void> {
+  await invoke(`${PLUGIN}set_reset_lock_settings`, { settings });
+}
+
+export async function getResetLockStatus(): Promise<ResetLockStatus | null> {
+  return invoke(`${PLUGIN}get_reset_lock_status`);
+}
+
+/** Log a panic button press and apply the configured escalation. */
+export async function pressPanicButton(): Promise<PanicResult> {
+  return invoke("use_panic_button");
 }
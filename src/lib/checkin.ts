@@ -0,0 +1,27 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { CheckIn, CheckInInput, CheckInSettings } from "../types";
+
+// The evening check-in. The backend sends the prompt at the configured time
+// and fills in the daily log from recorded events once each day ends.
+
+/** The check-in for a day (YYYY-MM-DD), today by default. */
+export async function getCheckIn(date?: string): Promise<CheckIn | null> {
+  return invoke("get_check_in", { date: date ?? null });
+}
+
+export async function saveCheckIn(checkIn: CheckInInput): Promise<CheckIn> {
+  return invoke("save_check_in", { checkIn });
+}
+
+/** Check-ins between two days, both inclusive, newest first. */
+export async function listCheckIns(from?: string, to?: string): Promise<CheckIn[]> {
+  return invoke("list_check_ins", { from: from ?? null, to: to ?? null });
+}
+
+export async function getCheckInSettings(): Promise<CheckInSettings> {
+  return invoke("get_check_in_settings");
+}
+
+export async function setCheckInSettings(settings: CheckInSettings): Promise<CheckInSettings> {
+  return invoke("set_check_in_settings", { settings });
+}
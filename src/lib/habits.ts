@@ -0,0 +1,61 @@
+import { invoke } from "@tauri-apps/api/core";
+import type {
+  FreezeEntry,
+  GraceSettings,
+  HabitChanges,
+  HabitRun,
+  HabitStatus,
+  NewHabit,
+} from "../types";
+
+// Habits beyond the main streak. The streak commands act on the default habit.
+
+export async function listHabits(): Promise<HabitStatus[]> {
+  return invoke("list_habits");
+}
+
+export async function createHabit(habit: NewHabit): Promise<HabitStatus> {
+  return invoke("create_habit", { habit });
+}
+
+export async function updateHabit(id: number, changes: HabitChanges): Promise<HabitStatus> {
+  return invoke("update_habit", { id, changes });
+}
+
+export async function archiveHabit(id: number): Promise<void> {
+  await invoke("archive_habit", { id });
+}
+
+/** Mark a do habit as done today, or on `date` (YYYY-MM-DD). */
+export async function checkInHabit(id: number, date?: string): Promise<HabitStatus> {
+  return invoke("check_in_habit", { id, date: date ?? null });
+}
+
+export async function resetHabit(id: number, reason?: string): Promise<HabitStatus> {
+  return invoke("reset_habit", { id, reason: reason ?? null });
+}
+
+export async function startHabit(id: number): Promise<HabitStatus> {
+  return invoke("start_habit", { id });
+}
+
+export async function getHabitHistory(id: number): Promise<HabitRun[]> {
+  return invoke("get_habit_history", { id });
+}
+
+/** Spend a freeze token on `day` (YYYY-MM-DD, default today). */
+export async function spendStreakFreeze(id: number, day?: string, note?: string): Promise<HabitStatus> {
+  return invoke("use_streak_freeze", { id, day: day ?? null, note: note ?? null });
+}
+
+export async function getFreezeHistory(id: number): Promise<FreezeEntry[]> {
+  return invoke("get_freeze_history", { id });
+}
+
+export async function getGraceSettings(): Promise<GraceSettings> {
+  return invoke("get_grace_settings");
+}
+
+export async function setGraceSettings(settings: GraceSettings): Promise<void> {
+  await invoke("set_grace_settings", { settings });
+}
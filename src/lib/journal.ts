@@ -0,0 +1,150 @@
+import { invoke } from "@tauri-apps/api/core";
+import type {
+  JournalEntry,
+  JournalExportFilter,
+  JournalExportFormat,
+  JournalExportSummary,
+  JournalFieldAggregateQuery,
+  JournalFieldBucket,
+  JournalImportSummary,
+  JournalInput,
+  JournalLockStatus,
+  JournalRevision,
+  JournalRevisionDiff,
+  JournalSearchHit,
+  JournalSearchQuery,
+  JournalTemplate,
+  MoodAnalytics,
+  MoodAnalyticsQuery,
+} from "../types";
+
+// Journal entries are saved by the backend, which also counts them towards
+// achievements. While the journal is encrypted, entries can only be read or
+// written unlocked; the backend emits `journal-locked` when it locks after
+// the idle timeout.
+
+export async function listJournalEntries(): Promise<JournalEntry[]> {
+  return invoke("list_journal_entries");
+}
+
+export async function createJournalEntry(entry: JournalInput): Promise<JournalEntry> {
+  return invoke("create_journal_entry", { entry });
+}
+
+export async function updateJournalEntry(id: number, entry: JournalInput): Promise<JournalEntry> {
+  return invoke("update_journal_entry", { id, entry });
+}
+
+export async function deleteJournalEntry(id: number): Promise<void> {
+  await invoke("delete_journal_entry", { id });
+}
+
+/** Earlier versions of an entry, newest first. Every update keeps one. */
+export async function listJournalRevisions(entryId: number): Promise<JournalRevision[]> {
+  return invoke("list_journal_revisions", { entryId });
+}
+
+/** Diff two versions of an entry; leave a revision id out for the entry as it is now. */
+export async function diffJournalRevisions(
+  entryId: number,
+  from: number | null,
+  to: number | null = null,
+): Promise<JournalRevisionDiff> {
+  return invoke("diff_journal_revisions", { entryId, from, to });
+}
+
+export async function restoreJournalRevision(
+  entryId: number,
+  revisionId: number,
+): Promise<JournalEntry> {
+  return invoke("restore_journal_revision", { entryId, revisionId });
+}
+
+export async function getJournalRevisionLimit(): Promise<number> {
+  return invoke("get_journal_revision_limit");
+}
+
+/** Revisions kept per entry, 0 to 500; 0 turns history off. */
+export async function setJournalRevisionLimit(limit: number): Promise<number> {
+  return invoke("set_journal_revision_limit", { limit });
+}
+
+/** Built-in templates first, then the user's. */
+export async function listJournalTemplates(): Promise<JournalTemplate[]> {
+  return invoke("list_journal_templates");
+}
+
+/** Create a template or replace one of the user's; the backend validates it. */
+export async function saveJournalTemplate(
+  template: Omit<JournalTemplate, "builtin">,
+): Promise<JournalTemplate> {
+  return invoke("save_journal_template", { template });
+}
+
+export async function deleteJournalTemplate(id: string): Promise<void> {
+  await invoke("delete_journal_template", { id });
+}
+
+/** Answers to one template field bucketed by period, e.g. average intensity per month. */
+export async function aggregateJournalField(
+  query: JournalFieldAggregateQuery,
+): Promise<JournalFieldBucket[]> {
+  return invoke("aggregate_journal_field", { query });
+}
+
+/** Mood over time and around resets, urges and relapses, for the stats page. */
+export async function getMoodAnalytics(query: MoodAnalyticsQuery = {}): Promise<MoodAnalytics> {
+  return invoke("get_mood_analytics", { query });
+}
+
+export async function searchJournal(query: JournalSearchQuery): Promise<JournalSearchHit[]> {
+  return invoke("search_journal", { query });
+}
+
+/**
+ * Export the entries `filter` picks, every entry without one; `destination`
+ * is a folder for Markdown, a file otherwise.
+ */
+export async function exportJournal(
+  format: JournalExportFormat,
+  destination: string,
+  filter: JournalExportFilter = {},
+): Promise<JournalExportSummary> {
+  return invoke("export_journal", { format, destination, filter });
+}
+
+/** Import Markdown entries from a .md file, a folder or a .zip archive. */
+export async function importJournal(source: string): Promise<JournalImportSummary> {
+  return invoke("import_journal", { source });
+}
+
+export async function getJournalLockStatus(): Promise<JournalLockStatus> {
+  return invoke("get_journal_lock_status");
+}
+
+export async function enableJournalEncryption(passphrase: string): Promise<JournalLockStatus> {
+  return invoke("enable_journal_encryption", { passphrase });
+}
+
+export async function disableJournalEncryption(passphrase: string): Promise<JournalLockStatus> {
+  return invoke("disable_journal_encryption", { passphrase });
+}
+
+export async function unlockJournal(passphrase: string): Promise<JournalLockStatus> {
+  return invoke("unlock_journal", { passphrase });
+}
+
+export async function lockJournal(): Promise<JournalLockStatus> {
+  return invoke("lock_journal");
+}
+
+export async function changeJournalPassphrase(
+  oldPassphrase: string,
+  newPassphrase: string,
+): Promise<JournalLockStatus> {
+  return invoke("change_journal_passphrase", { oldPassphrase, newPassphrase });
+}
+
+export async function setJournalLockTimeout(minutes: number): Promise<JournalLockStatus> {
+  return invoke("set_journal_lock_timeout", { minutes });
+}
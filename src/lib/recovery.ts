@@ -0,0 +1,29 @@
+import { invoke } from "@tauri-apps/api/core";
+import type {
+  RecoveryEntry,
+  RecoveryEntryFilter,
+  RecoveryEntryInput,
+  TriggerCount,
+} from "../types";
+
+// Relapse and urge logging with trigger tags.
+
+export async function logRelapse(entry: RecoveryEntryInput): Promise<RecoveryEntry> {
+  return invoke("log_relapse", { entry });
+}
+
+export async function logUrge(entry: RecoveryEntryInput): Promise<RecoveryEntry> {
+  return invoke("log_urge", { entry });
+}
+
+export async function getRelapses(filter?: RecoveryEntryFilter): Promise<RecoveryEntry[]> {
+  return invoke("get_relapses", { filter: filter ?? null });
+}
+
+export async function getUrges(filter?: RecoveryEntryFilter): Promise<RecoveryEntry[]> {
+  return invoke("get_urges", { filter: filter ?? null });
+}
+
+export async function getTriggerTags(): Promise<TriggerCount[]> {
+  return invoke("get_trigger_tags");
+}
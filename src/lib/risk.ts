@@ -0,0 +1,16 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { RiskProfile, RiskSettings } from "../types";
+
+// High-risk time detection and the alerts sent before each window.
+
+export async function getRiskProfile(): Promise<RiskProfile> {
+  return invoke("get_risk_profile");
+}
+
+export async function getRiskSettings(): Promise<RiskSettings> {
+  return invoke("get_risk_settings");
+}
+
+export async function setRiskSettings(settings: RiskSettings): Promise<RiskProfile> {
+  return invoke("set_risk_settings", { settings });
+}
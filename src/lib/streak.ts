@@ -0,0 +1,45 @@
+import { invoke } from "@tauri-apps/api/core";
+import type {
+  StreakAnalytics,
+  StreakMilestone,
+  StreakRecord,
+  StreakSettings,
+  StreakSummary,
+} from "../types";
+
+// Streak state lives in the backend; these wrap its commands.
+
+export async function getStreakData(): Promise<StreakSummary> {
+  return invoke("get_streak_data");
+}
+
+export async function startStreak(): Promise<StreakSummary> {
+  return invoke("start_streak");
+}
+
+export async function resetStreak(reason?: string): Promise<StreakSummary> {
+  return invoke("reset_streak", { reason: reason ?? null });
+}
+
+export async function getStreakHistory(): Promise<StreakRecord[]> {
+  return invoke("get_streak_history");
+}
+
+export async function getStreakAnalytics(trendLength?: number): Promise<StreakAnalytics> {
+  return invoke("get_streak_analytics", { trendLength: trendLength ?? null });
+}
+
+/** When the running streak reaches each milestone; the backend notifies then. */
+export async function getStreakMilestones(): Promise<StreakMilestone[]> {
+  return invoke("get_streak_milestones");
+}
+
+export async function getStreakSettings(): Promise<StreakSettings> {
+  return invoke("get_streak_settings");
+}
+
+export async function setStreakSettings(
+  settings: StreakSettings,
+): Promise<StreakSummary> {
+  return invoke("set_streak_settings", { settings });
+}
@@ -16,6 +16,198 @@ export interface StreakData {
   totalResets: number;
 }
 
+/** Streak data as computed by the backend from the `streaks` table. */
+export interface StreakSummary {
+  start_date: string | null; // ISO datetime
+  best_streak: number;
+  total_resets: number;
+  current_days: number; // clean days; frozen days are left out
+  frozen_days: number;
+  freeze_tokens: number;
+  clock_tampered: boolean;
+}
+
+export type HabitKind = 'avoid' | 'do';
+
+export interface Habit {
+  id: number;
+  name: string;
+  kind: HabitKind;
+  created_at: string; // ISO datetime
+  reminder_time: string | null; // local HH:MM
+  is_default: boolean; // the main streak
+  archived_at: string | null; // ISO datetime
+}
+
+export interface HabitStatus extends Habit {
+  start_date: string | null; // ISO datetime for avoid habits, YYYY-MM-DD for do habits
+  current_days: number;
+  best_days: number;
+  done_today: boolean | null; // null for avoid habits
+  frozen_days: number;
+  freeze_tokens: number;
+}
+
+export interface HabitRun {
+  start_date: string;
+  end_date: string | null; // null while running
+  days: number; // clean days
+  reset_reason: string | null;
+  frozen_days: string[]; // YYYY-MM-DD
+}
+
+export interface NewHabit {
+  name: string;
+  kind: HabitKind;
+  reminder_time?: string | null; // HH:MM
+}
+
+export interface HabitChanges {
+  name: string;
+  reminder_time: string | null; // HH:MM
+}
+
+export interface GraceSettings {
+  enabled: boolean;
+  days_per_token: number;
+  max_tokens: number;
+}
+
+export type FreezeKind = "earned" | "forfeited" | "spent";
+
+export interface FreezeEntry {
+  id: number;
+  habit_id: number;
+  kind: FreezeKind;
+  recorded_at: string; // ISO datetime
+  milestone: number | null; // earned and forfeited
+  day: string | null; // spent, YYYY-MM-DD
+  note: string | null;
+}
+
+export interface StreakSettings {
+  day_starts_at: number; // local hour, 0-23
+}
+
+export interface StreakRecord {
+  id: number;
+  habit_id: number;
+  start_date: string; // ISO datetime
+  end_date: string | null; // ISO datetime, null while running
+  days: number | null;
+  reset_reason: string | null;
+}
+
+export interface StreakMilestone {
+  days: number;
+  at: string; // ISO datetime the streak reaches `days` clean days
+  reached: boolean;
+}
+
+/** Payload of the `streak-milestone` event. */
+export interface StreakMilestoneReached {
+  days: number;
+  at: string; // ISO datetime
+}
+
+export interface StreakAnalytics {
+  completed_streaks: number;
+  mean_days: number;
+  median_days: number;
+  histogram: { min_days: number; max_days: number | null; count: number }[];
+  longest_by_month: { month: string; days: number }[]; // month as YYYY-MM
+  trend: {
+    recent_days: number[]; // oldest first
+    slope: number; // days per streak
+    direction: 'improving' | 'declining' | 'flat';
+  };
+  current_days: number;
+  current_percentile: number | null; // 0-100
+  last_reset_at: string | null; // ISO datetime
+  seconds_since_last_reset: number | null;
+}
+
+export type RecoveryEntryKind = 'relapse' | 'urge';
+
+export interface RecoveryEntryInput {
+  occurred_at?: string; // ISO datetime, defaults to now
+  intensity: number; // 1-10
+  notes?: string;
+  triggers: string[];
+}
+
+export interface RecoveryEntry {
+  id: number;
+  kind: RecoveryEntryKind;
+  occurred_at: string; // ISO datetime
+  intensity: number;
+  notes: string | null;
+  triggers: string[];
+  streak_id: number | null;
+}
+
+export interface RecoveryEntryFilter {
+  triggers?: string[]; // match any
+  from?: string; // ISO datetime or YYYY-MM-DD, inclusive
+  to?: string; // ISO datetime or YYYY-MM-DD, inclusive
+}
+
+export interface TriggerCount {
+  tag: string;
+  relapses: number;
+  urges: number;
+}
+
+export interface RiskSettings {
+  enabled: boolean;
+  lead_minutes: number; // alert this long before a window starts
+  extend_lock: boolean; // keep the blocklist locked until the window ends
+  min_events: number; // fewest events an hour needs to be high-risk
+  lookback_days: number;
+}
+
+export interface RiskWindow {
+  weekday: number; // 0 = Monday ... 6 = Sunday
+  start_hour: number; // 0-23, local
+  hours: number;
+  events: number;
+  next_start: string; // ISO datetime
+  next_end: string; // ISO datetime
+}
+
+export interface RiskProfile {
+  resets: number;
+  urges: number;
+  grid: number[][]; // [weekday][hour], Monday first
+  threshold: number;
+  windows: RiskWindow[]; // soonest first
+}
+
+/** The evening check-in, one per day. */
+export interface CheckIn {
+  date: string; // YYYY-MM-DD
+  mood: number; // 1 (terrible) to 5 (great)
+  energy: number | null; // 1-5
+  sleep_hours: number | null;
+  urge_level: number | null; // 0 (none) to 10
+  note: string | null;
+  recorded_at: string; // ISO datetime
+}
+
+export interface CheckInInput {
+  date?: string | null; // YYYY-MM-DD, defaults to today
+  mood: number;
+  energy?: number | null;
+  sleep_hours?: number | null;
+  urge_level?: number | null;
+  note?: string | null; // one line
+}
+
+export interface CheckInSettings {
+  enabled: boolean;
+  prompt_time: string; // HH:MM, local
+}
+
 export interface BlockCategory {
   id: string;
   name: string;
@@ -38,6 +230,189 @@ export interface JournalEntry {
   mood: Mood;
   createdAt: string;
   updatedAt: string;
+  /** The template the entry was written with, if any, and its answers by field key. */
+  templateId?: string | null;
+  fields?: JournalFields;
+}
+
+/** A journal entry as sent to the backend. */
+export interface JournalInput {
+  title: string;
+  content: string;
+  mood: Mood | null;
+  templateId?: string | null;
+  fields?: JournalFields;
+}
+
+export type JournalFields = Record<string, string | number | boolean | string[]>;
+
+export type JournalFieldType = 'text' | 'long_text' | 'number' | 'choice' | 'boolean';
+
+export interface JournalTemplateField {
+  key: string;
+  label: string;
+  type: JournalFieldType;
+  required?: boolean;
+  help?: string;
+  /** number fields only; inclusive bounds */
+  min?: number;
+  max?: number;
+  unit?: string;
+  /** choice fields only */
+  options?: string[];
+  multiple?: boolean;
+}
+
+/** A structured exercise such as a CBT thought record. */
+export interface JournalTemplate {
+  id: string; // lowercase letters, digits and underscores
+  name: string;
+  description: string;
+  fields: JournalTemplateField[];
+  builtin: boolean; // shipped with the app; can't be changed or deleted
+}
+
+export interface JournalFieldAggregateQuery {
+  template_id: string;
+  field: string;
+  period?: 'day' | 'week' | 'month' | 'all'; // default month
+  from?: string | null; // YYYY-MM-DD, inclusive
+  to?: string | null; // YYYY-MM-DD, inclusive
+}
+
+export interface JournalFieldBucket {
+  period: string; // 2024-06-01, 2024-W22, 2024-06 or "all"
+  entries: number;
+  /** number fields */
+  average: number | null;
+  min: number | null;
+  max: number | null;
+  /** choice and boolean fields: times each answer was given */
+  counts: Record<string, number>;
+}
+
+/** An earlier version of a journal entry. */
+export interface JournalRevision {
+  id: number;
+  entry_id: number;
+  title: string;
+  content: string;
+  mood: Mood | null;
+  saved_at: string; // ISO datetime
+  template_id: string | null;
+  fields: JournalFields;
+}
+
+export interface JournalDiffLine {
+  change: 'same' | 'added' | 'removed';
+  text: string;
+}
+
+export interface JournalRevisionDiff {
+  title_before: string;
+  title_after: string;
+  mood_before: Mood | null;
+  mood_after: Mood | null;
+  fields_before: JournalFields;
+  fields_after: JournalFields;
+  lines: JournalDiffLine[];
+}
+
+export interface MoodAnalyticsQuery {
+  from?: string | null; // YYYY-MM-DD, inclusive
+  to?: string | null; // YYYY-MM-DD, inclusive
+  window_days?: number; // rolling average, default 7
+  lookback_days?: number; // days before an event, default 3
+}
+
+export interface MoodPoint {
+  day: string; // YYYY-MM-DD
+  average: number; // 1 (terrible) to 5 (great)
+  entries: number;
+  rolling_average: number;
+}
+
+export interface MoodMarker {
+  day: string; // YYYY-MM-DD
+  kind: 'reset' | 'urge' | 'relapse';
+}
+
+/** Moods recorded in the lookback before one kind of event. */
+export interface PrecedingMood {
+  events: number;
+  with_mood: number;
+  average: number | null;
+  difference: number | null; // vs. baseline
+}
+
+export interface MoodOutcomes {
+  mood: Mood;
+  urges: number;
+  relapses: number;
+  relapse_share: number | null; // 0-1
+}
+
+export interface MoodAnalytics {
+  window_days: number;
+  lookback_days: number;
+  baseline: number | null;
+  series: MoodPoint[];
+  markers: MoodMarker[];
+  before_resets: PrecedingMood;
+  before_urges: PrecedingMood;
+  before_relapses: PrecedingMood;
+  /** Negative when low moods come before resets. */
+  reset_correlation: number | null;
+  by_mood: MoodOutcomes[];
+}
+
+/** A full-text journal search. `text` takes words, "quoted phrases" and prefix* terms. */
+export interface JournalSearchQuery {
+  text: string;
+  mood?: Mood | null;
+  from?: string | null; // YYYY-MM-DD, inclusive
+  to?: string | null; // YYYY-MM-DD, inclusive
+  limit?: number;
+}
+
+export interface JournalSearchHit {
+  id: number;
+  title: string;
+  /** Plain entry text with matches wrapped in <mark></mark>; escape the rest before rendering. */
+  snippet: string;
+  mood: Mood | null;
+  created_at: string; // ISO datetime
+  rank: number; // lower is better
+}
+
+/** markdown writes a folder of files; the others write one file. */
+export type JournalExportFormat = 'markdown' | 'markdown_zip' | 'json' | 'html';
+
+/** Which entries to export, like the filters of `JournalSearchQuery`. */
+export interface JournalExportFilter {
+  mood?: Mood | null;
+  from?: string | null; // YYYY-MM-DD, inclusive
+  to?: string | null; // YYYY-MM-DD, inclusive
+}
+
+export interface JournalExportSummary {
+  path: string;
+  entries: number;
+}
+
+export interface JournalImportSummary {
+  imported: number;
+  /** Entries already in the journal, matched on when they were written. */
+  duplicates: number;
+  /** "file: reason" for every file that could not be imported. */
+  skipped: string[];
+}
+
+/** Whether the journal is encrypted and readable right now. */
+export interface JournalLockStatus {
+  encrypted: boolean;
+  unlocked: boolean;
+  lock_after_minutes: number;
 }
 
 export interface Achievement {
@@ -51,6 +426,39 @@ export interface Achievement {
   progress: number;
 }
 
+/** An achievement as tracked by the backend engine. */
+export interface AchievementStatus {
+  id: string;
+  name: string;
+  description: string;
+  category: Achievement["category"];
+  requirement: number;
+  progress: number;
+  unlocked_at: string | null; // ISO datetime
+}
+
+/** One achievement before and after `recompute_achievements`. */
+export interface AchievementChange {
+  id: string;
+  name: string;
+  progress_before: number;
+  progress_after: number;
+  unlocked_at_before: string | null; // ISO datetime
+  unlocked_at_after: string | null; // ISO datetime
+}
+
+/** What replaying the saved history changes, by kind of change. */
+export interface RecomputeDiff {
+  unlocked: AchievementChange[];
+  revoked: AchievementChange[];
+  /** Unlocked before they could have been earned; moved to when they were. */
+  retimed: AchievementChange[];
+  progress: AchievementChange[];
+  /** Pass back to `recompute_achievements` to save exactly these changes. */
+  fingerprint: string;
+  applied: boolean;
+}
+
 export interface Quote {
   id: number;
   text: string;
@@ -96,3 +504,118 @@ export interface InstalledApp {
   appName: string;
   iconBase64: string;
 }
+
+// ─── Desktop blocker types ────────────────────────────────────────────────
+
+export interface DnsFlushResult {
+  flushed: boolean;
+  method: string | null; // e.g. "ipconfig /flushdns"
+  error: string | null;
+}
+
+export interface ApplyResult {
+  domainsApplied: number;
+  dnsFlush: DnsFlushResult;
+}
+
+export interface SafeSearchEndpoint {
+  engine: string;
+  target: string; // e.g. "forcesafesearch.google.com"
+  domains: string[];
+}
+
+export interface SafeSearchStatus {
+  enabled: boolean;
+  enforced: boolean;
+  engines: string[];
+}
+
+export type LockScope = 'blocklist' | 'lockdown';
+
+export interface LockStatus {
+  scope: LockScope;
+  isLocked: boolean;
+  expiresAt: string | null; // ISO datetime, in credited time
+  clockTampered: boolean;
+}
+
+// Also the payload of the clock-jump-detected event
+export interface ClockJump {
+  detectedAt: string; // ISO datetime
+  kind: 'forward' | 'backward';
+  seconds: number;
+  acrossRestart: boolean;
+}
+
+export interface ClockStatus {
+  tampered: boolean;
+  offsetSeconds: number; // wall clock minus credited time
+  jumps: ClockJump[]; // oldest first
+}
+
+export interface DeniedQuery {
+  domain: string;
+  at: string; // ISO datetime
+}
+
+export interface LockdownStatus {
+  active: boolean;
+  allowlist: string[];
+  expiresAt: string | null; // ISO datetime
+  deniedCount: number;
+  recentDenied: DeniedQuery[];
+}
+
+export interface CategoryBudget {
+  category: string;
+  minutesPerDay: number;
+}
+
+export interface BudgetSettings {
+  dayStartsAt: number; // local hour, 0-23
+  budgets: CategoryBudget[];
+}
+
+export interface BudgetStatus {
+  category: string;
+  minutesPerDay: number;
+  usedSeconds: number;
+  remainingSeconds: number;
+  exhausted: boolean;
+  inSession: boolean;
+  resetsAt: string; // ISO datetime
+}
+
+export type EscalationProfile = 'allCategories' | 'lockdown';
+
+export interface PanicEscalationSettings {
+  enabled: boolean;
+  hours: number;
+  profile: EscalationProfile;
+  lockdownAllowlist: string[];
+}
+
+export interface EscalationStatus {
+  profile: EscalationProfile;
+  startedAt: string; // ISO datetime
+  until: string; // ISO datetime
+}
+
+export interface ResetLockSettings {
+  enabled: boolean;
+  categories: string[]; // catalog category ids
+  hours: number;
+}
+
+// Also the payload of the reset-lock-started and reset-lock-ended events
+export interface ResetLockStatus {
+  categories: string[];
+  startedAt: string; // ISO datetime
+  until: string; // ISO datetime
+}
+
+export interface PanicResult {
+  logged_at: string; // ISO datetime
+  escalation: EscalationStatus | null;
+  escalation_error: string | null;
+}